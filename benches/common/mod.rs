@@ -0,0 +1,37 @@
+// common/mod.rs — Shared Benchmark and Test Helpers
+//
+// Reference implementations and sample generators used by the benches and
+// by tests/conformance_tests.rs (through `#[path]`), so that none of them is
+// exported from the library. Each user only needs part of it.
+
+#![allow(dead_code)]
+
+/// The original threshold-chain implementation of `demo_partition_map`,
+/// kept as the reference its bucketed fast path is tested and benchmarked
+/// against.
+pub fn reference_partition_map(risk_score: f64) -> Option<&'static str> {
+    if !(0.0..=1.0).contains(&risk_score) {
+        return None;
+    }
+    Some(match () {
+        _ if risk_score < 0.2 => "Optimal",
+        _ if risk_score < 0.4 => "Normal",
+        _ if risk_score < 0.6 => "Caution",
+        _ if risk_score < 0.8 => "Alert",
+        _ => "Halt",
+    })
+}
+
+/// `count` scores in [0, 1) from a fixed xorshift sequence. The scrambled
+/// order defeats branch prediction, as real score streams do.
+pub fn scrambled_scores(count: usize) -> Vec<f64> {
+    let mut state = 0x9e37_79b9_7f4a_7c15_u64;
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        })
+        .collect()
+}
@@ -0,0 +1,62 @@
+// partition_lookup.rs — PartitionMap Lookup Benchmark
+//
+// Compares a linear scan over the intervals with the binary search of
+// `PartitionMap::map` (which `classify` uses) on maps of 5, 20 and 100
+// equal-width intervals, after checking that both agree on every sample.
+// Run with `cargo bench --bench partition_lookup`.
+
+mod common;
+
+use common::scrambled_scores;
+use pmatrix_encoder::mode::{PartitionInterval, PartitionMap};
+use pmatrix_encoder::schema::MODES;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+// `n` equal intervals; the modes advance from Optimal to Halt across them.
+fn equal_width_map(n: usize) -> PartitionMap {
+    let intervals = (0..n)
+        .map(|i| PartitionInterval {
+            mode: MODES[i * MODES.len() / n],
+            lower: i as f64 / n as f64,
+            upper: (i + 1) as f64 / n as f64,
+            upper_closed: i == n - 1,
+        })
+        .collect();
+    PartitionMap::new(intervals).expect("equal intervals tile [0, 1]")
+}
+
+// The first interval containing `x`, found by scanning every interval.
+fn linear_scan(map: &PartitionMap, x: f64) -> Option<&'static str> {
+    map.intervals
+        .iter()
+        .find(|i| i.lower <= x && (x < i.upper || (i.upper_closed && x == i.upper)))
+        .map(|i| i.mode)
+}
+
+fn time(name: &str, samples: &[f64], f: impl Fn(f64) -> Option<&'static str>) {
+    let rounds = 20;
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for &x in samples {
+            black_box(f(black_box(x)));
+        }
+    }
+    let per_call = start.elapsed().as_nanos() as f64 / (rounds * samples.len()) as f64;
+    println!("  {:<14} {:>8.3} ns/call", name, per_call);
+}
+
+fn main() {
+    let samples = scrambled_scores(1_000_000);
+
+    for n in [5, 20, 100] {
+        let map = equal_width_map(n);
+        for &x in &samples {
+            assert_eq!(map.map(x), linear_scan(&map, x), "n={} risk_score={}", n, x);
+        }
+        println!("{} intervals:", n);
+        time("linear scan", &samples, |x| linear_scan(&map, x));
+        time("binary search", &samples, |x| map.map(x));
+    }
+}
@@ -0,0 +1,41 @@
+// partition_map.rs — demo_partition_map Benchmark
+//
+// Compares the bucketed `demo_partition_map` with the threshold-chain
+// reference it replaced, after checking that both agree on every sample.
+// Run with `cargo bench --bench partition_map`.
+
+mod common;
+
+use common::{reference_partition_map, scrambled_scores};
+use pmatrix_encoder::mode::demo_partition_map;
+
+use std::hint::black_box;
+use std::time::Instant;
+
+fn time(name: &str, samples: &[f64], f: fn(f64) -> Option<&'static str>) {
+    let rounds = 20;
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for &x in samples {
+            black_box(f(black_box(x)));
+        }
+    }
+    let per_call = start.elapsed().as_nanos() as f64 / (rounds * samples.len()) as f64;
+    println!("{:<10} {:>8.3} ns/call", name, per_call);
+}
+
+fn main() {
+    let n = 1_000_000;
+    let sweep: Vec<f64> = (0..=n).map(|k| k as f64 / n as f64).collect();
+    for &x in &sweep {
+        assert_eq!(demo_partition_map(x), reference_partition_map(x), "risk_score={}", x);
+    }
+
+    let scrambled = scrambled_scores(n + 1);
+
+    for (order, samples) in [("sorted", &sweep), ("scrambled", &scrambled)] {
+        println!("{} input:", order);
+        time("reference", samples, reference_partition_map);
+        time("fast path", samples, demo_partition_map);
+    }
+}
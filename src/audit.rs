@@ -0,0 +1,50 @@
+// audit.rs — Validation Audit Log
+//
+// Appends one JSON line per validated record to an audit sink, so that the
+// conformance history of a pipeline can be queried later without
+// re-validating. Each line is flushed as it is written; a crash can lose at
+// most the line being written, never an earlier one.
+
+use crate::error::PmatrixError;
+use crate::fingerprint::record_fingerprint;
+use crate::invariants::{violation_bitmask, InvariantResult};
+use crate::schema::RuntimeStateRecord;
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// One line of the audit log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEvent {
+    /// Fingerprint of the validated record.
+    pub fingerprint: String,
+    /// The record's own timestamp.
+    pub timestamp: u64,
+    pub conforming: bool,
+    /// Failed invariants, as encoded by `violation_bitmask`.
+    pub violation_bitmask: u32,
+}
+
+/// Builds the audit event for a record and its validation results.
+pub fn audit_event(record: &RuntimeStateRecord, results: &[InvariantResult]) -> AuditEvent {
+    let violation_bitmask = violation_bitmask(results);
+    AuditEvent {
+        fingerprint: record_fingerprint(record),
+        timestamp: record.timestamp,
+        conforming: violation_bitmask == 0,
+        violation_bitmask,
+    }
+}
+
+/// Appends the audit event for a record as one JSON line and flushes `w`.
+pub fn append_audit_event<W: Write>(
+    w: &mut W,
+    record: &RuntimeStateRecord,
+    results: &[InvariantResult],
+) -> Result<(), PmatrixError> {
+    let mut line = serde_json::to_vec(&audit_event(record, results))?;
+    line.push(b'\n');
+    w.write_all(&line)?;
+    w.flush()?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+// clock.rs — Time Sources
+//
+// Supplies the default timestamp for records emitted without one.
+// `SystemClock` reads the wall clock; `FixedClock` always returns the same
+// value, which keeps time-dependent behavior deterministic in tests.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A source of Unix timestamps in seconds.
+pub trait Clock {
+    fn now_secs(&self) -> u64;
+}
+
+/// The system wall clock. A clock set before the Unix epoch reads as 0.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_secs(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+}
+
+/// A clock that always reports the same timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub u64);
+
+impl Clock for FixedClock {
+    fn now_secs(&self) -> u64 {
+        self.0
+    }
+}
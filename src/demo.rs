@@ -6,7 +6,9 @@
 // Production implementations use proprietary evaluation pipelines that are
 // fundamentally different from this demonstration logic.
 
-use crate::schema::Functions;
+use crate::error::PmatrixError;
+use crate::mode::mode_interval;
+use crate::schema::{Functions, RuntimeStateRecord, MODES};
 
 /// WARNING:
 /// This implementation is for schema conformance demonstration only.
@@ -16,8 +18,11 @@ use crate::schema::Functions;
 ///
 /// Computes a demonstration stability_score as a simple arithmetic mean
 /// of the four evaluation function values.
+///
+/// The result is clamped into [0.0, 1.0] so that float rounding can never
+/// produce a score that fails INV-R2.
 pub fn demo_stability_score(f: &Functions) -> f64 {
-    (f.baseline + f.norm + f.stability + f.meta_control) / 4.0
+    f.mean().clamp(0.0, 1.0)
 }
 
 /// WARNING:
@@ -27,6 +32,208 @@ pub fn demo_stability_score(f: &Functions) -> f64 {
 /// This function exists solely to populate required fields for schema conformance.
 ///
 /// Computes a demonstration risk_score as the complement of stability_score.
+///
+/// The result is clamped into [0.0, 1.0] so that it always satisfies INV-R3.
 pub fn demo_risk_score(stability_score: f64) -> f64 {
-    1.0 - stability_score
+    (1.0 - stability_score).clamp(0.0, 1.0)
+}
+
+/// WARNING:
+/// Every aggregator is for schema conformance demonstration only. None of
+/// them reflects any production, kernel, or normative logic of P-MATRIX.
+///
+/// A strategy for combining the four function values into a stability_score.
+/// Implementations must return a value in [0.0, 1.0] for functions in
+/// [0.0, 1.0].
+pub trait ScoreAggregator {
+    /// The name the aggregator is registered under in `AGGREGATORS`.
+    fn name(&self) -> &'static str;
+
+    /// Combines the functions into a stability_score.
+    fn stability_score(&self, f: &Functions) -> f64;
+}
+
+/// The arithmetic mean, as computed by `demo_stability_score`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MeanAggregator;
+
+impl ScoreAggregator for MeanAggregator {
+    fn name(&self) -> &'static str {
+        "mean"
+    }
+
+    fn stability_score(&self, f: &Functions) -> f64 {
+        demo_stability_score(f)
+    }
+}
+
+/// A weighted mean, with weights in schema order
+/// (baseline, norm, stability, meta_control).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeightedAggregator {
+    weights: [f64; 4],
+}
+
+impl WeightedAggregator {
+    /// Fails with `PmatrixError::Config` unless every weight is finite and
+    /// non-negative and at least one is positive.
+    pub fn new(weights: [f64; 4]) -> Result<Self, PmatrixError> {
+        if weights.iter().any(|w| !w.is_finite() || *w < 0.0) {
+            return Err(PmatrixError::Config(format!("weights {:?} must be finite and non-negative", weights)));
+        }
+        if weights.iter().sum::<f64>() <= 0.0 {
+            return Err(PmatrixError::Config("at least one weight must be positive".to_string()));
+        }
+        Ok(WeightedAggregator { weights })
+    }
+}
+
+impl ScoreAggregator for WeightedAggregator {
+    fn name(&self) -> &'static str {
+        "weighted"
+    }
+
+    fn stability_score(&self, f: &Functions) -> f64 {
+        let values: [f64; 4] = f.clone().into();
+        let total: f64 = self.weights.iter().sum();
+        let weighted: f64 = values.iter().zip(&self.weights).map(|(v, w)| v * w).sum();
+        (weighted / total).clamp(0.0, 1.0)
+    }
+}
+
+/// The lowest function: the pessimistic choice, where the worst function
+/// alone drives risk.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MinAggregator;
+
+impl ScoreAggregator for MinAggregator {
+    fn name(&self) -> &'static str {
+        "min"
+    }
+
+    fn stability_score(&self, f: &Functions) -> f64 {
+        f.min().clamp(0.0, 1.0)
+    }
+}
+
+/// The geometric mean, which any single function at 0.0 pulls to 0.0.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct GeometricAggregator;
+
+impl ScoreAggregator for GeometricAggregator {
+    fn name(&self) -> &'static str {
+        "geometric"
+    }
+
+    fn stability_score(&self, f: &Functions) -> f64 {
+        let values: [f64; 4] = f.clone().into();
+        values.iter().product::<f64>().powf(0.25).clamp(0.0, 1.0)
+    }
+}
+
+/// The built-in aggregators with a short description, default first.
+pub const AGGREGATORS: [(&str, &str); 4] = [
+    ("mean", "arithmetic mean of the four functions (default)"),
+    ("weighted", "weighted mean; requires four weights"),
+    ("min", "the lowest function, so the worst one drives risk"),
+    ("geometric", "geometric mean; any function at 0 gives 0"),
+];
+
+/// Builds a registered aggregator by name.
+///
+/// `weights` is required by `weighted` and rejected by the others; it must
+/// hold exactly four values. Fails with `PmatrixError::Config` for an
+/// unknown name or invalid parameters.
+pub fn aggregator(name: &str, weights: Option<&[f64]>) -> Result<Box<dyn ScoreAggregator>, PmatrixError> {
+    if !AGGREGATORS.iter().any(|(n, _)| *n == name) {
+        let names: Vec<&str> = AGGREGATORS.iter().map(|(n, _)| *n).collect();
+        return Err(PmatrixError::Config(format!("unknown aggregator `{}`; expected one of {}", name, names.join(", "))));
+    }
+    match (name, weights) {
+        ("weighted", None) => Err(PmatrixError::Config("the weighted aggregator requires weights".to_string())),
+        ("weighted", Some(w)) => {
+            let w: [f64; 4] = w.try_into().map_err(|_| {
+                PmatrixError::Config(format!(
+                    "the weighted aggregator takes 4 weights (baseline, norm, stability, meta_control), got {}",
+                    w.len()
+                ))
+            })?;
+            Ok(Box::new(WeightedAggregator::new(w)?))
+        }
+        (_, Some(_)) => Err(PmatrixError::Config(format!("the {} aggregator takes no weights", name))),
+        ("min", None) => Ok(Box::new(MinAggregator)),
+        ("geometric", None) => Ok(Box::new(GeometricAggregator)),
+        _ => Ok(Box::new(MeanAggregator)),
+    }
+}
+
+/// WARNING:
+/// This implementation is for schema conformance demonstration only.
+/// It does NOT reflect any production, kernel, or normative logic of P-MATRIX.
+///
+/// Returns the name and value of the lowest function, which under the demo
+/// relationship `risk = 1 - mean(functions)` contributes most to risk. Ties
+/// go to the first field in schema order; NaN values are ignored unless all
+/// four are NaN.
+pub fn dominant_risk_factor(f: &Functions) -> (&'static str, f64) {
+    let named = [("baseline", f.baseline), ("norm", f.norm), ("stability", f.stability), ("meta_control", f.meta_control)];
+    let mut lowest = named[0];
+    for (name, v) in &named[1..] {
+        if *v < lowest.1 || (lowest.1.is_nan() && !v.is_nan()) {
+            lowest = (name, *v);
+        }
+    }
+    lowest
+}
+
+/// How far a record's functions must improve to reach a lower-risk mode.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RemediationHint {
+    /// The mode the hint targets.
+    pub target: &'static str,
+    /// The mean of the four functions must rise by more than this amount.
+    pub mean_increase: f64,
+    /// Adding more than this amount to every function (each capped at 1.0)
+    /// reaches the target. Equals `mean_increase` unless some function saturates.
+    pub per_function_increase: f64,
+}
+
+/// WARNING:
+/// This implementation is for schema conformance demonstration only.
+/// It does NOT reflect any production, kernel, or normative logic of P-MATRIX.
+///
+/// Computes the smallest posture improvement that moves a record into `target`
+/// or a lower-risk mode, using the demo relationship `risk = 1 - mean(functions)`.
+/// Because the upper boundary of each interval is exclusive, the increases are
+/// thresholds that must be strictly exceeded.
+///
+/// Returns None if `target` is unknown or the demo risk is already below the
+/// target's upper boundary.
+pub fn remediation_hint(record: &RuntimeStateRecord, target: &str) -> Option<RemediationHint> {
+    let (_, upper) = mode_interval(target)?;
+    let target = MODES.iter().copied().find(|m| *m == target)?;
+    let f = &record.functions;
+    let mut values = f.values();
+    let mean = demo_stability_score(f);
+    let required_mean = 1.0 - upper;
+    if mean > required_mean || (target == "Halt" && mean >= required_mean) {
+        return None;
+    }
+    let mean_increase = required_mean - mean;
+
+    // Uniform increase d with saturation: find how many of the largest values
+    // hit 1.0, then spread the remaining shortfall over the others.
+    values.sort_by(|a, b| b.total_cmp(a));
+    let needed_sum = 4.0 * required_mean;
+    let mut per_function_increase = mean_increase;
+    for saturated in 0..4 {
+        let rest = &values[saturated..];
+        let d = (needed_sum - saturated as f64 - rest.iter().sum::<f64>()) / rest.len() as f64;
+        if rest[0] + d <= 1.0 {
+            per_function_increase = d;
+            break;
+        }
+    }
+
+    Some(RemediationHint { target, mean_increase, per_function_increase })
 }
@@ -0,0 +1,252 @@
+// diff.rs — Record and Stream Comparison
+//
+// Compares runtime state records field by field, and aligns two streams so
+// that side-by-side emitters can be checked for divergence.
+
+use crate::schema::{spec_example_record, RuntimeStateRecord};
+
+use serde_json::Value;
+use std::fmt;
+
+/// A single field that differs between two records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldDiff {
+    pub field: &'static str,
+    pub left: String,
+    pub right: String,
+}
+
+/// Which input stream a record belongs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+}
+
+/// How records of two streams are paired before comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alignment {
+    /// Pair records by position.
+    Index,
+    /// Pair records with equal timestamps, in order of appearance.
+    Timestamp,
+}
+
+/// A divergence between two streams.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamDiff {
+    /// The first differing field of a paired record.
+    Field {
+        left_index: usize,
+        right_index: usize,
+        diff: FieldDiff,
+    },
+    /// The streams contain a different number of records.
+    LengthMismatch { left_len: usize, right_len: usize },
+    /// A record with no counterpart in the other stream (timestamp alignment).
+    Unmatched { side: Side, index: usize, timestamp: u64 },
+}
+
+/// A structural difference between a JSON document and a reference
+/// document: a field that is missing, unexpected, or of another JSON type.
+/// Paths are JSON Pointers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShapeDiff {
+    Missing { path: String, expected: &'static str },
+    Unexpected { path: String, found: &'static str },
+    WrongType { path: String, expected: &'static str, found: &'static str },
+}
+
+impl fmt::Display for ShapeDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShapeDiff::Missing { path, expected } => write!(f, "{} is missing (expected {})", path, expected),
+            ShapeDiff::Unexpected { path, found } => write!(f, "{} is not in the example (found {})", path, found),
+            ShapeDiff::WrongType { path, expected, found } => {
+                write!(f, "{} is {}, expected {}", path, found, expected)
+            }
+        }
+    }
+}
+
+// JSON type name; integers are told apart from other numbers.
+fn json_type(v: &Value) -> &'static str {
+    match v {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_u64() || n.is_i64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Compares the field presence and JSON types of `value` with `reference`,
+/// recursing into objects. Values are not compared, and an integer is
+/// accepted where the reference has a number.
+pub fn diff_shape(value: &Value, reference: &Value) -> Vec<ShapeDiff> {
+    let mut diffs = Vec::new();
+    shape_at("", value, reference, &mut diffs);
+    diffs
+}
+
+fn shape_at(path: &str, value: &Value, reference: &Value, diffs: &mut Vec<ShapeDiff>) {
+    let (found, expected) = (json_type(value), json_type(reference));
+    match (value, reference) {
+        (Value::Object(v), Value::Object(r)) => {
+            for (key, rv) in r {
+                let sub = format!("{}/{}", path, key);
+                match v.get(key) {
+                    Some(vv) => shape_at(&sub, vv, rv, diffs),
+                    None => diffs.push(ShapeDiff::Missing { path: sub, expected: json_type(rv) }),
+                }
+            }
+            for (key, vv) in v.iter().filter(|(k, _)| !r.contains_key(*k)) {
+                diffs.push(ShapeDiff::Unexpected { path: format!("{}/{}", path, key), found: json_type(vv) });
+            }
+        }
+        _ if found == expected || (found == "integer" && expected == "number") => {}
+        _ => diffs.push(ShapeDiff::WrongType {
+            path: if path.is_empty() { "/".to_string() } else { path.to_string() },
+            expected,
+            found,
+        }),
+    }
+}
+
+/// Compares the structure of `value` with `spec_example_record()`, as
+/// `diff_shape` does. The optional `sequence_number` is accepted as an
+/// integer although the example omits it.
+pub fn diff_shape_against_example(value: &Value) -> Vec<ShapeDiff> {
+    let mut example = serde_json::to_value(spec_example_record()).expect("records serialize");
+    if let (Some(_), Value::Object(map)) = (value.get("sequence_number"), &mut example) {
+        map.insert("sequence_number".to_string(), Value::from(0u64));
+    }
+    diff_shape(value, &example)
+}
+
+// `a == b` first: equal infinities differ by NaN.
+fn floats_match(a: f64, b: f64, eps: f64) -> bool {
+    a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() <= eps
+}
+
+/// Returns true if two records are equal up to `eps` in their floating-point
+/// fields, with the same rules as `diff_records`: two NaNs are equal, and all
+/// other fields must match exactly.
+///
+/// This is an explicit opt-in for comparing the output of float-producing
+/// pipelines in downstream tests; it only mirrors `diff_records`, which the
+/// diff subcommands call directly. `==` on records remains exact, except that
+/// `-0.0` equals `0.0` and any NaN equals any NaN.
+pub fn records_approx_eq(a: &RuntimeStateRecord, b: &RuntimeStateRecord, eps: f64) -> bool {
+    diff_records(a, b, eps).is_empty()
+}
+
+/// Compares two records field by field, in schema order.
+///
+/// Floating-point fields are equal when they differ by at most `eps`
+/// (two NaNs are treated as equal). All other fields are compared exactly.
+pub fn diff_records(a: &RuntimeStateRecord, b: &RuntimeStateRecord, eps: f64) -> Vec<FieldDiff> {
+    let mut diffs = Vec::new();
+
+    let mut text = |field: &'static str, l: &str, r: &str| {
+        if l != r {
+            diffs.push(FieldDiff { field, left: l.to_string(), right: r.to_string() });
+        }
+    };
+    text("spec_version", &a.spec_version, &b.spec_version);
+    text("schema_version", &a.schema_version, &b.schema_version);
+    text("timestamp", &a.timestamp.to_string(), &b.timestamp.to_string());
+    let seq = |r: &RuntimeStateRecord| r.sequence_number.map_or("(absent)".to_string(), |n| n.to_string());
+    text("sequence_number", &seq(a), &seq(b));
+
+    let floats = [
+        ("functions.baseline", a.functions.baseline, b.functions.baseline),
+        ("functions.norm", a.functions.norm, b.functions.norm),
+        ("functions.stability", a.functions.stability, b.functions.stability),
+        ("functions.meta_control", a.functions.meta_control, b.functions.meta_control),
+        ("stability_score", a.stability_score, b.stability_score),
+        ("risk_score", a.risk_score, b.risk_score),
+    ];
+    for (field, l, r) in floats {
+        if !floats_match(l, r, eps) {
+            diffs.push(FieldDiff { field, left: l.to_string(), right: r.to_string() });
+        }
+    }
+
+    if a.mode != b.mode {
+        diffs.push(FieldDiff { field: "mode", left: a.mode.clone(), right: b.mode.clone() });
+    }
+    if a.risk_level != b.risk_level {
+        diffs.push(FieldDiff {
+            field: "risk_level",
+            left: a.risk_level.clone(),
+            right: b.risk_level.clone(),
+        });
+    }
+    diffs
+}
+
+/// Compares two streams position by position.
+///
+/// Reports the first differing field for each position, followed by a
+/// length mismatch if the streams have different lengths.
+pub fn diff_streams(a: &[RuntimeStateRecord], b: &[RuntimeStateRecord], eps: f64) -> Vec<StreamDiff> {
+    diff_streams_aligned(a, b, eps, Alignment::Index)
+}
+
+/// Compares two streams using the given alignment.
+///
+/// With `Alignment::Timestamp` both streams are expected to satisfy INV-T1;
+/// records sharing a timestamp are paired in order of appearance and any
+/// record without a counterpart is reported as `Unmatched`.
+pub fn diff_streams_aligned(
+    a: &[RuntimeStateRecord],
+    b: &[RuntimeStateRecord],
+    eps: f64,
+    alignment: Alignment,
+) -> Vec<StreamDiff> {
+    let mut out = Vec::new();
+    let compare = |i: usize, j: usize, out: &mut Vec<StreamDiff>| {
+        if let Some(diff) = diff_records(&a[i], &b[j], eps).into_iter().next() {
+            out.push(StreamDiff::Field { left_index: i, right_index: j, diff });
+        }
+    };
+
+    match alignment {
+        Alignment::Index => {
+            for i in 0..a.len().min(b.len()) {
+                compare(i, i, &mut out);
+            }
+        }
+        Alignment::Timestamp => {
+            let (mut i, mut j) = (0, 0);
+            while i < a.len() && j < b.len() {
+                let (ta, tb) = (a[i].timestamp, b[j].timestamp);
+                if ta == tb {
+                    compare(i, j, &mut out);
+                    i += 1;
+                    j += 1;
+                } else if ta < tb {
+                    out.push(StreamDiff::Unmatched { side: Side::Left, index: i, timestamp: ta });
+                    i += 1;
+                } else {
+                    out.push(StreamDiff::Unmatched { side: Side::Right, index: j, timestamp: tb });
+                    j += 1;
+                }
+            }
+            for (index, r) in a.iter().enumerate().skip(i) {
+                out.push(StreamDiff::Unmatched { side: Side::Left, index, timestamp: r.timestamp });
+            }
+            for (index, r) in b.iter().enumerate().skip(j) {
+                out.push(StreamDiff::Unmatched { side: Side::Right, index, timestamp: r.timestamp });
+            }
+        }
+    }
+
+    if a.len() != b.len() {
+        out.push(StreamDiff::LengthMismatch { left_len: a.len(), right_len: b.len() });
+    }
+    out
+}
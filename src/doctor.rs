@@ -0,0 +1,350 @@
+// doctor.rs — Diagnosis of Malformed Records
+//
+// Turns serde and invariant errors into a prioritized list of likely
+// problems, each with a hint, for people writing their first emitter.
+// Diagnosis degrades step by step: input that is not JSON gets one syntax
+// finding; an object with missing, mistyped or unknown fields gets a finding
+// for each, plus checks of the fields that are usable on their own; and a
+// record that parses gets one finding per failed invariant.
+
+use crate::input::{clean_input, FUNCTION_FIELDS};
+use crate::invariants::{explain_consistency_failure, validate_all, ErrorCode};
+use crate::schema::{RuntimeStateRecord, META_CONTROL_ALIASES, MODES, RISK_LEVELS, SCHEMA_VERSION, SEQUENCE_SCHEMA_VERSION, SPEC_VERSION};
+
+use serde_json::{Map, Value};
+
+/// One likely problem with a record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    /// What is wrong, naming the field involved.
+    pub problem: String,
+    /// What to change to fix it.
+    pub hint: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Text,
+    Integer,
+    Number,
+    Functions,
+}
+
+/// The eight required fields, in schema order, with their JSON types.
+const REQUIRED_FIELDS: [(&str, Kind); 8] = [
+    ("spec_version", Kind::Text),
+    ("schema_version", Kind::Text),
+    ("timestamp", Kind::Integer),
+    ("functions", Kind::Functions),
+    ("stability_score", Kind::Number),
+    ("risk_score", Kind::Number),
+    ("mode", Kind::Text),
+    ("risk_level", Kind::Text),
+];
+
+/// Diagnoses a single record given as JSON text. Findings come most
+/// fundamental first: syntax, then missing, mistyped and unknown fields, then
+/// versions, then out-of-range values, then mode and risk_level consistency,
+/// since later problems often follow from earlier ones. An empty list means
+/// the record conforms.
+pub fn diagnose(input: &str) -> Vec<Finding> {
+    let input = clean_input(input);
+    if input.is_empty() {
+        return vec![finding("the input is empty", "Pass a single JSON record object, such as the output of `emit`.")];
+    }
+    match serde_json::from_str::<Value>(&input) {
+        Ok(value) => diagnose_value(&value),
+        Err(e) => vec![syntax_finding(&input, &e)],
+    }
+}
+
+/// Like `diagnose`, for an already parsed JSON value. Of an array, only the
+/// first element is diagnosed.
+pub fn diagnose_value(value: &Value) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let object = match value {
+        Value::Object(object) => object,
+        Value::Array(items) => {
+            findings.push(finding(
+                format!("the input is an array of {} value(s), not a single record", items.len()),
+                "Only the first element is diagnosed here; check a whole stream with `validate --batch`.",
+            ));
+            match items.first() {
+                Some(Value::Object(object)) => object,
+                Some(other) => {
+                    findings.push(not_an_object(other));
+                    return findings;
+                }
+                None => return findings,
+            }
+        }
+        other => return vec![not_an_object(other)],
+    };
+
+    let object = &with_canonical_names(object);
+    let known = structural_findings(object, &mut findings);
+    match serde_json::from_value::<RuntimeStateRecord>(Value::Object(known)) {
+        Ok(record) => findings.extend(invariant_findings(&record)),
+        Err(_) => findings.extend(partial_findings(object)),
+    }
+    findings
+}
+
+fn finding(problem: impl Into<String>, hint: impl Into<String>) -> Finding {
+    Finding { problem: problem.into(), hint: hint.into() }
+}
+
+fn not_an_object(value: &Value) -> Finding {
+    finding(
+        format!("the input is {}, not a record object", describe(value)),
+        "A record is a JSON object with the eight required fields; see the output of `emit`.",
+    )
+}
+
+fn syntax_finding(input: &str, e: &serde_json::Error) -> Finding {
+    let msg = e.to_string();
+    let hint = if msg.contains("trailing characters") {
+        "More input follows the first value. Diagnose one record at a time, or check a stream with `validate --batch`."
+    } else if msg.contains("trailing comma") {
+        "Remove the comma after the last field or element."
+    } else if e.is_eof() {
+        "The record is cut short; check that every `{`, `[` and `\"` is closed."
+    } else if input.contains("NaN") || input.contains("Infinity") {
+        "JSON has no NaN or Infinity; every score must be a finite number in [0.0, 1.0]."
+    } else if input.contains('\'') {
+        "JSON strings and keys need double quotes, not single quotes."
+    } else {
+        "Fix the JSON syntax at the position shown."
+    };
+    finding(format!("the input is not valid JSON: {}", msg), hint)
+}
+
+// Describes a JSON value for a message, showing scalars in full.
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Null => "null".to_string(),
+        Value::Bool(b) => format!("the boolean {}", b),
+        Value::Number(n) => format!("the number {}", n),
+        Value::String(s) => format!("the string {:?}", s),
+        Value::Array(_) => "an array".to_string(),
+        Value::Object(_) => "an object".to_string(),
+    }
+}
+
+// What a field should hold, for hints.
+fn expected(field: &str) -> String {
+    match field {
+        "spec_version" => format!("the string \"{}\"", SPEC_VERSION),
+        "schema_version" => format!("a MAJOR.MINOR.PATCH string such as \"{}\"", SCHEMA_VERSION),
+        "timestamp" => "an integer number of Unix seconds, such as 1707500000".to_string(),
+        "functions" => format!("an object with {}", FUNCTION_FIELDS.join(", ")),
+        "sequence_number" => "a non-negative integer".to_string(),
+        "mode" => format!("one of {}", MODES.join(", ")),
+        "risk_level" => format!("one of {}", RISK_LEVELS.join(", ")),
+        _ => "a number in [0.0, 1.0]".to_string(),
+    }
+}
+
+fn has_kind(value: &Value, kind: Kind) -> bool {
+    match kind {
+        Kind::Text => value.is_string(),
+        Kind::Integer => value.is_u64(),
+        Kind::Number => value.is_number(),
+        Kind::Functions => value.is_object(),
+    }
+}
+
+// Lowercase alphanumerics only, so `riskScore` and `Risk-Score` both match
+// `risk_score`.
+fn normalized(name: &str) -> String {
+    name.chars().filter(char::is_ascii_alphanumeric).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+// Renames a `meta_control` alias that the codec accepts to the canonical
+// name, so it is neither missing nor unknown. With both spellings present
+// the alias is left alone and reported as unknown.
+fn with_canonical_names(object: &Map<String, Value>) -> Map<String, Value> {
+    let mut object = object.clone();
+    if let Some(Value::Object(functions)) = object.get_mut("functions") {
+        if !functions.contains_key("meta_control") {
+            if let Some(alias) = META_CONTROL_ALIASES.iter().find(|a| functions.contains_key(**a)) {
+                let value = functions.remove(*alias).expect("alias is present");
+                functions.insert("meta_control".to_string(), value);
+            }
+        }
+    }
+    object
+}
+
+// Checks one object's fields against `fields`, pushing findings for missing,
+// misnamed, mistyped and unknown fields, and returns the known fields.
+// `prefix` is prepended to names, e.g. "functions.".
+fn check_fields(
+    object: &Map<String, Value>,
+    fields: &[(&str, Kind)],
+    prefix: &str,
+    findings: &mut Vec<Finding>,
+) -> Map<String, Value> {
+    let mut unknown: Vec<&String> = object.keys().filter(|k| !fields.iter().any(|(f, _)| f == k)).collect();
+    let mut missing = Vec::new();
+    let mut mistyped = Vec::new();
+    for &(name, kind) in fields {
+        match object.get(name) {
+            // sequence_number is the one optional field.
+            None if name == "sequence_number" => {}
+            None => match unknown.iter().position(|k| normalized(k) == normalized(name)) {
+                Some(i) => {
+                    let key = unknown.remove(i);
+                    missing.push(finding(
+                        format!("`{}{}` should be named `{}{}`", prefix, key, prefix, name),
+                        "Rename it; field names are snake_case.",
+                    ));
+                }
+                None => missing.push(finding(
+                    format!("missing required field `{}{}`", prefix, name),
+                    format!("Add `{}{}`: {}.", prefix, name, expected(name)),
+                )),
+            },
+            Some(Value::Array(_)) if kind == Kind::Functions => mistyped.push(finding(
+                "`functions` is an array, not an object",
+                format!(
+                    "Write it as {{\"baseline\": …, \"norm\": …, \"stability\": …, \"meta_control\": …}}, \
+                     or pass --functions-array to accept the [{}] form.",
+                    FUNCTION_FIELDS.join(", ")
+                ),
+            )),
+            Some(value) if !has_kind(value, kind) => mistyped.push(finding(
+                format!("`{}{}` is {}", prefix, name, describe(value)),
+                format!("`{}{}` must be {}.", prefix, name, expected(name)),
+            )),
+            Some(_) => {}
+        }
+    }
+    findings.extend(missing);
+    findings.extend(mistyped);
+    for key in unknown {
+        findings.push(finding(
+            format!("unknown field `{}{}`", prefix, key),
+            "Remove it: records allow no additional fields (INV-S2). `validate --lenient` ignores unknown fields.",
+        ));
+    }
+    object.iter().filter(|(k, _)| fields.iter().any(|(f, _)| f == k)).map(|(k, v)| (k.clone(), v.clone())).collect()
+}
+
+// Pushes the field-level findings of a record object and returns the object
+// without its unknown fields, which may then deserialize.
+fn structural_findings(object: &Map<String, Value>, findings: &mut Vec<Finding>) -> Map<String, Value> {
+    let mut fields = REQUIRED_FIELDS.to_vec();
+    fields.push(("sequence_number", Kind::Integer));
+    let mut known = check_fields(object, &fields, "", findings);
+    if let Some(Value::Object(functions)) = object.get("functions") {
+        let function_fields: Vec<(&str, Kind)> = FUNCTION_FIELDS.iter().map(|&f| (f, Kind::Number)).collect();
+        let known_functions = check_fields(functions, &function_fields, "functions.", findings);
+        known.insert("functions".to_string(), Value::Object(known_functions));
+    }
+    known
+}
+
+// Checks the usable fields of an object that does not deserialize.
+fn partial_findings(object: &Map<String, Value>) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if let Some(Value::String(spec)) = object.get("spec_version").filter(|v| v.as_str() != Some(SPEC_VERSION)) {
+        findings.push(finding(format!("spec_version is {:?}", spec), hint(ErrorCode::SpecVersionMismatch)));
+    }
+    if object.get("timestamp").and_then(Value::as_u64) == Some(0) {
+        findings.push(finding("timestamp is 0", hint(ErrorCode::TimestampZero)));
+    }
+    let functions = object.get("functions").and_then(Value::as_object);
+    let scores = FUNCTION_FIELDS
+        .iter()
+        .map(|f| (format!("functions.{}", f), functions.and_then(|o| o.get(*f)), ErrorCode::FunctionOutOfRange))
+        .chain([
+            ("stability_score".to_string(), object.get("stability_score"), ErrorCode::StabilityScoreOutOfRange),
+            ("risk_score".to_string(), object.get("risk_score"), ErrorCode::RiskScoreOutOfRange),
+        ]);
+    for (name, value, code) in scores {
+        if let Some(v) = value.and_then(Value::as_f64).filter(|v| !(0.0..=1.0).contains(v)) {
+            findings.push(finding(format!("{}={} outside [0.0, 1.0]", name, v), hint(code)));
+        }
+    }
+    for (name, allowed) in [("mode", &MODES), ("risk_level", &RISK_LEVELS)] {
+        if let Some(Value::String(s)) = object.get(name).filter(|v| !allowed.iter().any(|a| v.as_str() == Some(a))) {
+            findings.push(finding(format!("{} {:?} is not {}", name, s, expected(name)), format!("Use {}.", expected(name))));
+        }
+    }
+    findings
+}
+
+// One finding per failed invariant: versions first, then ranges, then one
+// finding for all of INV-C1/C2/C3.
+fn invariant_findings(record: &RuntimeStateRecord) -> Vec<Finding> {
+    let results = validate_all(record);
+    let mut findings = Vec::new();
+    let mut consistency = false;
+    for series in ["INV-S", "INV-R", "INV-C"] {
+        for r in results.iter().filter(|r| r.id.starts_with(series)) {
+            let Some(code) = r.code else { continue };
+            match code {
+                ErrorCode::ModeScoreMismatch | ErrorCode::LevelModeMismatch | ErrorCode::InconsistentClassification => {
+                    consistency = true
+                }
+                _ => findings.push(finding(format!("{}: {}", r.id, r.detail), hint(code))),
+            }
+        }
+    }
+    if consistency {
+        let problem = explain_consistency_failure(record)
+            .unwrap_or_else(|| "mode and risk_level do not follow from risk_score".to_string());
+        findings.push(finding(problem, CONSISTENCY_HINT));
+    }
+    findings
+}
+
+const CONSISTENCY_HINT: &str = "Derive mode from risk_score and risk_level from mode instead of setting them \
+     separately (see `legend`); `validate --recompute-mode --corrected-out FILE` writes corrected records.";
+
+// The fix for a failed check.
+fn hint(code: ErrorCode) -> String {
+    match code {
+        ErrorCode::FunctionOutOfRange => "Function values must be in [0.0, 1.0]; clamp or rescale them before emitting.".into(),
+        ErrorCode::StabilityScoreOutOfRange => {
+            "stability_score must be in [0.0, 1.0]; check how it is aggregated from the functions.".into()
+        }
+        ErrorCode::RiskScoreOutOfRange => "risk_score must be in [0.0, 1.0].".into(),
+        ErrorCode::FunctionSubnormal | ErrorCode::StabilityScoreSubnormal | ErrorCode::RiskScoreSubnormal => {
+            "Subnormal values usually come from an upstream computation bug; recompute the value.".into()
+        }
+        ErrorCode::TimestampZero => "Set timestamp to the emission time in Unix seconds.".into(),
+        ErrorCode::AggregationMismatch => {
+            "This check is demo-only; production aggregation is expected to differ.".into()
+        }
+        ErrorCode::ModeScoreMismatch | ErrorCode::LevelModeMismatch | ErrorCode::InconsistentClassification => {
+            CONSISTENCY_HINT.into()
+        }
+        ErrorCode::StaleClassification => {
+            "The record predates a partition change; `validate --recompute-mode --corrected-out FILE` writes it \
+             with the current mode and risk_level."
+                .into()
+        }
+        ErrorCode::TimestampNotIncreasing => {
+            "Give every record a later timestamp than the one before it, or validate without --strict-monotonic \
+             if the dataset allows duplicates."
+                .into()
+        }
+        ErrorCode::DerivationMismatch => {
+            "Use a level map that keeps the normative order of risk levels.".into()
+        }
+        ErrorCode::DegenerateFunctions => {
+            "Four identical function values usually mean a stubbed emitter; check the measurements.".into()
+        }
+        ErrorCode::MissingField => "spec_version, schema_version, mode and risk_level must not be empty.".into(),
+        ErrorCode::UnsupportedField => {
+            format!("Set schema_version to {} or later, or drop sequence_number.", SEQUENCE_SCHEMA_VERSION)
+        }
+        ErrorCode::SpecVersionMismatch => format!("Set spec_version to \"{}\".", SPEC_VERSION),
+        ErrorCode::InvalidSchemaVersion => format!("Use MAJOR.MINOR.PATCH, such as \"{}\".", SCHEMA_VERSION),
+        ErrorCode::SchemaVersionOutOfRange => {
+            "Upgrade the emitter, or widen the accepted range if this version is part of the rollout.".into()
+        }
+    }
+}
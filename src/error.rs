@@ -0,0 +1,58 @@
+// error.rs — P-MATRIX Encoder Error Type
+//
+// A single error type shared by the library and the CLI so that callers can
+// compose encoder operations with `?`.
+
+use std::fmt;
+
+/// Errors produced by the reference encoder.
+#[derive(Debug)]
+pub enum PmatrixError {
+    /// Encoding inputs were rejected (NaN, infinite, or outside [0.0, 1.0]).
+    Encode(String),
+    /// A user-supplied configuration (e.g. a level map) is invalid.
+    Config(String),
+    /// Input could not be parsed as a runtime state record.
+    Parse(serde_json::Error),
+    /// Reading input or writing output failed.
+    Io(std::io::Error),
+    /// A record was parsed but violates one or more invariants.
+    /// Holds the IDs of the failed invariants.
+    Validation(Vec<&'static str>),
+}
+
+impl fmt::Display for PmatrixError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PmatrixError::Encode(msg) => write!(f, "{}", msg),
+            PmatrixError::Config(msg) => write!(f, "invalid configuration: {}", msg),
+            PmatrixError::Parse(e) => write!(f, "JSON parse error: {}", e),
+            PmatrixError::Io(e) => write!(f, "I/O error: {}", e),
+            PmatrixError::Validation(ids) => {
+                write!(f, "invariant violation(s): {}", ids.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for PmatrixError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PmatrixError::Parse(e) => Some(e),
+            PmatrixError::Io(e) => Some(e),
+            PmatrixError::Encode(_) | PmatrixError::Config(_) | PmatrixError::Validation(_) => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for PmatrixError {
+    fn from(e: serde_json::Error) -> Self {
+        PmatrixError::Parse(e)
+    }
+}
+
+impl From<std::io::Error> for PmatrixError {
+    fn from(e: std::io::Error) -> Self {
+        PmatrixError::Io(e)
+    }
+}
@@ -0,0 +1,36 @@
+// filter.rs — Stream Filtering
+//
+// Selects records from a stream while preserving their order. A filtered
+// stream keeps INV-T1: dropping records never makes timestamps decrease.
+
+use crate::schema::RuntimeStateRecord;
+
+/// Keeps the records that satisfy `predicate`, in their original order.
+pub fn filter_records<I, P>(records: I, predicate: P) -> impl Iterator<Item = RuntimeStateRecord>
+where
+    I: IntoIterator<Item = RuntimeStateRecord>,
+    P: FnMut(&RuntimeStateRecord) -> bool,
+{
+    records.into_iter().filter(predicate)
+}
+
+/// A risk_score band and mode selection. Empty criteria match everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RecordFilter {
+    /// Inclusive lower bound on risk_score.
+    pub min_risk: Option<f64>,
+    /// Inclusive upper bound on risk_score.
+    pub max_risk: Option<f64>,
+    /// Accepted modes; empty accepts any mode.
+    pub modes: Vec<String>,
+}
+
+impl RecordFilter {
+    /// Returns true if the record falls within the band and has an accepted mode.
+    /// A NaN risk_score never matches a bound.
+    pub fn matches(&self, record: &RuntimeStateRecord) -> bool {
+        self.min_risk.is_none_or(|min| record.risk_score >= min)
+            && self.max_risk.is_none_or(|max| record.risk_score <= max)
+            && (self.modes.is_empty() || self.modes.contains(&record.mode))
+    }
+}
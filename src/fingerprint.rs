@@ -0,0 +1,53 @@
+// fingerprint.rs — Record Fingerprinting
+//
+// A fingerprint is the SHA-256 digest of a record's canonical serialization:
+// compact JSON with fields in schema order. Two records share a fingerprint
+// exactly when they serialize identically.
+
+use crate::schema::RuntimeStateRecord;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Returns the canonical serialization used for fingerprinting: compact JSON
+/// with fields in schema order (the declaration order of the struct).
+pub fn canonical_bytes(record: &RuntimeStateRecord) -> Vec<u8> {
+    serde_json::to_vec(record).expect("record serialization is infallible")
+}
+
+/// Computes the lowercase hex SHA-256 fingerprint of a record.
+pub fn record_fingerprint(record: &RuntimeStateRecord) -> String {
+    to_hex(&Sha256::digest(canonical_bytes(record)))
+}
+
+/// Returns true if `expected` (hex, case-insensitive) matches the record's
+/// fingerprint.
+pub fn fingerprint_matches(record: &RuntimeStateRecord, expected: &str) -> bool {
+    record_fingerprint(record).eq_ignore_ascii_case(expected.trim())
+}
+
+/// A record together with its fingerprint, for tamper-evident storage.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SealedRecord {
+    pub record: RuntimeStateRecord,
+    pub fingerprint: String,
+}
+
+impl SealedRecord {
+    /// Returns true if the stored fingerprint matches the record.
+    pub fn verify(&self) -> bool {
+        fingerprint_matches(&self.record, &self.fingerprint)
+    }
+}
+
+/// Pairs a record with its fingerprint. Sealing the same record always
+/// produces the same result.
+pub fn seal(record: RuntimeStateRecord) -> SealedRecord {
+    let fingerprint = record_fingerprint(&record);
+    SealedRecord { record, fingerprint }
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
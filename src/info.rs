@@ -0,0 +1,48 @@
+// info.rs — Build and Capability Report
+//
+// Lets embedders and operators check at runtime which versions, features
+// and invariants the linked crate supports.
+
+use crate::invariants::{INVARIANTS, OPTIONAL_INVARIANTS, QUALITY_INVARIANTS};
+use crate::schema::{SCHEMA_VERSION, SEQUENCE_SCHEMA_VERSION, SPEC_VERSION};
+
+use serde::Serialize;
+
+/// Cargo features that change what the crate can do.
+const FEATURES: [(&str, bool); 5] = [
+    ("canonical", cfg!(feature = "canonical")),
+    ("gzip", cfg!(feature = "gzip")),
+    ("chrono", cfg!(feature = "chrono")),
+    ("arbitrary", cfg!(feature = "arbitrary")),
+    ("json-schema", cfg!(feature = "json-schema")),
+];
+
+/// What this build of the crate supports, as returned by `crate_info`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CrateInfo {
+    /// Crate version from Cargo.toml.
+    pub version: &'static str,
+    /// spec_version values accepted by INV-S3.
+    pub spec_versions: Vec<&'static str>,
+    /// schema_version values whose fields the encoder knows, oldest first.
+    pub schema_versions: Vec<&'static str>,
+    /// Cargo features enabled in this build, in Cargo.toml order.
+    pub features: Vec<&'static str>,
+    /// The normative invariant IDs, in validation order.
+    pub invariants: Vec<&'static str>,
+    /// IDs of the opt-in checks, optional invariants before quality checks.
+    pub optional_invariants: Vec<&'static str>,
+}
+
+/// Reports the crate version, supported versions, enabled features and
+/// invariant IDs of this build.
+pub fn crate_info() -> CrateInfo {
+    CrateInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        spec_versions: vec![SPEC_VERSION],
+        schema_versions: vec![SCHEMA_VERSION, SEQUENCE_SCHEMA_VERSION],
+        features: FEATURES.iter().filter(|(_, on)| *on).map(|(name, _)| *name).collect(),
+        invariants: INVARIANTS.iter().map(|(id, _)| *id).collect(),
+        optional_invariants: OPTIONAL_INVARIANTS.iter().chain(&QUALITY_INVARIANTS).map(|(id, _)| *id).collect(),
+    }
+}
@@ -0,0 +1,542 @@
+// input.rs — Record Input Helpers
+//
+// Reads runtime state records from files or stdin. A stream is either a JSON
+// array of records or a sequence of records separated by whitespace
+// (NDJSON being the common case). Single records can be parsed strictly
+// (unknown fields rejected) or leniently (unknown fields ignored and reported).
+// Input is JSON; `sniff_format` recognizes other encodings only to reject them
+// with a clear message.
+
+use crate::error::PmatrixError;
+use crate::schema::{Functions, RuntimeStateRecord, META_CONTROL_ALIASES};
+
+use serde::de::{DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use serde::Deserialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+
+/// The two magic bytes that open every gzip member (RFC 1952).
+pub const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// The byte order mark some Windows tools write at the start of UTF-8 files.
+pub const UTF8_BOM: char = '\u{feff}';
+
+/// Prepares text for parsing: strips a UTF-8 byte order mark from the start
+/// of every line, so that concatenated exports still parse as NDJSON, and
+/// trims surrounding whitespace. CRLF line endings need no treatment, since
+/// JSON allows `\r` between values.
+///
+/// `read_input` and `read_input_as` apply this to everything they read, and
+/// `read_records` strips the same BOMs line by line as it streams.
+pub fn clean_input(input: &str) -> Cow<'_, str> {
+    if !input.contains(UTF8_BOM) {
+        return Cow::Borrowed(input.trim());
+    }
+    // A JSON string cannot span lines, so a BOM after leading whitespace is
+    // never part of a value.
+    let lines: Vec<&str> = input.lines().map(|l| l.trim_start().trim_start_matches(UTF8_BOM)).collect();
+    Cow::Owned(lines.join("\n").trim().to_string())
+}
+
+// Applies `clean_input`, reusing the buffer when nothing changes.
+fn cleaned(input: String) -> String {
+    match clean_input(&input) {
+        Cow::Borrowed(s) if s.len() == input.len() => None,
+        other => Some(other.into_owned()),
+    }
+    .unwrap_or(input)
+}
+
+/// Opens a file, or stdin when `path` is None or "-", as a buffered reader.
+///
+/// Files are decompressed transparently when they end in `.gz` or begin with
+/// the gzip magic bytes; stdin is decompressed only when `force_gzip` is set.
+/// Decompression streams, so memory use does not grow with the input size.
+/// Without the `gzip` feature, compressed input is rejected with an error.
+/// A leading UTF-8 byte order mark is skipped.
+pub fn open_input(path: Option<&Path>, force_gzip: bool) -> Result<Box<dyn BufRead>, PmatrixError> {
+    let (mut reader, named_gz): (Box<dyn BufRead>, bool) = match path {
+        Some(p) if p != Path::new("-") => (
+            Box::new(BufReader::new(File::open(p)?)),
+            p.extension().is_some_and(|e| e == "gz"),
+        ),
+        _ => (Box::new(BufReader::new(io::stdin())), false),
+    };
+
+    let is_stdin = path.is_none_or(|p| p == Path::new("-"));
+    let gzip = force_gzip || named_gz || (!is_stdin && reader.fill_buf()?.starts_with(&GZIP_MAGIC));
+    if gzip {
+        reader = decompress(reader)?;
+    }
+    if reader.fill_buf()?.starts_with("\u{feff}".as_bytes()) {
+        reader.consume(UTF8_BOM.len_utf8());
+    }
+    Ok(reader)
+}
+
+#[cfg(feature = "gzip")]
+fn decompress(reader: Box<dyn BufRead>) -> Result<Box<dyn BufRead>, PmatrixError> {
+    Ok(Box::new(BufReader::new(flate2::bufread::MultiGzDecoder::new(reader))))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn decompress(_reader: Box<dyn BufRead>) -> Result<Box<dyn BufRead>, PmatrixError> {
+    Err(PmatrixError::Io(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "gzip-compressed input requires the `gzip` feature",
+    )))
+}
+
+/// An encoding detected by `sniff_format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Json,
+    Cbor,
+    Yaml,
+}
+
+/// Detects the encoding of an input from its leading bytes.
+///
+/// CBOR is recognized by its self-describe tag or a leading map/array header
+/// (bytes that never start JSON or YAML text), JSON by a leading `{` or `[`
+/// (or an empty input),
+/// and YAML by a `---` document marker or a `key:` first line. Anything else
+/// is rejected rather than guessed.
+pub fn sniff_format(bytes: &[u8]) -> Result<InputFormat, PmatrixError> {
+    if bytes.starts_with(&[0xd9, 0xd9, 0xf7]) || matches!(bytes.first(), Some(0x80..=0xbf)) {
+        return Ok(InputFormat::Cbor);
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| {
+        undetected("input is neither CBOR nor UTF-8 text")
+    })?;
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+    // Empty input is left to the JSON parser (an empty stream is valid).
+    if text.is_empty() || text.starts_with('{') || text.starts_with('[') {
+        return Ok(InputFormat::Json);
+    }
+    let first_line = text.lines().next().unwrap_or("");
+    if first_line.starts_with("---") || first_line.split_once(':').is_some_and(|(k, _)| is_yaml_key(k)) {
+        return Ok(InputFormat::Yaml);
+    }
+    Err(undetected("input does not start like JSON"))
+}
+
+fn is_yaml_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '"')
+}
+
+fn undetected(reason: &str) -> PmatrixError {
+    PmatrixError::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("could not detect input format ({}); pass --input-format json to parse it as JSON", reason),
+    ))
+}
+
+fn unsupported(format: &str) -> PmatrixError {
+    PmatrixError::Io(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("{} input is not supported; convert it to JSON first", format),
+    ))
+}
+
+/// Reads the full input like `read_input`, checking its encoding first.
+///
+/// With `format` None the encoding is sniffed. Only JSON can be decoded;
+/// CBOR and YAML input is rejected with an error naming the format.
+pub fn read_input_as(
+    path: Option<&Path>,
+    force_gzip: bool,
+    format: Option<InputFormat>,
+) -> Result<String, PmatrixError> {
+    let mut bytes = Vec::new();
+    open_input(path, force_gzip)?.read_to_end(&mut bytes)?;
+    let format = match format {
+        Some(f) => f,
+        None => sniff_format(&bytes)?,
+    };
+    match format {
+        InputFormat::Json => String::from_utf8(bytes)
+            .map(cleaned)
+            .map_err(|e| PmatrixError::Io(io::Error::new(io::ErrorKind::InvalidData, e))),
+        InputFormat::Cbor => Err(unsupported("CBOR")),
+        InputFormat::Yaml => Err(unsupported("YAML")),
+    }
+}
+
+/// Opens the input like `open_input`, checking its encoding first.
+///
+/// The encoding is sniffed from the first buffered bytes when `format` is
+/// None, and non-JSON input is rejected as by `read_input_as`.
+pub fn open_input_as(
+    path: Option<&Path>,
+    force_gzip: bool,
+    format: Option<InputFormat>,
+) -> Result<Box<dyn BufRead>, PmatrixError> {
+    let mut reader = open_input(path, force_gzip)?;
+    let format = match format {
+        Some(f) => f,
+        None => sniff_format(utf8_prefix(reader.fill_buf()?))?,
+    };
+    match format {
+        InputFormat::Json => Ok(reader),
+        InputFormat::Cbor => Err(unsupported("CBOR")),
+        InputFormat::Yaml => Err(unsupported("YAML")),
+    }
+}
+
+// Drops a multi-byte character cut off at the end of a buffer.
+fn utf8_prefix(bytes: &[u8]) -> &[u8] {
+    match std::str::from_utf8(bytes) {
+        Err(e) if e.error_len().is_none() => &bytes[..e.valid_up_to()],
+        _ => bytes,
+    }
+}
+
+/// Reads the full input from a file, or from stdin when `path` is None or "-",
+/// decompressing it as described for `open_input` and cleaning it with
+/// `clean_input`.
+pub fn read_input(path: Option<&Path>, force_gzip: bool) -> Result<String, PmatrixError> {
+    let mut input = String::new();
+    open_input(path, force_gzip)?.read_to_string(&mut input)?;
+    Ok(cleaned(input))
+}
+
+/// Parses a stream of records from a JSON array or from whitespace-separated
+/// JSON records (e.g. NDJSON). Records are returned in input order.
+pub fn parse_stream(input: &str) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+    parse_stream_bounded(input, usize::MAX, usize::MAX)
+}
+
+/// Lazily parses a sequence of whitespace-separated records (such as NDJSON)
+/// from a reader, so that streams larger than memory can be processed. JSON
+/// arrays are not supported; use `parse_stream` for those. A BOM at the start
+/// of any line is skipped, as by `clean_input`.
+pub fn read_records<R: Read>(reader: R) -> impl Iterator<Item = Result<RuntimeStateRecord, PmatrixError>> {
+    serde_json::Deserializer::from_reader(BomStripper::new(BufReader::new(reader)))
+        .into_iter::<RuntimeStateRecord>()
+        .map(|r| r.map_err(PmatrixError::from))
+}
+
+// Reads line by line, dropping the BOMs that `clean_input` would drop.
+struct BomStripper<R> {
+    inner: R,
+    line: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: BufRead> BomStripper<R> {
+    fn new(inner: R) -> Self {
+        BomStripper { inner, line: Vec::new(), pos: 0 }
+    }
+}
+
+impl<R: BufRead> Read for BomStripper<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos == self.line.len() {
+            self.line.clear();
+            self.pos = 0;
+            if self.inner.read_until(b'\n', &mut self.line)? == 0 {
+                return Ok(0);
+            }
+            let indent = self.line.iter().take_while(|b| b.is_ascii_whitespace()).count();
+            let bom = "\u{feff}".as_bytes();
+            let boms = self.line[indent..].chunks(bom.len()).take_while(|c| *c == bom).count();
+            self.line.drain(indent..indent + boms * bom.len());
+        }
+        let n = buf.len().min(self.line.len() - self.pos);
+        buf[..n].copy_from_slice(&self.line[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Parses records embedded in larger documents at an RFC 6901 JSON Pointer.
+///
+/// Each JSON value of `input` (one document, or several as in NDJSON) is
+/// resolved at `pointer`: an object is parsed strictly as one record, and an
+/// array as a sequence of records. Fails if the pointer does not resolve in
+/// some document or selects anything else.
+pub fn parse_at_pointer(input: &str, pointer: &str) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+    parse_at_pointer_bounded(input, pointer, usize::MAX)
+}
+
+/// Like `parse_at_pointer`, but fails as soon as more than `max_records`
+/// records have been selected.
+pub fn parse_at_pointer_bounded(
+    input: &str,
+    pointer: &str,
+    max_records: usize,
+) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+    if !pointer.is_empty() && !pointer.starts_with('/') {
+        return Err(PmatrixError::Config(format!(
+            "JSON pointer `{}` must be empty or start with '/'",
+            pointer
+        )));
+    }
+    let mut records = Vec::new();
+    for (i, doc) in serde_json::Deserializer::from_str(input).into_iter::<Value>().enumerate() {
+        let doc = doc?;
+        let shape_error = |what: &str| {
+            let msg = format!("JSON pointer `{}` {} in document {}", pointer, what, i + 1);
+            PmatrixError::Parse(serde::de::Error::custom(msg))
+        };
+        let mut push = |record: &Value| {
+            if records.len() == max_records {
+                return Err(too_many_records(max_records));
+            }
+            records.push(RuntimeStateRecord::deserialize(record)?);
+            Ok(())
+        };
+        match doc.pointer(pointer) {
+            Some(record @ Value::Object(_)) => push(record)?,
+            Some(Value::Array(items)) => items.iter().try_for_each(push)?,
+            Some(_) => return Err(shape_error("selects neither an object nor an array")),
+            None => return Err(shape_error("does not resolve")),
+        }
+    }
+    Ok(records)
+}
+
+/// Like `parse_stream`, but returns at most the first `limit` records.
+///
+/// For NDJSON, parsing stops after `limit` records, so a malformed record
+/// further on is never reached. A JSON array is parsed whole and truncated.
+pub fn parse_stream_limit(input: &str, limit: usize) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+    parse_stream_bounded(input, limit, usize::MAX)
+}
+
+/// Default for the `max_records` cap of `parse_stream_bounded`: generous, but
+/// finite so that untrusted input cannot grow memory without bound.
+pub const DEFAULT_MAX_RECORDS: usize = 10_000_000;
+
+/// Parses at most the first `limit` records, like `parse_stream_limit`, and
+/// fails as soon as more than `max_records` records would have to be held in
+/// memory. `parse_stream` and `parse_stream_limit` are this function without
+/// a cap.
+///
+/// A JSON array aborts when its element `max_records + 1` is parsed, before
+/// the rest of the array is read. For NDJSON only the first `limit` records
+/// count toward the cap.
+pub fn parse_stream_bounded(input: &str, limit: usize, max_records: usize) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+    if input.trim_start().starts_with('[') {
+        return parse_capped_array(serde_json::Deserializer::from_str(input), limit, max_records);
+    }
+    let mut records = Vec::new();
+    for r in serde_json::Deserializer::from_str(input).into_iter::<RuntimeStateRecord>().take(limit) {
+        if records.len() == max_records {
+            return Err(too_many_records(max_records));
+        }
+        records.push(r?);
+    }
+    Ok(records)
+}
+
+/// Reads a JSON array or NDJSON stream from `reader` as `parse_stream_bounded`
+/// parses it from a string, without holding the input text in memory: only
+/// the parsed records are kept, so `max_records` bounds memory use even for
+/// compressed input that expands without limit.
+pub fn read_stream_bounded<R: BufRead>(mut reader: R, limit: usize, max_records: usize) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+    loop {
+        let buf = reader.fill_buf()?;
+        let blank = buf.iter().take_while(|b| b.is_ascii_whitespace()).count();
+        if blank < buf.len() {
+            let is_array = buf[blank] == b'[';
+            reader.consume(blank);
+            if is_array {
+                let de = serde_json::Deserializer::from_reader(BomStripper::new(reader));
+                return parse_capped_array(de, limit, max_records);
+            }
+            return cap_records(read_records(reader).take(limit), max_records).collect();
+        }
+        if buf.is_empty() {
+            return Ok(Vec::new());
+        }
+        reader.consume(blank);
+    }
+}
+
+fn parse_capped_array<'de, R: serde_json::de::Read<'de>>(
+    mut de: serde_json::Deserializer<R>,
+    limit: usize,
+    max_records: usize,
+) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+    let exceeded = Cell::new(false);
+    let seed = CappedRecords { max: max_records, exceeded: &exceeded };
+    let mut records = match seed.deserialize(&mut de).and_then(|r| de.end().map(|()| r)) {
+        Ok(records) => records,
+        Err(_) if exceeded.get() => return Err(too_many_records(max_records)),
+        Err(e) => return Err(e.into()),
+    };
+    records.truncate(limit);
+    Ok(records)
+}
+
+/// The error for input holding more than `max_records` records. It is a
+/// parse error: the input, not the reader, is at fault.
+pub fn too_many_records(max_records: usize) -> PmatrixError {
+    let msg = format!("input has more than {} records; raise --max-records to accept it", max_records);
+    PmatrixError::Parse(serde::de::Error::custom(msg))
+}
+
+/// Passes `records` through until more than `max_records` have been seen,
+/// then yields `too_many_records` and stops. Applies the cap of
+/// `parse_stream_bounded` to a lazily read stream such as `read_records`.
+pub fn cap_records<I>(records: I, max_records: usize) -> impl Iterator<Item = Result<RuntimeStateRecord, PmatrixError>>
+where
+    I: IntoIterator<Item = Result<RuntimeStateRecord, PmatrixError>>,
+{
+    let mut records = records.into_iter();
+    let mut seen = 0usize;
+    std::iter::from_fn(move || {
+        if seen > max_records {
+            return None;
+        }
+        let next = records.next()?;
+        seen += 1;
+        if seen > max_records {
+            return Some(Err(too_many_records(max_records)));
+        }
+        Some(next)
+    })
+}
+
+// Deserializes a JSON array of records, failing (and setting `exceeded`)
+// once it holds more than `max` elements.
+struct CappedRecords<'a> {
+    max: usize,
+    exceeded: &'a Cell<bool>,
+}
+
+impl<'de> DeserializeSeed<'de> for CappedRecords<'_> {
+    type Value = Vec<RuntimeStateRecord>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de> Visitor<'de> for CappedRecords<'_> {
+    type Value = Vec<RuntimeStateRecord>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON array of at most {} records", self.max)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut records = Vec::new();
+        while let Some(record) = seq.next_element()? {
+            if records.len() == self.max {
+                self.exceeded.set(true);
+                return Err(serde::de::Error::custom("too many records"));
+            }
+            records.push(record);
+        }
+        Ok(records)
+    }
+}
+
+/// Field names of the array form of `functions`, in position order.
+pub const FUNCTION_FIELDS: [&str; 4] = ["baseline", "norm", "stability", "meta_control"];
+
+/// Rewrites a record's `functions` from the compact array form
+/// `[baseline, norm, stability, meta_control]` into the named object, in
+/// place. A `functions` object, or a value that is not an object, is left
+/// as it is.
+///
+/// Fails with `PmatrixError::Parse` if the array does not hold exactly four
+/// elements.
+pub fn expand_functions_array(record: &mut Value) -> Result<(), PmatrixError> {
+    let Some(Value::Array(values)) = record.get_mut("functions") else {
+        return Ok(());
+    };
+    if values.len() != FUNCTION_FIELDS.len() {
+        let msg = format!(
+            "functions array has {} element(s); expected 4: [baseline, norm, stability, meta_control]",
+            values.len()
+        );
+        return Err(PmatrixError::Parse(serde::de::Error::custom(msg)));
+    }
+    let named = FUNCTION_FIELDS.iter().map(|f| f.to_string()).zip(values.drain(..)).collect();
+    record["functions"] = Value::Object(named);
+    Ok(())
+}
+
+/// Rewrites every record of `input` (a single record, a JSON array or
+/// NDJSON) with `expand_functions_array`, returning the result as a JSON
+/// array if the input was one and as NDJSON otherwise. The output then
+/// parses with the usual strict functions.
+pub fn expand_functions_arrays(input: &str) -> Result<String, PmatrixError> {
+    let mut out = String::new();
+    for doc in serde_json::Deserializer::from_str(input).into_iter::<Value>() {
+        let mut doc = doc?;
+        match &mut doc {
+            Value::Array(records) => records.iter_mut().try_for_each(expand_functions_array)?,
+            record => expand_functions_array(record)?,
+        }
+        out.push_str(&serde_json::to_string(&doc)?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Parses a single record, rejecting unknown fields (INV-S2).
+///
+/// This is the default, conformance-grade parse.
+pub fn parse_strict(input: &str) -> Result<RuntimeStateRecord, PmatrixError> {
+    Ok(serde_json::from_str(input)?)
+}
+
+// Shadow of `RuntimeStateRecord` that tolerates unknown keys.
+#[derive(Deserialize)]
+struct LenientRecord {
+    spec_version: String,
+    schema_version: String,
+    timestamp: u64,
+    #[serde(default)]
+    sequence_number: Option<u64>,
+    // Split into known and unknown keys by `parse_lenient`.
+    functions: serde_json::Map<String, Value>,
+    stability_score: f64,
+    risk_score: f64,
+    mode: String,
+    risk_level: String,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, Value>,
+}
+
+/// Parses a single record, ignoring unknown fields for forward compatibility.
+///
+/// Returns the record together with the names of the ignored fields; keys
+/// inside `functions` are reported as `functions.<name>`. Required fields
+/// are still required.
+pub fn parse_lenient(input: &str) -> Result<(RuntimeStateRecord, Vec<String>), PmatrixError> {
+    let shadow: LenientRecord = serde_json::from_str(input)?;
+
+    let mut ignored: Vec<String> = shadow.unknown.into_keys().collect();
+    // The known keys, aliases included, go through the strict `Functions`
+    // codec so that both parsers accept the same names.
+    let is_known = |k: &str| FUNCTION_FIELDS.contains(&k) || META_CONTROL_ALIASES.contains(&k);
+    let (known, unknown): (serde_json::Map<_, _>, serde_json::Map<_, _>) =
+        shadow.functions.into_iter().partition(|(k, _)| is_known(k));
+    ignored.extend(unknown.into_iter().map(|(k, _)| format!("functions.{}", k)));
+    let functions = Functions::deserialize(Value::Object(known))?;
+
+    let record = RuntimeStateRecord {
+        spec_version: shadow.spec_version,
+        schema_version: shadow.schema_version,
+        timestamp: shadow.timestamp,
+        sequence_number: shadow.sequence_number,
+        functions,
+        stability_score: shadow.stability_score,
+        risk_score: shadow.risk_score,
+        mode: shadow.mode,
+        risk_level: shadow.risk_level,
+    };
+    Ok((record, ignored))
+}
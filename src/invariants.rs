@@ -3,28 +3,324 @@
 // Implements all 12 invariants from D1-A §4 / D1-B §5.
 // A record that violates any invariant is malformed.
 
-use crate::mode::{demo_partition_map, mode_to_risk_level};
-use crate::schema::{RuntimeStateRecord, SPEC_VERSION};
+use crate::demo::demo_stability_score;
+use crate::error::PmatrixError;
+use crate::mode::{risk_score_to_level, round_to_decimals, LevelMap, PartitionMap};
+use crate::schema::{
+    supports_sequence_number, RuntimeStateRecord, MODES, SEQUENCE_SCHEMA_VERSION, SPEC_VERSION,
+};
+
+use serde::Serialize;
+use std::collections::BTreeMap;
 
 /// Result of validating a single invariant.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct InvariantResult {
     pub id: &'static str,
     pub passed: bool,
+    /// Why the check failed, for programs; None when it passed.
+    pub code: Option<ErrorCode>,
+    /// Human-readable explanation. Its wording may change between releases.
     pub detail: String,
 }
 
+impl InvariantResult {
+    /// The severity of this result's invariant.
+    pub fn severity(&self) -> Severity {
+        invariant_severity(self.id)
+    }
+
+    /// True if the check failed and the failure breaks conformance.
+    pub fn is_violation(&self) -> bool {
+        !self.passed && self.severity() == Severity::Error
+    }
+
+    /// True if the check failed but only warrants a warning.
+    pub fn is_warning(&self) -> bool {
+        !self.passed && self.severity() == Severity::Warning
+    }
+}
+
+/// Why a check failed, as a stable machine-readable code.
+///
+/// Serializes as the SCREAMING_SNAKE_CASE name, e.g. `"MODE_SCORE_MISMATCH"`.
+/// Unlike `InvariantResult::detail`, codes are part of the API: existing
+/// codes keep their meaning, and new ones are only added alongside new
+/// checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    /// INV-R1: a function value is outside [0.0, 1.0] or NaN.
+    FunctionOutOfRange,
+    /// INV-R1 with `strict_floats`: a function value is subnormal.
+    FunctionSubnormal,
+    /// INV-R2: stability_score is outside [0.0, 1.0] or NaN.
+    StabilityScoreOutOfRange,
+    /// INV-R2 with `strict_floats`: stability_score is subnormal.
+    StabilityScoreSubnormal,
+    /// INV-R3: risk_score is outside [0.0, 1.0] or NaN.
+    RiskScoreOutOfRange,
+    /// INV-R3 with `strict_floats`: risk_score is subnormal.
+    RiskScoreSubnormal,
+    /// INV-R4: timestamp is 0.
+    TimestampZero,
+    /// INV-R6: stability_score differs from the demo aggregation.
+    AggregationMismatch,
+    /// INV-C1: mode does not match the partition of risk_score.
+    ModeScoreMismatch,
+    /// INV-C2: risk_level does not match the level of mode.
+    LevelModeMismatch,
+    /// INV-C3: mode and risk_level are not both determined by risk_score.
+    InconsistentClassification,
+    /// INV-C4: the two derivation paths of risk_level disagree.
+    DerivationMismatch,
+    /// INV-C5: mode or risk_level differs from the one recomputed from
+    /// risk_score, as for a record emitted under an older partition.
+    StaleClassification,
+    /// INV-T2: timestamp is not greater than the previous record's.
+    TimestampNotIncreasing,
+    /// INV-Q1: all four function values are identical.
+    DegenerateFunctions,
+    /// INV-S1: a required string field is empty.
+    MissingField,
+    /// INV-S2: sequence_number is present but schema_version predates it.
+    UnsupportedField,
+    /// INV-S3: spec_version is not the supported version.
+    SpecVersionMismatch,
+    /// INV-S4: schema_version is not valid MAJOR.MINOR.PATCH.
+    InvalidSchemaVersion,
+    /// INV-S6: schema_version is outside the accepted range.
+    SchemaVersionOutOfRange,
+}
+
+impl ErrorCode {
+    /// The serialized name, e.g. `"MODE_SCORE_MISMATCH"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ErrorCode::FunctionOutOfRange => "FUNCTION_OUT_OF_RANGE",
+            ErrorCode::FunctionSubnormal => "FUNCTION_SUBNORMAL",
+            ErrorCode::StabilityScoreOutOfRange => "STABILITY_SCORE_OUT_OF_RANGE",
+            ErrorCode::StabilityScoreSubnormal => "STABILITY_SCORE_SUBNORMAL",
+            ErrorCode::RiskScoreOutOfRange => "RISK_SCORE_OUT_OF_RANGE",
+            ErrorCode::RiskScoreSubnormal => "RISK_SCORE_SUBNORMAL",
+            ErrorCode::TimestampZero => "TIMESTAMP_ZERO",
+            ErrorCode::AggregationMismatch => "AGGREGATION_MISMATCH",
+            ErrorCode::ModeScoreMismatch => "MODE_SCORE_MISMATCH",
+            ErrorCode::LevelModeMismatch => "LEVEL_MODE_MISMATCH",
+            ErrorCode::InconsistentClassification => "INCONSISTENT_CLASSIFICATION",
+            ErrorCode::DerivationMismatch => "DERIVATION_MISMATCH",
+            ErrorCode::StaleClassification => "STALE_CLASSIFICATION",
+            ErrorCode::TimestampNotIncreasing => "TIMESTAMP_NOT_INCREASING",
+            ErrorCode::DegenerateFunctions => "DEGENERATE_FUNCTIONS",
+            ErrorCode::MissingField => "MISSING_FIELD",
+            ErrorCode::UnsupportedField => "UNSUPPORTED_FIELD",
+            ErrorCode::SpecVersionMismatch => "SPEC_VERSION_MISMATCH",
+            ErrorCode::InvalidSchemaVersion => "INVALID_SCHEMA_VERSION",
+            ErrorCode::SchemaVersionOutOfRange => "SCHEMA_VERSION_OUT_OF_RANGE",
+        }
+    }
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How a failed check affects conformance.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The record is malformed.
+    Error,
+    /// The record is conforming but suspicious.
+    Warning,
+}
+
+/// The 12 normative invariants with a short description, in validation order.
+pub const INVARIANTS: [(&str, &str); 12] = [
+    ("INV-R1", "All function values in [0.0, 1.0]"),
+    ("INV-R2", "stability_score in [0.0, 1.0]"),
+    ("INV-R3", "risk_score in [0.0, 1.0]"),
+    ("INV-R4", "timestamp > 0"),
+    ("INV-C1", "mode = threshold_map(risk_score)"),
+    ("INV-C2", "risk_level = level_map(mode)"),
+    ("INV-C3", "mode and risk_level mutually consistent"),
+    ("INV-S1", "All eight required fields present"),
+    ("INV-S2", "No additional fields"),
+    ("INV-S3", "spec_version = \"pmatrix-3.5\""),
+    ("INV-S4", "schema_version is valid semver"),
+    ("INV-T1", "Timestamps monotonically non-decreasing (stream-level)"),
+];
+
+/// Opt-in checks outside the normative 12, enabled through `ValidationOptions`.
+/// Stream-level INV-T2 is checked per record with `check_inv_t2`.
+pub const OPTIONAL_INVARIANTS: [(&str, &str); 5] = [
+    ("INV-R6", "stability_score matches demo aggregation of functions (demo-only)"),
+    ("INV-C4", "mode and risk_level agree with risk_score via both derivation paths"),
+    ("INV-S6", "schema_version within the accepted range"),
+    ("INV-C5", "mode and risk_level equal those recomputed from risk_score"),
+    ("INV-T2", "Timestamps strictly increasing (stream-level)"),
+];
+
+/// Opt-in data quality heuristics. Their failures are warnings: they never
+/// make a record non-conforming.
+pub const QUALITY_INVARIANTS: [(&str, &str); 1] = [
+    ("INV-Q1", "Function values not all identical (anti-degenerate)"),
+];
+
+/// Default tolerance for INV-R6.
+pub const DEMO_AGGREGATION_EPS: f64 = 1e-9;
+
+/// Returns the short description of an invariant, or None for unknown IDs.
+pub fn invariant_description(id: &str) -> Option<&'static str> {
+    INVARIANTS
+        .iter()
+        .chain(OPTIONAL_INVARIANTS.iter())
+        .chain(QUALITY_INVARIANTS.iter())
+        .find(|(i, _)| *i == id)
+        .map(|(_, d)| *d)
+}
+
+/// Returns the severity of an invariant: `Warning` for the quality checks and
+/// `Error` for everything else.
+pub fn invariant_severity(id: &str) -> Severity {
+    if QUALITY_INVARIANTS.iter().any(|(i, _)| *i == id) {
+        Severity::Warning
+    } else {
+        Severity::Error
+    }
+}
+
+/// Optional, non-default validation behaviour.
+///
+/// `ValidationOptions::default()` reproduces the normative checks exactly.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationOptions {
+    /// Fail range invariants (INV-R1/R2/R3) on subnormal values, which
+    /// almost always indicate an upstream computation bug.
+    pub strict_floats: bool,
+    /// When set, also check INV-R6 with this tolerance. DEMO-ONLY: production
+    /// pipelines use proprietary aggregation, so conforming production records
+    /// are expected to fail it.
+    pub demo_aggregation_eps: Option<f64>,
+    /// The risk_score → mode partition consulted by INV-C1 (and INV-C3).
+    pub partition_map: PartitionMap,
+    /// The mode → risk_level mapping consulted by INV-C2 (and INV-C3).
+    pub level_map: LevelMap,
+    /// Also check INV-C4: INV-C1 and INV-C2 hold, and risk_level equals the
+    /// normative level of risk_score partitioned directly.
+    pub check_chain: bool,
+    /// Also run the `QUALITY_INVARIANTS` heuristics (warnings only).
+    pub quality_checks: bool,
+    /// Round risk_score to this many decimals before classifying it for
+    /// INV-C1 (and INV-C3/C4); see `mode::classify_rounded`. Not normative:
+    /// it must match the producer's rounding to be meaningful.
+    pub classify_precision: Option<u32>,
+    /// When set, also check INV-S6: schema_version lies in this range. Without
+    /// it any well-formed version is accepted (INV-S4).
+    pub schema_version_range: Option<SchemaVersionRange>,
+    /// Also check INV-C5: mode and risk_level equal those `reclassify_record`
+    /// recomputes from risk_score. `validate --recompute-mode` reports it in
+    /// place of INV-C1 to INV-C3.
+    pub recompute_classification: bool,
+}
+
 /// Validates all 12 invariants against a runtime state record.
 /// Returns a Vec of results — one per invariant.
 pub fn validate_all(record: &RuntimeStateRecord) -> Vec<InvariantResult> {
-    vec![
-        check_inv_r1(record),
-        check_inv_r2(record),
-        check_inv_r3(record),
+    validate_all_with(record, &ValidationOptions::default())
+}
+
+/// Validates all 12 invariants using the given options, followed by any
+/// opt-in checks the options enable.
+pub fn validate_all_with(record: &RuntimeStateRecord, opts: &ValidationOptions) -> Vec<InvariantResult> {
+    let mut results = Vec::from(check_all(record, opts));
+    if let Some(eps) = opts.demo_aggregation_eps {
+        results.push(check_inv_r6(record, eps));
+    }
+    if opts.check_chain {
+        results.push(check_inv_c4(record, opts));
+    }
+    if let Some(range) = &opts.schema_version_range {
+        results.push(check_inv_s6(record, range));
+    }
+    if opts.recompute_classification {
+        results.push(check_inv_c5(record, opts));
+    }
+    if opts.quality_checks {
+        results.push(check_inv_q1(record));
+    }
+    results
+}
+
+/// Validates all 12 invariants into a caller-provided array, in `INVARIANTS`
+/// order, without allocating a Vec. The detail strings are still formatted;
+/// see `validate_summary_into` for a path with no heap allocation at all.
+pub fn validate_into(record: &RuntimeStateRecord, out: &mut [InvariantResult; 12]) {
+    validate_into_with(record, &ValidationOptions::default(), out)
+}
+
+/// Like `validate_into`, using the given options. Opt-in checks are not run.
+pub fn validate_into_with(
+    record: &RuntimeStateRecord,
+    opts: &ValidationOptions,
+    out: &mut [InvariantResult; 12],
+) {
+    *out = check_all(record, opts);
+}
+
+/// Result of one invariant with a static summary instead of a formatted detail.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct InvariantSummary {
+    pub id: &'static str,
+    pub passed: bool,
+    /// The invariant's description from `INVARIANTS`.
+    pub summary: &'static str,
+}
+
+/// Validates all 12 invariants into a caller-provided array without any heap
+/// allocation. Verdicts are identical to `validate_all_with`; each entry
+/// carries the invariant's description rather than a formatted detail.
+///
+/// Building `ValidationOptions` allocates, so hot loops should create the
+/// options once and reuse them. Opt-in checks are not run.
+pub fn validate_summary_into(
+    record: &RuntimeStateRecord,
+    opts: &ValidationOptions,
+    out: &mut [InvariantSummary; 12],
+) {
+    let c1 = c1_holds(record, opts);
+    let c2 = c2_holds(record, &opts.level_map);
+    let f = &record.functions;
+    let verdicts = [
+        f.values().iter().all(|&v| in_range(v, opts)),
+        in_range(record.stability_score, opts),
+        in_range(record.risk_score, opts),
+        record.timestamp > 0,
+        c1,
+        c2,
+        c1 && c2,
+        s1_holds(record),
+        s2_holds(record),
+        record.spec_version == SPEC_VERSION,
+        s4_holds(record),
+        true,
+    ];
+    for (slot, ((id, summary), passed)) in out.iter_mut().zip(INVARIANTS.iter().zip(verdicts)) {
+        *slot = InvariantSummary { id, passed, summary };
+    }
+}
+
+// The 12 normative checks, in `INVARIANTS` order.
+fn check_all(record: &RuntimeStateRecord, opts: &ValidationOptions) -> [InvariantResult; 12] {
+    [
+        check_inv_r1(record, opts),
+        check_inv_r2(record, opts),
+        check_inv_r3(record, opts),
         check_inv_r4(record),
-        check_inv_c1(record),
-        check_inv_c2(record),
-        check_inv_c3(record),
+        check_inv_c1(record, opts),
+        check_inv_c2(record, &opts.level_map),
+        check_inv_c3(record, opts),
         check_inv_s1(record),
         check_inv_s2(record),
         check_inv_s3(record),
@@ -34,47 +330,196 @@ pub fn validate_all(record: &RuntimeStateRecord) -> Vec<InvariantResult> {
     ]
 }
 
+/// Validates only the consistency invariants INV-C1, INV-C2 and INV-C3.
+///
+/// Intended for callers that already guarantee the range and structural
+/// fields; the results are identical to the C-series entries of `validate_all`.
+pub fn validate_consistency(record: &RuntimeStateRecord) -> Vec<InvariantResult> {
+    let opts = ValidationOptions::default();
+    vec![
+        check_inv_c1(record, &opts),
+        check_inv_c2(record, &opts.level_map),
+        check_inv_c3(record, &opts),
+    ]
+}
+
+/// Validates only the structural invariants INV-S1 to INV-S4.
+///
+/// These do not look at any numeric field, so they are a cheap first filter
+/// for records with a wrong version or missing fields; the results are
+/// identical to the S-series entries of `validate_all`.
+pub fn validate_structural(record: &RuntimeStateRecord) -> Vec<InvariantResult> {
+    vec![
+        check_inv_s1(record),
+        check_inv_s2(record),
+        check_inv_s3(record),
+        check_inv_s4(record),
+    ]
+}
+
+/// Like `validate_structural`, followed by INV-S6 when `opts` sets a
+/// schema version range. No other option applies to the structural checks.
+pub fn validate_structural_with(record: &RuntimeStateRecord, opts: &ValidationOptions) -> Vec<InvariantResult> {
+    let mut results = validate_structural(record);
+    if let Some(range) = &opts.schema_version_range {
+        results.push(check_inv_s6(record, range));
+    }
+    results
+}
+
+/// Recomputes mode and risk_level from the record's stored risk_score with
+/// the partition, level map and classify precision of `opts`, keeping every
+/// other field, including the scores, as it is. Returns None if risk_score
+/// maps to no mode (NaN or outside [0.0, 1.0]).
+pub fn reclassify_record(record: &RuntimeStateRecord, opts: &ValidationOptions) -> Option<RuntimeStateRecord> {
+    let mode = expected_mode(record, opts)?;
+    let risk_level = opts.level_map.level_for(mode)?;
+    Some(RuntimeStateRecord { mode: mode.to_string(), risk_level: risk_level.to_string(), ..record.clone() })
+}
+
+/// Explains in plain English why a record fails INV-C1/C2/C3, or returns
+/// None if mode and risk_level are consistent with risk_score.
+///
+/// For example: "risk_score 0.35 maps to Normal/L2, but the record says
+/// Caution/L3; the mode is one bucket too severe."
+pub fn explain_consistency_failure(record: &RuntimeStateRecord) -> Option<String> {
+    explain_consistency_failure_with(record, &ValidationOptions::default())
+}
+
+/// Like `explain_consistency_failure`, using the partition and level maps of
+/// `opts`.
+pub fn explain_consistency_failure_with(record: &RuntimeStateRecord, opts: &ValidationOptions) -> Option<String> {
+    let levels = &opts.level_map;
+    if c1_holds(record, opts) && c2_holds(record, levels) {
+        return None;
+    }
+    let score = normalize_zero(record.risk_score);
+    let says = format!("{}/{}", record.mode, record.risk_level);
+    let Some(expected_mode) = expected_mode(record, opts) else {
+        return Some(format!(
+            "risk_score {} is outside [0.0, 1.0], so it maps to no mode; the record says {}.",
+            score, says
+        ));
+    };
+    let expected_level = levels.level_for(expected_mode).unwrap_or("?");
+    let mut out = format!("risk_score {} maps to {}/{}, but the record says {}", score, expected_mode, expected_level, says);
+    let severity = |mode: &str| MODES.iter().position(|m| *m == mode);
+    match (severity(expected_mode), severity(&record.mode)) {
+        (_, None) => out.push_str(&format!("; `{}` is not one of the five modes.", record.mode)),
+        (Some(e), Some(a)) if a != e => {
+            let n = a.abs_diff(e);
+            let buckets = match n {
+                1 => "one bucket".to_string(),
+                2 => "two buckets".to_string(),
+                3 => "three buckets".to_string(),
+                _ => format!("{} buckets", n),
+            };
+            let direction = if a > e { "too severe" } else { "too lenient" };
+            out.push_str(&format!("; the mode is {} {}", buckets, direction));
+            if !c2_holds(record, levels) {
+                out.push_str(&format!(
+                    ", and risk_level {} does not match it either",
+                    record.risk_level
+                ));
+            }
+            out.push('.');
+        }
+        _ => out.push_str(&format!(
+            "; the mode is right, but {} belongs with {}.",
+            record.mode, expected_level
+        )),
+    }
+    Some(out)
+}
+
 /// Returns true only if all invariants pass.
 pub fn is_valid(record: &RuntimeStateRecord) -> bool {
     validate_all(record).iter().all(|r| r.passed)
 }
 
+/// Returns true only if all invariants pass under the given options.
+pub fn is_valid_with(record: &RuntimeStateRecord, opts: &ValidationOptions) -> bool {
+    !validate_all_with(record, opts).iter().any(|r| r.is_violation())
+}
+
 // --- Range Invariants ---
 
-fn check_inv_r1(r: &RuntimeStateRecord) -> InvariantResult {
+// Treats -0.0 as 0.0 so details never show a confusing "-0".
+fn normalize_zero(v: f64) -> f64 {
+    if v == 0.0 { 0.0 } else { v }
+}
+
+// Range predicate shared by INV-R1/R2/R3.
+fn in_range(v: f64, opts: &ValidationOptions) -> bool {
+    (0.0..=1.0).contains(&v) && !(opts.strict_floats && v.is_subnormal())
+}
+
+// Range check shared by INV-R1/R2/R3. Returns a failure reason, if any.
+fn range_violation(name: &str, v: f64, opts: &ValidationOptions) -> Option<String> {
+    if in_range(v, opts) {
+        return None;
+    }
+    if !(0.0..=1.0).contains(&v) {
+        return Some(format!("{}={} outside [0.0, 1.0]", name, normalize_zero(v)));
+    }
+    Some(format!("{}={:e} is subnormal (bits=0x{:016x})", name, v, v.to_bits()))
+}
+
+// Code for a failed range check: subnormal if the value is otherwise in range.
+fn range_code(v: f64, opts: &ValidationOptions, out_of_range: ErrorCode, subnormal: ErrorCode) -> Option<ErrorCode> {
+    if in_range(v, opts) {
+        None
+    } else if (0.0..=1.0).contains(&v) {
+        Some(subnormal)
+    } else {
+        Some(out_of_range)
+    }
+}
+
+fn check_inv_r1(r: &RuntimeStateRecord, opts: &ValidationOptions) -> InvariantResult {
     let f = &r.functions;
-    let in_range = |v: f64| (0.0..=1.0).contains(&v) && !v.is_nan();
-    let ok = in_range(f.baseline) && in_range(f.norm)
-        && in_range(f.stability) && in_range(f.meta_control);
+    let values = [
+        ("baseline", f.baseline),
+        ("norm", f.norm),
+        ("stability", f.stability),
+        ("meta_control", f.meta_control),
+    ];
+    let violations: Vec<String> = values.iter().filter_map(|&(name, v)| range_violation(name, v, opts)).collect();
+    let ok = violations.is_empty();
+    // An out-of-range value outranks a subnormal one.
+    let codes = values
+        .iter()
+        .filter_map(|&(_, v)| range_code(v, opts, ErrorCode::FunctionOutOfRange, ErrorCode::FunctionSubnormal));
     InvariantResult {
         id: "INV-R1",
         passed: ok,
+        code: codes.reduce(|a, b| if b == ErrorCode::FunctionOutOfRange { b } else { a }),
         detail: if ok {
             "All function values in [0.0, 1.0].".into()
         } else {
-            format!(
-                "Function value(s) out of range: baseline={}, norm={}, stability={}, meta_control={}",
-                f.baseline, f.norm, f.stability, f.meta_control
-            )
+            format!("Function value(s) out of range: {}", violations.join(", "))
         },
     }
 }
 
-fn check_inv_r2(r: &RuntimeStateRecord) -> InvariantResult {
-    let ok = (0.0..=1.0).contains(&r.stability_score) && !r.stability_score.is_nan();
+fn check_inv_r2(r: &RuntimeStateRecord, opts: &ValidationOptions) -> InvariantResult {
+    let violation = range_violation("stability_score", r.stability_score, opts);
     InvariantResult {
         id: "INV-R2",
-        passed: ok,
-        detail: format!("stability_score={}", r.stability_score),
+        passed: violation.is_none(),
+        code: range_code(r.stability_score, opts, ErrorCode::StabilityScoreOutOfRange, ErrorCode::StabilityScoreSubnormal),
+        detail: violation
+            .unwrap_or_else(|| format!("stability_score={}", normalize_zero(r.stability_score))),
     }
 }
 
-fn check_inv_r3(r: &RuntimeStateRecord) -> InvariantResult {
-    let ok = (0.0..=1.0).contains(&r.risk_score) && !r.risk_score.is_nan();
+fn check_inv_r3(r: &RuntimeStateRecord, opts: &ValidationOptions) -> InvariantResult {
+    let violation = range_violation("risk_score", r.risk_score, opts);
     InvariantResult {
         id: "INV-R3",
-        passed: ok,
-        detail: format!("risk_score={}", r.risk_score),
+        passed: violation.is_none(),
+        code: range_code(r.risk_score, opts, ErrorCode::RiskScoreOutOfRange, ErrorCode::RiskScoreSubnormal),
+        detail: violation.unwrap_or_else(|| format!("risk_score={}", normalize_zero(r.risk_score))),
     }
 }
 
@@ -83,31 +528,77 @@ fn check_inv_r4(r: &RuntimeStateRecord) -> InvariantResult {
     InvariantResult {
         id: "INV-R4",
         passed: ok,
+        code: (!ok).then_some(ErrorCode::TimestampZero),
         detail: format!("timestamp={}", r.timestamp),
     }
 }
 
-// --- Consistency Invariants ---
+// --- Opt-in Demo Aggregation Check ---
 
-fn check_inv_c1(r: &RuntimeStateRecord) -> InvariantResult {
-    let expected = demo_partition_map(r.risk_score);
-    let ok = expected.map_or(false, |m| m == r.mode);
+fn check_inv_r6(r: &RuntimeStateRecord, eps: f64) -> InvariantResult {
+    // DEMO-ONLY: compares against the demonstration mean, not production logic.
+    let expected = demo_stability_score(&r.functions);
+    let ok = (r.stability_score - expected).abs() <= eps;
     InvariantResult {
-        id: "INV-C1",
+        id: "INV-R6",
         passed: ok,
+        code: (!ok).then_some(ErrorCode::AggregationMismatch),
         detail: format!(
-            "risk_score={} → expected mode={:?}, actual mode={}",
-            r.risk_score, expected, r.mode
+            "stability_score={}, demo aggregation={} (eps={})",
+            r.stability_score, expected, eps
         ),
     }
 }
 
-fn check_inv_c2(r: &RuntimeStateRecord) -> InvariantResult {
-    let expected = mode_to_risk_level(&r.mode);
-    let ok = expected.map_or(false, |l| l == r.risk_level);
+// --- Consistency Invariants ---
+
+// The risk_score that is classified, rounded if `classify_precision` is set.
+fn classified_score(r: &RuntimeStateRecord, opts: &ValidationOptions) -> f64 {
+    match opts.classify_precision {
+        Some(decimals) => round_to_decimals(r.risk_score, decimals),
+        None => r.risk_score,
+    }
+}
+
+fn expected_mode(r: &RuntimeStateRecord, opts: &ValidationOptions) -> Option<&'static str> {
+    opts.partition_map.classify(classified_score(r, opts))
+}
+
+fn c1_holds(r: &RuntimeStateRecord, opts: &ValidationOptions) -> bool {
+    expected_mode(r, opts).is_some_and(|m| m == r.mode)
+}
+
+fn c2_holds(r: &RuntimeStateRecord, levels: &LevelMap) -> bool {
+    levels.level_for(&r.mode).is_some_and(|l| l == r.risk_level)
+}
+
+fn check_inv_c1(r: &RuntimeStateRecord, opts: &ValidationOptions) -> InvariantResult {
+    let expected = expected_mode(r, opts);
+    let ok = c1_holds(r, opts);
+    let score = match opts.classify_precision {
+        Some(decimals) => format!(
+            "{} (rounded to {} decimals: {})",
+            normalize_zero(r.risk_score),
+            decimals,
+            normalize_zero(classified_score(r, opts))
+        ),
+        None => normalize_zero(r.risk_score).to_string(),
+    };
+    InvariantResult {
+        id: "INV-C1",
+        passed: ok,
+        code: (!ok).then_some(ErrorCode::ModeScoreMismatch),
+        detail: format!("risk_score={} → expected mode={:?}, actual mode={}", score, expected, r.mode),
+    }
+}
+
+fn check_inv_c2(r: &RuntimeStateRecord, levels: &LevelMap) -> InvariantResult {
+    let expected = levels.level_for(&r.mode);
+    let ok = c2_holds(r, levels);
     InvariantResult {
         id: "INV-C2",
         passed: ok,
+        code: (!ok).then_some(ErrorCode::LevelModeMismatch),
         detail: format!(
             "mode={} → expected risk_level={:?}, actual risk_level={}",
             r.mode, expected, r.risk_level
@@ -115,14 +606,13 @@ fn check_inv_c2(r: &RuntimeStateRecord) -> InvariantResult {
     }
 }
 
-fn check_inv_c3(r: &RuntimeStateRecord) -> InvariantResult {
+fn check_inv_c3(r: &RuntimeStateRecord, opts: &ValidationOptions) -> InvariantResult {
     // INV-C3: INV-C1 ∧ INV-C2 → risk_level fully determined by risk_score.
-    let c1 = check_inv_c1(r).passed;
-    let c2 = check_inv_c2(r).passed;
-    let ok = c1 && c2;
+    let ok = c1_holds(r, opts) && c2_holds(r, &opts.level_map);
     InvariantResult {
         id: "INV-C3",
         passed: ok,
+        code: (!ok).then_some(ErrorCode::InconsistentClassification),
         detail: if ok {
             "mode and risk_level are mutually consistent with risk_score.".into()
         } else {
@@ -131,28 +621,107 @@ fn check_inv_c3(r: &RuntimeStateRecord) -> InvariantResult {
     }
 }
 
+fn check_inv_c4(r: &RuntimeStateRecord, opts: &ValidationOptions) -> InvariantResult {
+    // INV-C4: risk_score → mode → risk_level (C1, C2) must agree with
+    // risk_score → risk_level taken directly. The direct path always uses the
+    // normative partition, so custom maps that reorder levels fail here.
+    let levels = &opts.level_map;
+    let direct = risk_score_to_level(classified_score(r, opts));
+    let ok = c1_holds(r, opts) && c2_holds(r, levels) && direct == Some(r.risk_level.as_str());
+    InvariantResult {
+        id: "INV-C4",
+        passed: ok,
+        code: (!ok).then_some(ErrorCode::DerivationMismatch),
+        detail: format!(
+            "risk_score={} → mode={:?} → risk_level={:?}; direct risk_level={:?}; actual mode={}, risk_level={}",
+            normalize_zero(r.risk_score),
+            expected_mode(r, opts),
+            expected_mode(r, opts).and_then(|m| levels.level_for(m)),
+            direct,
+            r.mode,
+            r.risk_level
+        ),
+    }
+}
+
+fn check_inv_c5(r: &RuntimeStateRecord, opts: &ValidationOptions) -> InvariantResult {
+    // INV-C5: the stored classification is the one reclassify_record would
+    // write. A risk_score that maps to no mode is left to INV-R3.
+    let stale = reclassify_record(r, opts).is_some_and(|f| (&f.mode, &f.risk_level) != (&r.mode, &r.risk_level));
+    InvariantResult {
+        id: "INV-C5",
+        passed: !stale,
+        code: stale.then_some(ErrorCode::StaleClassification),
+        detail: match explain_consistency_failure_with(r, opts).filter(|_| stale) {
+            Some(explanation) => format!("Stale classification: {}", explanation),
+            None => format!("mode={}, risk_level={} match the recomputed classification", r.mode, r.risk_level),
+        },
+    }
+}
+
+// --- Quality Heuristics ---
+
+fn check_inv_q1(r: &RuntimeStateRecord) -> InvariantResult {
+    // INV-Q1: four bitwise-equal function values usually come from a stubbed
+    // or uninitialized emitter rather than a real measurement.
+    let f = &r.functions;
+    let bits = f.baseline.to_bits();
+    let degenerate = [f.norm, f.stability, f.meta_control].iter().all(|v| v.to_bits() == bits);
+    InvariantResult {
+        id: "INV-Q1",
+        passed: !degenerate,
+        code: degenerate.then_some(ErrorCode::DegenerateFunctions),
+        detail: if degenerate {
+            format!("all four functions = {} (stubbed emitter?)", f.baseline)
+        } else {
+            "functions differ".to_string()
+        },
+    }
+}
+
 // --- Structural Invariants ---
 
-fn check_inv_s1(r: &RuntimeStateRecord) -> InvariantResult {
+fn s1_holds(r: &RuntimeStateRecord) -> bool {
     // All 8 required fields present — guaranteed by struct deserialization,
     // but we verify no empty strings for string fields.
-    let ok = !r.spec_version.is_empty()
+    !r.spec_version.is_empty()
         && !r.schema_version.is_empty()
         && !r.mode.is_empty()
-        && !r.risk_level.is_empty();
+        && !r.risk_level.is_empty()
+}
+
+fn check_inv_s1(r: &RuntimeStateRecord) -> InvariantResult {
+    let ok = s1_holds(r);
     InvariantResult {
         id: "INV-S1",
         passed: ok,
+        code: (!ok).then_some(ErrorCode::MissingField),
         detail: "All eight required fields present.".into(),
     }
 }
 
-fn check_inv_s2(_r: &RuntimeStateRecord) -> InvariantResult {
+fn s2_holds(r: &RuntimeStateRecord) -> bool {
     // No additional fields — enforced by #[serde(deny_unknown_fields)] at parse time.
-    // At validation time on a parsed struct, this is inherently satisfied.
+    // The one field the struct knows beyond 1.0.0 is gated on schema_version.
+    r.sequence_number.is_none() || supports_sequence_number(&r.schema_version)
+}
+
+fn check_inv_s2(r: &RuntimeStateRecord) -> InvariantResult {
+    if !s2_holds(r) {
+        return InvariantResult {
+            id: "INV-S2",
+            passed: false,
+            code: Some(ErrorCode::UnsupportedField),
+            detail: format!(
+                "sequence_number requires schema_version >= {}, got {}",
+                SEQUENCE_SCHEMA_VERSION, r.schema_version
+            ),
+        };
+    }
     InvariantResult {
         id: "INV-S2",
         passed: true,
+        code: None,
         detail: "No additional fields (enforced by strict deserialization).".into(),
     }
 }
@@ -162,18 +731,142 @@ fn check_inv_s3(r: &RuntimeStateRecord) -> InvariantResult {
     InvariantResult {
         id: "INV-S3",
         passed: ok,
+        code: (!ok).then_some(ErrorCode::SpecVersionMismatch),
         detail: format!("spec_version={}, expected={}", r.spec_version, SPEC_VERSION),
     }
 }
 
+// Why a schema_version is not a valid MAJOR.MINOR.PATCH version.
+enum SemverViolation<'a> {
+    ComponentCount(usize),
+    Empty,
+    NonNumeric(&'a str),
+    LeadingZero(&'a str),
+    Overflow(&'a str),
+}
+
+// Checks MAJOR.MINOR.PATCH: three decimal u32 components without leading
+// zeros (a lone "0" is fine). Allocation-free.
+fn semver_violation(version: &str) -> Option<SemverViolation<'_>> {
+    let count = version.split('.').count();
+    if count != 3 {
+        return Some(SemverViolation::ComponentCount(count));
+    }
+    version.split('.').find_map(|p| {
+        if p.is_empty() {
+            Some(SemverViolation::Empty)
+        } else if !p.bytes().all(|b| b.is_ascii_digit()) {
+            Some(SemverViolation::NonNumeric(p))
+        } else if p.len() > 1 && p.starts_with('0') {
+            Some(SemverViolation::LeadingZero(p))
+        } else if p.parse::<u32>().is_err() {
+            Some(SemverViolation::Overflow(p))
+        } else {
+            None
+        }
+    })
+}
+
+fn s4_holds(r: &RuntimeStateRecord) -> bool {
+    semver_violation(&r.schema_version).is_none()
+}
+
 fn check_inv_s4(r: &RuntimeStateRecord) -> InvariantResult {
-    // Valid semver: MAJOR.MINOR.PATCH, all numeric.
-    let parts: Vec<&str> = r.schema_version.split('.').collect();
-    let ok = parts.len() == 3 && parts.iter().all(|p| p.parse::<u32>().is_ok());
+    let violation = semver_violation(&r.schema_version);
+    let reason = match &violation {
+        None => String::new(),
+        Some(SemverViolation::ComponentCount(n)) => format!(" has {} component(s), expected 3", n),
+        Some(SemverViolation::Empty) => " has an empty component".to_string(),
+        Some(SemverViolation::NonNumeric(p)) => format!(" component `{}` is not numeric", p),
+        Some(SemverViolation::LeadingZero(p)) => format!(" component `{}` has a leading zero", p),
+        Some(SemverViolation::Overflow(p)) => format!(" component `{}` exceeds {}", p, u32::MAX),
+    };
     InvariantResult {
         id: "INV-S4",
+        passed: violation.is_none(),
+        code: violation.is_some().then_some(ErrorCode::InvalidSchemaVersion),
+        detail: format!("schema_version={}{}", r.schema_version, reason),
+    }
+}
+
+/// A span of accepted schema versions for INV-S6, e.g. `>=1.0.0, <2.0.0`.
+///
+/// The range is a comma-separated list of comparators, each one of `>=`,
+/// `>`, `<=`, `<` or `=` (which may be omitted) followed by a
+/// MAJOR.MINOR.PATCH version. A version is in range when it satisfies every
+/// comparator; versions that fail INV-S4 never are.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaVersionRange {
+    comparators: Vec<(&'static str, [u32; 3])>,
+}
+
+impl SchemaVersionRange {
+    /// Parses a range such as `>=1.0.0, <2.0.0` or `1.1.0`.
+    pub fn parse(range: &str) -> Result<Self, PmatrixError> {
+        let invalid = |why: String| PmatrixError::Config(format!("schema version range `{}`: {}", range, why));
+        let comparators = range
+            .split(',')
+            .map(|part| {
+                let part = part.trim();
+                if part.is_empty() {
+                    return Err(invalid("empty comparator".to_string()));
+                }
+                let (op, version) = [">=", "<=", ">", "<", "="]
+                    .iter()
+                    .find_map(|op| part.strip_prefix(op).map(|v| (*op, v.trim_start())))
+                    .unwrap_or(("=", part));
+                let version = version_triple(version)
+                    .ok_or_else(|| invalid(format!("`{}` is not MAJOR.MINOR.PATCH", version)))?;
+                Ok((op, version))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(SchemaVersionRange { comparators })
+    }
+
+    /// True if `version` is well-formed and satisfies every comparator.
+    pub fn contains(&self, version: &str) -> bool {
+        let Some(version) = version_triple(version) else {
+            return false;
+        };
+        self.comparators.iter().all(|(op, bound)| {
+            let ordering = version.cmp(bound);
+            match *op {
+                ">=" => ordering.is_ge(),
+                ">" => ordering.is_gt(),
+                "<=" => ordering.is_le(),
+                "<" => ordering.is_lt(),
+                _ => ordering.is_eq(),
+            }
+        })
+    }
+}
+
+impl std::fmt::Display for SchemaVersionRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, (op, [major, minor, patch])) in self.comparators.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ", " };
+            write!(f, "{}{}{}.{}.{}", sep, op, major, minor, patch)?;
+        }
+        Ok(())
+    }
+}
+
+// The components of a version that passes INV-S4.
+fn version_triple(version: &str) -> Option<[u32; 3]> {
+    if semver_violation(version).is_some() {
+        return None;
+    }
+    let mut parts = version.split('.').map(|p| p.parse().expect("checked by semver_violation"));
+    Some([parts.next()?, parts.next()?, parts.next()?])
+}
+
+fn check_inv_s6(r: &RuntimeStateRecord, range: &SchemaVersionRange) -> InvariantResult {
+    let ok = range.contains(&r.schema_version);
+    InvariantResult {
+        id: "INV-S6",
         passed: ok,
-        detail: format!("schema_version={}", r.schema_version),
+        code: (!ok).then_some(ErrorCode::SchemaVersionOutOfRange),
+        detail: format!("schema_version={}, accepted={}", r.schema_version, range),
     }
 }
 
@@ -185,18 +878,89 @@ fn check_inv_t1_note() -> InvariantResult {
     InvariantResult {
         id: "INV-T1",
         passed: true,
+        code: None,
         detail: "Stream-level invariant. Not checkable on a single record. \
                  Use validate_stream() for sequential validation.".into(),
     }
 }
 
+/// Encodes the failed invariants of one record as a bitmask: bit `i` is set
+/// when `INVARIANTS[i]` failed (INV-R1 is bit 0, INV-T1 bit 11), and bit
+/// `12 + j` when `OPTIONAL_INVARIANTS[j]` failed. Zero means conforming;
+/// warnings are not encoded.
+pub fn violation_bitmask(results: &[InvariantResult]) -> u32 {
+    let ids = INVARIANTS.iter().chain(OPTIONAL_INVARIANTS.iter());
+    results
+        .iter()
+        .filter(|r| r.is_violation())
+        .filter_map(|r| ids.clone().position(|(id, _)| *id == r.id))
+        .fold(0, |mask, bit| mask | 1 << bit)
+}
+
+/// Counts how many records failed each invariant across a batch.
+///
+/// Only invariants that failed at least once appear. Stream-level INV-T1,
+/// which is checked separately, and warnings are not included.
+pub fn invariant_failure_counts(results: &[Vec<InvariantResult>]) -> BTreeMap<&'static str, usize> {
+    let mut counts = BTreeMap::new();
+    for r in results.iter().flatten().filter(|r| r.is_violation()) {
+        *counts.entry(r.id).or_insert(0) += 1;
+    }
+    counts
+}
+
 /// Validates INV-T1 across a sequence of records.
 /// Returns the index of the first violation, or None if all pass.
 pub fn validate_stream_t1(records: &[RuntimeStateRecord]) -> Option<usize> {
-    for i in 1..records.len() {
-        if records[i].timestamp < records[i - 1].timestamp {
-            return Some(i);
+    (1..records.len()).find(|&i| records[i].timestamp < records[i - 1].timestamp)
+}
+
+/// Checks `sequence_number` continuity across a sequence of records.
+///
+/// Each numbered record must carry exactly the previous numbered record's
+/// sequence_number plus one. Returns the indices of records that break this
+/// (a gap, repeat or decrease); records without the field are skipped.
+pub fn validate_sequence(records: &[RuntimeStateRecord]) -> Vec<usize> {
+    let mut last: Option<u64> = None;
+    let mut bad = Vec::new();
+    for (i, r) in records.iter().enumerate() {
+        let Some(seq) = r.sequence_number else { continue };
+        if last.is_some_and(|prev| prev.checked_add(1) != Some(seq)) {
+            bad.push(i);
         }
+        last = Some(seq);
+    }
+    bad
+}
+
+/// Checks the opt-in INV-T2, strictly increasing timestamps, across a
+/// sequence of records. Returns the index of the first record whose timestamp
+/// is `<=` its predecessor's, or None if all pass.
+///
+/// This is stricter than INV-T1, which permits equal consecutive timestamps.
+/// It is not part of the specification; use it for datasets whose ordering
+/// contract forbids duplicates.
+pub fn validate_strict_monotonic(records: &[RuntimeStateRecord]) -> Option<usize> {
+    (1..records.len()).find(|&i| !check_inv_t2(Some(&records[i - 1]), &records[i]).passed)
+}
+
+/// INV-T2 for one record of a stream: its timestamp is greater than that of
+/// `previous`, the record before it. The first record (`previous` None)
+/// always passes.
+pub fn check_inv_t2(previous: Option<&RuntimeStateRecord>, record: &RuntimeStateRecord) -> InvariantResult {
+    let Some(previous) = previous else {
+        return InvariantResult {
+            id: "INV-T2",
+            passed: true,
+            code: None,
+            detail: format!("timestamp={} (first record)", record.timestamp),
+        };
+    };
+    let ok = record.timestamp > previous.timestamp;
+    InvariantResult {
+        id: "INV-T2",
+        passed: ok,
+        code: (!ok).then_some(ErrorCode::TimestampNotIncreasing),
+        detail: format!("timestamp={}, previous={}", record.timestamp, previous.timestamp),
     }
-    None
 }
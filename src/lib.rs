@@ -6,16 +6,81 @@ pub mod schema;
 pub mod mode;
 pub mod invariants;
 pub mod demo;
+pub mod error;
+pub mod input;
+pub mod diff;
+#[cfg(feature = "canonical")]
+pub mod fingerprint;
+#[cfg(feature = "canonical")]
+pub mod manifest;
+#[cfg(feature = "canonical")]
+pub mod audit;
+pub mod report;
+pub mod stats;
+pub mod watch;
+pub mod clock;
+pub mod lint;
+pub mod output;
+pub mod filter;
+pub mod predicate;
+pub mod doctor;
+pub mod redact;
+pub mod sample;
+pub mod precision;
+pub mod sink;
+pub mod summary;
+#[cfg(feature = "json-schema")]
+pub mod schema_check;
+pub mod info;
+pub mod packed;
 
 use schema::{Functions, RuntimeStateRecord, SCHEMA_VERSION, SPEC_VERSION};
 use mode::{demo_partition_map, mode_to_risk_level};
-use demo::{demo_stability_score, demo_risk_score};
-use invariants::{validate_all, is_valid, InvariantResult};
+use demo::{demo_risk_score, MeanAggregator, ScoreAggregator};
+use invariants::{
+    validate_all, validate_all_with, validate_stream_t1, is_valid, InvariantResult, ValidationOptions,
+};
+use clock::{Clock, SystemClock};
+use diff::{diff_records, FieldDiff};
+use serde::Deserialize;
+pub use error::PmatrixError;
 
-use std::time::{SystemTime, UNIX_EPOCH};
+/// Named inputs for `emit_demo_record_from`, so that function values cannot
+/// be transposed by position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmitInputs {
+    pub functions: Functions,
+    /// Unix timestamp; None uses the current time.
+    pub timestamp: Option<u64>,
+}
+
+/// Emits a demonstration runtime state record from named inputs.
+///
+/// WARNING: Uses demonstration aggregation logic only.
+/// Production implementations use proprietary evaluation pipelines.
+pub fn emit_demo_record_from(inputs: EmitInputs) -> Result<RuntimeStateRecord, PmatrixError> {
+    let f = inputs.functions;
+    emit_demo_record_with_clock(f.baseline, f.norm, f.stability, f.meta_control, inputs.timestamp, &SystemClock)
+}
+
+/// Like `emit_demo_record_from`, computing stability_score with `aggregator`
+/// instead of the mean. Such records fail the opt-in INV-R6 check and are
+/// reported as drift by `replay_drift`, which both assume the mean.
+///
+/// WARNING: Every aggregator is demonstration logic only.
+pub fn emit_demo_record_aggregated(
+    inputs: EmitInputs,
+    aggregator: &dyn ScoreAggregator,
+) -> Result<RuntimeStateRecord, PmatrixError> {
+    let f = inputs.functions;
+    emit_with(f.baseline, f.norm, f.stability, f.meta_control, inputs.timestamp, &SystemClock, aggregator)
+}
 
 /// Emits a demonstration runtime state record from four function values.
 ///
+/// Equivalent to `emit_demo_record_from`; prefer that to avoid transposing
+/// positional arguments.
+///
 /// WARNING: Uses demonstration aggregation logic only.
 /// Production implementations use proprietary evaluation pipelines.
 pub fn emit_demo_record(
@@ -24,7 +89,32 @@ pub fn emit_demo_record(
     stability: f64,
     meta_control: f64,
     timestamp: Option<u64>,
-) -> Result<RuntimeStateRecord, String> {
+) -> Result<RuntimeStateRecord, PmatrixError> {
+    emit_demo_record_with_clock(baseline, norm, stability, meta_control, timestamp, &SystemClock)
+}
+
+/// Like `emit_demo_record`, but reads the default timestamp from `clock`
+/// instead of the system time.
+pub fn emit_demo_record_with_clock(
+    baseline: f64,
+    norm: f64,
+    stability: f64,
+    meta_control: f64,
+    timestamp: Option<u64>,
+    clock: &dyn Clock,
+) -> Result<RuntimeStateRecord, PmatrixError> {
+    emit_with(baseline, norm, stability, meta_control, timestamp, clock, &MeanAggregator)
+}
+
+fn emit_with(
+    baseline: f64,
+    norm: f64,
+    stability: f64,
+    meta_control: f64,
+    timestamp: Option<u64>,
+    clock: &dyn Clock,
+    aggregator: &dyn ScoreAggregator,
+) -> Result<RuntimeStateRecord, PmatrixError> {
     // Validate input ranges
     for (name, val) in [
         ("baseline", baseline),
@@ -33,10 +123,13 @@ pub fn emit_demo_record(
         ("meta_control", meta_control),
     ] {
         if val.is_nan() || val.is_infinite() {
-            return Err(format!("{} is NaN or infinite", name));
+            return Err(PmatrixError::Encode(format!("{} is NaN or infinite", name)));
         }
         if !(0.0..=1.0).contains(&val) {
-            return Err(format!("{} = {} is outside [0.0, 1.0]", name, val));
+            return Err(PmatrixError::Encode(format!(
+                "{} = {} is outside [0.0, 1.0]",
+                name, val
+            )));
         }
     }
 
@@ -47,25 +140,21 @@ pub fn emit_demo_record(
         meta_control,
     };
 
-    let stability_score = demo_stability_score(&functions);
+    let stability_score = aggregator.stability_score(&functions);
     let risk_score = demo_risk_score(stability_score);
 
     let mode = demo_partition_map(risk_score)
-        .ok_or_else(|| format!("risk_score {} out of range", risk_score))?;
+        .ok_or_else(|| PmatrixError::Encode(format!("risk_score {} out of range", risk_score)))?;
     let risk_level = mode_to_risk_level(mode)
-        .ok_or_else(|| format!("unknown mode {}", mode))?;
+        .ok_or_else(|| PmatrixError::Encode(format!("unknown mode {}", mode)))?;
 
-    let ts = timestamp.unwrap_or_else(|| {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs()
-    });
+    let ts = timestamp.unwrap_or_else(|| clock.now_secs());
 
     Ok(RuntimeStateRecord {
         spec_version: SPEC_VERSION.to_string(),
         schema_version: SCHEMA_VERSION.to_string(),
         timestamp: ts,
+        sequence_number: None,
         functions,
         stability_score,
         risk_score,
@@ -74,6 +163,57 @@ pub fn emit_demo_record(
     })
 }
 
+/// Emits a demonstration stream from `(baseline, norm, stability, meta_control, timestamp)` tuples.
+///
+/// Fails with the index of the first tuple that cannot be encoded, or of the
+/// first record whose timestamp breaks INV-T1 (reported as
+/// `PmatrixError::Validation(vec!["INV-T1"])`).
+pub fn collect_stream<I>(iter: I) -> Result<Vec<RuntimeStateRecord>, (usize, PmatrixError)>
+where
+    I: IntoIterator<Item = (f64, f64, f64, f64, u64)>,
+{
+    let records = iter
+        .into_iter()
+        .enumerate()
+        .map(|(i, (b, n, s, m, ts))| emit_demo_record(b, n, s, m, Some(ts)).map_err(|e| (i, e)))
+        .collect::<Result<Vec<_>, _>>()?;
+    if let Some(i) = validate_stream_t1(&records) {
+        return Err((i, PmatrixError::Validation(vec!["INV-T1"])));
+    }
+    Ok(records)
+}
+
+/// Re-derives stability_score, risk_score, mode and risk_level from the
+/// record's functions with the demonstration aggregation logic.
+///
+/// All other fields are kept as they are. Fails under the same conditions as
+/// `emit_demo_record`.
+///
+/// WARNING: Uses demonstration aggregation logic only. Normalizing a
+/// production record replaces its derived fields with demo values.
+pub fn normalize_record(record: &RuntimeStateRecord) -> Result<RuntimeStateRecord, PmatrixError> {
+    let f = &record.functions;
+    let derived = emit_demo_record(f.baseline, f.norm, f.stability, f.meta_control, Some(record.timestamp))?;
+    Ok(RuntimeStateRecord {
+        stability_score: derived.stability_score,
+        risk_score: derived.risk_score,
+        mode: derived.mode,
+        risk_level: derived.risk_level,
+        ..record.clone()
+    })
+}
+
+/// Returns the derived fields whose stored values disagree with the ones
+/// `normalize_record` re-derives, comparing scores within `eps`. An empty
+/// list means the record's producer matches the demo aggregation.
+///
+/// This is independent of validation: a record can pass INV-C1 to INV-C3
+/// with scores from a different aggregation. Fails under the same
+/// conditions as `normalize_record`.
+pub fn replay_drift(record: &RuntimeStateRecord, eps: f64) -> Result<Vec<FieldDiff>, PmatrixError> {
+    Ok(diff_records(record, &normalize_record(record)?, eps))
+}
+
 /// Validates a runtime state record against all 12 invariants (D1-A §4).
 ///
 /// Returns a list of invariant check results.
@@ -81,7 +221,54 @@ pub fn validate_record(record: &RuntimeStateRecord) -> Vec<InvariantResult> {
     validate_all(record)
 }
 
+/// Parses a JSON record strictly and validates it in one call.
+///
+/// Returns the parse error if the input is not a well-formed record, otherwise
+/// the invariant results (which may themselves contain failures).
+pub fn validate_json(input: &str) -> Result<Vec<InvariantResult>, PmatrixError> {
+    Ok(validate_all(&input::parse_strict(input)?))
+}
+
+/// Like `validate_json`, but deserializes from an in-memory JSON value
+/// without a string round-trip. Unknown fields are still rejected.
+pub fn validate_value(value: &serde_json::Value) -> Result<Vec<InvariantResult>, PmatrixError> {
+    Ok(validate_all(&RuntimeStateRecord::deserialize(value)?))
+}
+
+/// Validates a runtime state record using non-default validation options.
+pub fn validate_record_with(record: &RuntimeStateRecord, opts: &ValidationOptions) -> Vec<InvariantResult> {
+    validate_all_with(record, opts)
+}
+
+/// Validates each record of a batch independently.
+///
+/// Returns one result list per record, in input order. Stream-level INV-T1
+/// must be checked separately with `invariants::validate_stream_t1`.
+pub fn validate_records(records: &[RuntimeStateRecord]) -> Vec<Vec<InvariantResult>> {
+    records.iter().map(validate_all).collect()
+}
+
+/// Validates each record of a batch using non-default validation options.
+pub fn validate_records_with(
+    records: &[RuntimeStateRecord],
+    opts: &ValidationOptions,
+) -> Vec<Vec<InvariantResult>> {
+    records.iter().map(|r| validate_all_with(r, opts)).collect()
+}
+
 /// Returns true if the record satisfies all invariants.
 pub fn is_record_valid(record: &RuntimeStateRecord) -> bool {
     is_valid(record)
 }
+
+/// Returns the index of the first record that fails `is_record_valid`, or
+/// None if every record conforms. Helps locate where a pipeline started
+/// emitting malformed records.
+///
+/// This is a linear scan, not a true bisection: conformance need not be
+/// monotonic along a stream, and a scan finds the first failure either
+/// way. Only per-record invariants are checked; use `validate_stream_t1`
+/// for INV-T1.
+pub fn first_nonconforming(records: &[RuntimeStateRecord]) -> Option<usize> {
+    records.iter().position(|r| !is_valid(r))
+}
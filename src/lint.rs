@@ -0,0 +1,125 @@
+// lint.rs — Fixture Directory Linting
+//
+// Validates every record file under a directory, treating each file
+// independently. A file may hold a single record, a JSON array or NDJSON.
+
+use crate::input::{parse_stream_bounded, read_input, DEFAULT_MAX_RECORDS};
+use crate::invariants::{validate_all, validate_stream_t1};
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Default pattern for record files.
+pub const DEFAULT_GLOB: &str = "**/*.json";
+
+/// The verdict for one linted file.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FileVerdict {
+    /// Every record satisfies every invariant.
+    Conforming { records: usize },
+    /// At least one record, or the stream's INV-T1, fails.
+    NonConforming { records: usize, failed: Vec<&'static str> },
+    /// The file could not be read or parsed.
+    Error(String),
+}
+
+/// Matches a `/`-separated relative path against a glob pattern.
+///
+/// `*` matches any run of characters within one path segment, `?` a single
+/// character, and a `**` segment any number of whole segments (including none).
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| match_segments(rest, &path[skip..])),
+        Some((seg, rest)) => {
+            !path.is_empty() && match_segment(seg.as_bytes(), path[0].as_bytes()) && match_segments(rest, &path[1..])
+        }
+    }
+}
+
+fn match_segment(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| match_segment(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && match_segment(rest, &text[1..]),
+        Some((c, rest)) => text.first() == Some(c) && match_segment(rest, &text[1..]),
+    }
+}
+
+/// Recursively lists files under `dir` whose relative path matches `glob`
+/// and none of `ignore`. Paths are returned sorted. Symbolic links to files
+/// are listed, but symbolic links to directories are not followed, so a link
+/// cycle cannot make the walk run forever.
+pub fn find_record_files(dir: &Path, glob: &str, ignore: &[String]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let entry = entry?;
+            let path = entry.path();
+            // DirEntry::file_type does not follow symbolic links.
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if file_type.is_symlink() && path.is_dir() {
+                continue;
+            }
+            let relative = relative_path(dir, &path);
+            if glob_match(glob, &relative) && !ignore.iter().any(|p| glob_match(p, &relative)) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// The path of `path` relative to `dir`, with `/` separators.
+pub fn relative_path(dir: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(dir).unwrap_or(path);
+    relative
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Validates one record file: every record against all invariants and,
+/// for multi-record files, INV-T1 across them. A file of more than
+/// `DEFAULT_MAX_RECORDS` records is an `Error` verdict.
+pub fn lint_file(path: &Path) -> FileVerdict {
+    lint_file_bounded(path, DEFAULT_MAX_RECORDS)
+}
+
+/// Like `lint_file`, but a file holding more than `max_records` records is
+/// an `Error` verdict, as for `input::parse_stream_bounded`.
+pub fn lint_file_bounded(path: &Path, max_records: usize) -> FileVerdict {
+    let input = read_input(Some(path), false);
+    let records = match input.and_then(|input| parse_stream_bounded(&input, usize::MAX, max_records)) {
+        Ok(records) => records,
+        Err(e) => return FileVerdict::Error(e.to_string()),
+    };
+    let mut failed: Vec<&'static str> = Vec::new();
+    for r in records.iter().flat_map(validate_all).filter(|r| !r.passed) {
+        if !failed.contains(&r.id) {
+            failed.push(r.id);
+        }
+    }
+    if validate_stream_t1(&records).is_some() {
+        failed.push("INV-T1");
+    }
+    if failed.is_empty() {
+        FileVerdict::Conforming { records: records.len() }
+    } else {
+        FileVerdict::NonConforming { records: records.len(), failed }
+    }
+}
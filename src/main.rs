@@ -4,13 +4,111 @@
 //
 // Usage:
 //   pmatrix-encoder emit --baseline 0.25 --norm 0.70 --stability 0.30 --meta-control 0.20
+//   pmatrix-encoder emit --baseline 0.25 ... --count 100000 --array > stream.json
+//   pmatrix-encoder emit --baseline 0.25 ... --aggregator weighted --weights 2,1,1,1
+//   PMATRIX_BASELINE=0.25 PMATRIX_NORM=0.70 ... pmatrix-encoder emit
 //   pmatrix-encoder validate < record.json
+//   pmatrix-encoder validate --lenient < record-from-newer-emitter.json
+//   pmatrix-encoder validate --invert fixtures/bad/wrong-mode.json
+//   pmatrix-encoder validate --compare-to-example my-first-record.json
+//   pmatrix-encoder validate --batch --audit-log audit.jsonl stream.ndjson
+//   pmatrix-encoder validate --batch --json-pointer /state enveloped.ndjson
+//   pmatrix-encoder validate --batch --format markdown < stream.ndjson > report.md
+//   pmatrix-encoder validate --batch --format json stream.ndjson | jq 'select(.conforming == false)'
+//   pmatrix-encoder validate --batch --strict-monotonic < stream.ndjson
+//   pmatrix-encoder validate --level-map levels.json < record.json
+//   pmatrix-encoder validate --classify-precision 4 < record.json
+//   pmatrix-encoder validate --batch --recompute-mode --corrected-out fixed.ndjson fleet.ndjson
+//   pmatrix-encoder validate --batch --quality-checks stream.ndjson
+//   pmatrix-encoder validate --batch --structural-only --fail-fast stream.ndjson
+//   pmatrix-encoder validate --batch --schema-version-range '>=1.0.0, <2.0.0' fleet.ndjson
+//   pmatrix-encoder validate --batch --only-failures big-stream.ndjson
 //   echo '{"spec_version":...}' | pmatrix-encoder validate
+//   pmatrix-encoder inspect record.json
+//   pmatrix-encoder --input-format json inspect record.json
+//   pmatrix-encoder --functions-array validate --batch compact.ndjson
+//   pmatrix-encoder normalize --dry-run stream.ndjson
+//   pmatrix-encoder replay third-party.ndjson --eps 1e-6
+//   pmatrix-encoder bisect pipeline.ndjson
+//   pmatrix-encoder doctor my-first-record.json
+//   pmatrix-encoder verify record.json --fingerprint 3f2a...
+//   pmatrix-encoder seal --baseline 0.5 --norm 0.5 --stability 0.5 --meta-control 0.5 --out record.json
+//   pmatrix-encoder verify record.json --fingerprint-file record.sha256
+//   pmatrix-encoder manifest batch.ndjson > batch.manifest.json
+//   pmatrix-encoder verify-manifest batch.ndjson --manifest batch.manifest.json
+//   pmatrix-encoder stats stream.ndjson --percentiles 50,90,99
+//   pmatrix-encoder stats stream.ndjson --flatline-run 1000
+//   pmatrix-encoder stats huge.ndjson --streaming
+//   pmatrix-encoder stats stream.ndjson --sustained 30 --sustained-level L4
+//   pmatrix-encoder stats stream.ndjson --flapping 20 --flapping-changes 5
+//   pmatrix-encoder stats stream.ndjson --group-by mode
+//   pmatrix-encoder stats stream.ndjson --color always | less -R
+//   pmatrix-encoder stats stream.ndjson --timezone +09:00   (with the `chrono` feature)
+//   pmatrix-encoder check-schema record.json --schema team-schema.json
+//   pmatrix-encoder smooth stream.ndjson --window 10
+//   pmatrix-encoder smooth stream.ndjson --window 5 --dominant-mode
+//   pmatrix-encoder tally stream.ndjson
+//   pmatrix-encoder filter stream.ndjson --min-risk 0.6 --mode Alert,Halt
+//   pmatrix-encoder filter stream.ndjson --where 'risk_level >= L4 && norm < 0.3'
+//   pmatrix-encoder sample huge.ndjson --k 100 --seed 42
+//   pmatrix-encoder redact stream.ndjson --bucket > shareable.ndjson
+//   pmatrix-encoder convert stream.ndjson --to json > stream.json
+//   pmatrix-encoder watch emitter.ndjson --watch-interval-ms 250
+//   pmatrix-encoder watch emitter.ndjson --max-staleness 60
+//   pmatrix-encoder validate --batch --max-staleness 300 latest.ndjson
+//   pmatrix-encoder --exit-code-scheme legacy validate < record.json
+//   pmatrix-encoder diff-streams old.ndjson new.ndjson --align-by timestamp
+//   pmatrix-encoder lint fixtures/ --ignore 'drafts/**'
+//   pmatrix-encoder legend --format json --threshold-profile conservative
+//   pmatrix-encoder info | jq -e '.features | index("gzip")'
+//   pmatrix-encoder completions bash > /etc/bash_completion.d/pmatrix-encoder
 
-use clap::{Parser, Subcommand};
-use pmatrix_encoder::{emit_demo_record, validate_record};
-use pmatrix_encoder::schema::RuntimeStateRecord;
-use std::io::{self, Read};
+use clap::error::{ContextKind, ContextValue, ErrorKind};
+use clap::builder::{PossibleValue, PossibleValuesParser};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use pmatrix_encoder::{
+    emit_demo_record_aggregated, first_nonconforming, is_record_valid, normalize_record, replay_drift, validate_record, validate_record_with, EmitInputs,
+    PmatrixError,
+};
+use pmatrix_encoder::diff::{diff_records, diff_shape_against_example, diff_streams_aligned, Alignment, Side, StreamDiff};
+use pmatrix_encoder::invariants::{
+    check_inv_t2, explain_consistency_failure_with, reclassify_record, validate_stream_t1, validate_structural_with, InvariantResult, SchemaVersionRange, ValidationOptions,
+    DEMO_AGGREGATION_EPS, INVARIANTS, OPTIONAL_INVARIANTS, QUALITY_INVARIANTS,
+};
+use pmatrix_encoder::info::crate_info;
+use pmatrix_encoder::sink::{FailuresOnly, JsonSink, ResultSink, StdoutSink};
+use pmatrix_encoder::summary::BatchSummary;
+use pmatrix_encoder::lint::{find_record_files, lint_file_bounded, relative_path, FileVerdict, DEFAULT_GLOB};
+use pmatrix_encoder::mode::{builtin_profile, partition_table, LevelMap, THRESHOLD_PROFILES};
+use pmatrix_encoder::output::{write_records_array, write_records_ndjson};
+use pmatrix_encoder::filter::{filter_records, RecordFilter};
+use pmatrix_encoder::doctor::diagnose;
+use pmatrix_encoder::demo::{aggregator, AGGREGATORS};
+use pmatrix_encoder::predicate::Predicate;
+use pmatrix_encoder::precision::lossy_values;
+use pmatrix_encoder::redact::{redact_with, RedactOptions};
+use pmatrix_encoder::clock::{Clock, SystemClock};
+use pmatrix_encoder::sample::{reservoir_sample, SplitMix64};
+use pmatrix_encoder::schema::{Functions, RuntimeStateRecord, MODES, RISK_LEVELS};
+use pmatrix_encoder::input::{
+    cap_records, clean_input, expand_functions_arrays, open_input_as, parse_at_pointer_bounded, parse_lenient,
+    parse_stream_bounded, parse_strict, read_input, read_input_as, read_records, read_stream_bounded, InputFormat,
+    DEFAULT_MAX_RECORDS,
+};
+use pmatrix_encoder::report::{
+    format_timestamp, parse_utc_offset, render_batch_markdown_with, render_inspect,
+    render_diagnosis, render_legend, render_markdown_with, render_mode_stats, render_stats_colored, render_tally, render_text, render_text_failures, TimestampStyle,
+};
+use pmatrix_encoder::watch::{T1Outcome, T1Tracker, TailEvent, TailReader};
+use pmatrix_encoder::stats::{
+    annotate_dominant_mode, detect_flapping, detect_flatline, detect_flatline_within, mode_counts, risk_percentiles_with, stability_percentiles_with, stream_stats_iter, stream_stats_with, is_stale, record_age_secs, smooth, stats_by_mode, sustained_high_risk,
+    NanPolicy, DEFAULT_PERCENTILES,
+};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(
@@ -19,85 +117,1766 @@ use std::io::{self, Read};
     version
 )]
 struct Cli {
+    /// Treat stdin as gzip-compressed. Files ending in `.gz` or starting with
+    /// the gzip magic bytes are always decompressed (requires the `gzip` feature).
+    #[arg(long, global = true)]
+    gzip: bool,
+    /// Input encoding. `auto` sniffs it and rejects anything that is not JSON.
+    #[arg(long, global = true, value_enum, default_value_t = FormatArg::Auto)]
+    input_format: FormatArg,
+    /// UTC offset for RFC 3339 timestamps in text and markdown reports,
+    /// e.g. +02:00 (default UTC; requires the `chrono` feature).
+    #[arg(long, global = true)]
+    timezone: Option<String>,
+    /// Abort when the input holds more than N records, whether it is
+    /// loaded whole or streamed.
+    #[arg(long, global = true, value_name = "N", default_value_t = DEFAULT_MAX_RECORDS)]
+    max_records: usize,
+    /// Also accept `functions` as a four-element array
+    /// [baseline, norm, stability, meta_control], as written by compact
+    /// producers. Not supported by `stats --streaming`, `watch` and `lint`.
+    #[arg(long, global = true)]
+    functions_array: bool,
+    /// How failures map to exit codes; `legacy` keeps the pre-scheme codes
+    /// for existing scripts.
+    #[arg(long, global = true, value_enum, default_value_t = ExitCodeScheme::Standard)]
+    exit_code_scheme: ExitCodeScheme,
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    Auto,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum ConvertFormat {
+    Json,
+    Ndjson,
+}
+
+/// How record input is read, shared by all subcommands.
+#[derive(Clone, Copy)]
+struct Source {
+    gzip: bool,
+    format: Option<InputFormat>,
+    max_records: usize,
+    functions_array: bool,
+}
+
+impl Source {
+    fn read(&self, path: Option<&Path>) -> Result<String, PmatrixError> {
+        let input = read_input_as(path, self.gzip, self.format)?;
+        if self.functions_array {
+            return expand_functions_arrays(&input);
+        }
+        Ok(input)
+    }
+
+    /// Reads and parses a whole stream, honoring --max-records.
+    fn records(&self, path: Option<&Path>) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+        self.records_limit(path, usize::MAX)
+    }
+
+    /// Reads and parses at most `limit` records, honoring --max-records.
+    /// Records are parsed straight from the (decompressed) reader, so the
+    /// input text is never held in memory.
+    fn records_limit(&self, path: Option<&Path>, limit: usize) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+        if self.functions_array {
+            return parse_stream_bounded(&self.read(path)?, limit, self.max_records);
+        }
+        read_stream_bounded(open_input_as(path, self.gzip, self.format)?, limit, self.max_records)
+    }
+
+    /// Lazily reads NDJSON, honoring --input-format and failing once it
+    /// passes --max-records records.
+    fn stream(&self, path: Option<&Path>) -> Result<impl Iterator<Item = Result<RuntimeStateRecord, PmatrixError>>, PmatrixError> {
+        if self.functions_array {
+            return Err(PmatrixError::Config("--functions-array is not supported with --streaming".into()));
+        }
+        Ok(cap_records(read_records(open_input_as(path, self.gzip, self.format)?), self.max_records))
+    }
+}
+
 #[derive(Subcommand)]
 enum Commands {
     /// Emit a demonstration runtime state record from four function values.
+    ///
+    /// Each function value may also be supplied through its environment
+    /// variable; an explicit flag always takes precedence over the environment.
     Emit {
+        #[command(flatten)]
+        values: EmitValues,
+        /// Emit a stream of this many records, one second apart, as NDJSON.
+        #[arg(long, default_value_t = 1)]
+        count: u64,
+        /// With --count, write the stream as a single JSON array instead.
+        #[arg(long)]
+        array: bool,
+    },
+    /// Emit a demonstration record in canonical form together with its
+    /// fingerprint, for tamper-evident storage. Check it later with `verify`.
+    ///
+    /// With --out, writes the canonical record to that path and a
+    /// `sha256sum`-compatible sidecar next to it with the extension
+    /// `.sha256`. With --inline, prints `{"record": ..., "fingerprint": ...}`.
+    /// Sealing is idempotent for a fixed --timestamp.
+    Seal {
+        #[command(flatten)]
+        values: EmitValues,
+        /// Canonical record file to write, e.g. record.json.
+        #[arg(long, required_unless_present = "inline", conflicts_with = "inline")]
+        out: Option<PathBuf>,
+        /// Print the record and fingerprint as one JSON object instead.
         #[arg(long)]
-        baseline: f64,
+        inline: bool,
+    },
+    /// Validate a runtime state record (JSON file or stdin) against all 12 invariants.
+    Validate(ValidateArgs),
+    /// Validate a record and print it with classification and conformance notes.
+    Inspect {
+        /// Record file (defaults to stdin).
+        path: Option<PathBuf>,
+    },
+    /// DEMO-ONLY: re-derive scores, mode and risk_level from each record's
+    /// functions and print the normalized records as NDJSON.
+    Normalize {
+        /// Record or stream file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Print the fields normalization would change instead of the records.
+        /// Exits non-zero if any record would change.
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// DEMO-ONLY: re-derive each record of a stream and report whether its
+    /// stored scores, mode and risk_level match the demo aggregation.
+    ///
+    /// Exits non-zero if any record drifts or cannot be re-derived.
+    Replay {
+        /// Record or stream file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Tolerance for stability_score and risk_score.
+        #[arg(long, default_value_t = 1e-9)]
+        eps: f64,
+    },
+    /// Find the first record of a stream that fails a per-record invariant.
+    ///
+    /// Exits non-zero if there is one.
+    Bisect {
+        /// Stream file (defaults to stdin).
+        path: Option<PathBuf>,
+    },
+    /// Explain what is wrong with a possibly malformed record, most
+    /// fundamental problem first, with a hint for each.
+    ///
+    /// Works on input that does not parse as a record, or not even as JSON.
+    /// Exits non-zero if any problem is found.
+    Doctor {
+        /// Record file (defaults to stdin).
+        path: Option<PathBuf>,
+    },
+    /// Print the SHA-256 fingerprint of a record's canonical serialization.
+    Fingerprint {
+        /// Record file (defaults to stdin).
+        path: Option<PathBuf>,
+    },
+    /// Check a record against a stored fingerprint and validate it.
+    ///
+    /// Without --fingerprint or --fingerprint-file, the input must be a
+    /// sealed `{"record": ..., "fingerprint": ...}` object from `seal --inline`.
+    /// Exits non-zero if the fingerprint differs or any invariant fails.
+    Verify {
+        /// Record file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Expected fingerprint (hex).
+        #[arg(long, conflicts_with = "fingerprint_file")]
+        fingerprint: Option<String>,
+        /// File whose first word is the expected fingerprint, such as a
+        /// `.sha256` sidecar written by `seal`.
+        #[arg(long)]
+        fingerprint_file: Option<PathBuf>,
+    },
+    /// Print a JSON manifest of a stream: record count, time span, a
+    /// fingerprint of the whole sequence and per-mode counts.
+    Manifest {
+        /// Stream file (defaults to stdin).
+        path: Option<PathBuf>,
+    },
+    /// Recompute the manifest of a stream and compare it with a stored one.
+    ///
+    /// Exits non-zero if any manifest field differs.
+    VerifyManifest {
+        /// Stream file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Stored manifest JSON file.
+        #[arg(long)]
+        manifest: PathBuf,
+    },
+    /// Print aggregate statistics for a record stream (JSON array or NDJSON).
+    Stats {
+        /// Stream file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Comma-separated percentiles of risk_score and stability_score.
+        #[arg(long, value_delimiter = ',', default_values_t = DEFAULT_PERCENTILES)]
+        percentiles: Vec<f64>,
+        /// Report runs of at least N consecutive records with identical functions.
+        #[arg(long, value_name = "N")]
+        flatline_run: Option<usize>,
+        /// With --flatline-run, treat function values within this tolerance
+        /// as identical (default: bitwise equality).
+        #[arg(long, requires = "flatline_run")]
+        flatline_eps: Option<f64>,
+        /// Report periods of at least SECS (by timestamp) in which every
+        /// record is at or above --sustained-level.
+        #[arg(long, value_name = "SECS")]
+        sustained: Option<u64>,
+        /// Lowest risk_level counted by --sustained (default L4, i.e. Alert
+        /// or Halt).
+        #[arg(
+            long,
+            requires = "sustained",
+            default_value = "L4",
+            value_parser = PossibleValuesParser::new(RISK_LEVELS),
+        )]
+        sustained_level: String,
+        /// Report mode flapping: windows of N consecutive records with at
+        /// least --flapping-changes mode changes.
+        #[arg(long, value_name = "N")]
+        flapping: Option<usize>,
+        /// Minimum number of mode changes within a --flapping window.
+        #[arg(long, requires = "flapping", default_value_t = 3)]
+        flapping_changes: usize,
+        /// Also print the count, mean risk_score and time spent per group.
+        #[arg(long, value_enum)]
+        group_by: Option<GroupBy>,
+        /// Compute statistics over at most the first N records.
+        #[arg(long, value_name = "N")]
+        stop_after: Option<usize>,
+        /// Read NDJSON one record at a time in constant memory. Percentiles
+        /// and flatline detection need the whole stream and are skipped.
+        #[arg(long, conflicts_with_all = ["percentiles", "flatline_run", "sustained", "flapping", "group_by"])]
+        streaming: bool,
+        /// Highlight the mean risk and any INV-T1 violations with ANSI colors.
+        #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+        color: ColorChoice,
+    },
+    /// Transcode records between encodings. Single records, arrays and
+    /// NDJSON are accepted; only JSON and NDJSON output are supported.
+    Convert {
+        /// Record or stream file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Input shape: `json` is one record or an array, `ndjson` one record
+        /// per line. Skips --input-format sniffing; other shapes are rejected.
+        #[arg(long, value_enum)]
+        from: Option<ConvertFormat>,
+        /// Output encoding. `json` writes a single array.
+        #[arg(long, value_enum)]
+        to: ConvertFormat,
+        /// Convert records even if they fail invariants.
+        #[arg(long)]
+        no_validate: bool,
+    },
+    /// Validate a record against a JSON Schema file as well as the native
+    /// invariants and report whether the two verdicts agree. Exits with 1 on
+    /// disagreement. Needs the `json-schema` feature.
+    CheckSchema {
+        /// Record file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// JSON Schema to check the record against.
+        #[arg(long)]
+        schema: PathBuf,
+    },
+    /// Print each record with a trailing moving average of risk_score and
+    /// the mode it falls in, as NDJSON `{"record", "smoothed_risk_score",
+    /// "smoothed_mode"}` envelopes. The records themselves are unchanged.
+    Smooth {
+        /// Stream file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Number of records averaged, including the current one.
+        #[arg(long, value_name = "N")]
+        window: usize,
+        /// Instead annotate each record with the most frequent mode in the
+        /// window, as `{"record", "dominant_mode"}` (ties go to the more
+        /// severe mode).
         #[arg(long)]
-        norm: f64,
+        dominant_mode: bool,
+    },
+    /// Print the number of records in each mode, Optimal to Halt, and a total.
+    Tally {
+        /// Stream file (defaults to stdin).
+        path: Option<PathBuf>,
+    },
+    /// Print only the records within a risk_score band and/or with given
+    /// modes, as NDJSON in their original order.
+    Filter {
+        /// Stream file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Keep records with risk_score >= this value.
         #[arg(long)]
-        stability: f64,
-        #[arg(long, name = "meta-control")]
-        meta_control: f64,
-        /// Optional Unix timestamp (defaults to current time).
+        min_risk: Option<f64>,
+        /// Keep records with risk_score <= this value.
         #[arg(long)]
-        timestamp: Option<u64>,
+        max_risk: Option<f64>,
+        /// Keep records in these modes (comma-separated).
+        #[arg(long, value_delimiter = ',', value_parser = PossibleValuesParser::new(MODES))]
+        mode: Vec<String>,
+        /// Keep records matching this predicate, e.g.
+        /// "risk_score > 0.6 && mode != Optimal" or "risk_level >= L4".
+        #[arg(long = "where", value_name = "EXPR")]
+        predicate: Option<String>,
     },
-    /// Validate a runtime state record (JSON from stdin) against all 12 invariants.
-    Validate,
+    /// Print a uniform random sample of an NDJSON stream, holding only the
+    /// sample in memory. The sample is not in input order, so it is not a
+    /// valid stream (INV-T1).
+    Sample {
+        /// NDJSON stream file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Number of records to sample.
+        #[arg(long)]
+        k: usize,
+        /// Seed for a reproducible sample (defaults to the current time).
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Hide function values so a stream can be shared externally. Prints
+    /// NDJSON; mode, risk_level and timestamps are preserved.
+    Redact {
+        /// Stream file (defaults to stdin).
+        path: Option<PathBuf>,
+        /// Round function values to the midpoint of their fifth of [0, 1]
+        /// instead of zeroing them.
+        #[arg(long)]
+        bucket: bool,
+        /// Also set stability_score and risk_score to 0.
+        #[arg(long)]
+        blank_scores: bool,
+    },
+    /// Follow an NDJSON file, validating records as they are appended.
+    ///
+    /// Survives log rotation: truncation or replacement of the file re-opens
+    /// it from the start, and a missing file is waited for.
+    Watch {
+        /// NDJSON file to follow.
+        path: PathBuf,
+        /// Poll interval in milliseconds.
+        #[arg(long, default_value_t = 500)]
+        watch_interval_ms: u64,
+        /// Exit with code 5 once the latest record's timestamp is more than
+        /// SECS seconds behind the system clock.
+        #[arg(long, value_name = "SECS")]
+        max_staleness: Option<u64>,
+    },
+    /// Compare two record streams (JSON array or NDJSON files) for divergence.
+    DiffStreams {
+        /// Reference stream ("-" for stdin).
+        left: PathBuf,
+        /// Stream to compare against the reference.
+        right: PathBuf,
+        /// Tolerance for floating-point fields.
+        #[arg(long, default_value_t = 1e-9)]
+        eps: f64,
+        /// How records of the two streams are paired.
+        #[arg(long, value_enum, default_value_t = AlignBy::Index)]
+        align_by: AlignBy,
+    },
+    /// Validate every record file under a directory, one verdict per file.
+    ///
+    /// Exits non-zero if any file is non-conforming or unreadable.
+    Lint {
+        /// Directory to search recursively.
+        dir: PathBuf,
+        /// Pattern for record files, relative to the directory.
+        #[arg(long, default_value = DEFAULT_GLOB)]
+        glob: String,
+        /// Skip files matching this pattern (repeatable).
+        #[arg(long)]
+        ignore: Vec<String>,
+    },
+    /// Print the partition legend: each risk_score interval with its mode
+    /// and risk_level, for the selected profile and level map.
+    Legend {
+        /// Output format: `text` for a table, `json` for an array of
+        /// {mode, level, lower, upper, upper_inclusive} objects.
+        #[arg(long, value_enum, default_value_t = LegendFormat::Text)]
+        format: LegendFormat,
+        /// Named risk_score → mode partition.
+        #[arg(
+            long,
+            default_value = "default",
+            value_parser = PossibleValuesParser::new(
+                THRESHOLD_PROFILES.iter().map(|(name, _, help)| PossibleValue::new(*name).help(*help))
+            ),
+        )]
+        threshold_profile: String,
+        /// JSON file mapping each mode to its risk_level.
+        #[arg(long)]
+        level_map: Option<PathBuf>,
+    },
+    /// Print the crate version, supported spec and schema versions, enabled
+    /// features and invariant IDs of this binary as JSON.
+    Info,
+    /// Print a shell completion script to stdout.
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+}
+
+/// The four function values and optional timestamp of an emitted record.
+#[derive(Args)]
+struct EmitValues {
+    #[arg(long, env = "PMATRIX_BASELINE")]
+    baseline: f64,
+    #[arg(long, env = "PMATRIX_NORM")]
+    norm: f64,
+    #[arg(long, env = "PMATRIX_STABILITY")]
+    stability: f64,
+    #[arg(long, name = "meta-control", env = "PMATRIX_META_CONTROL")]
+    meta_control: f64,
+    /// Optional Unix timestamp (defaults to current time).
+    #[arg(long)]
+    timestamp: Option<u64>,
+    /// How the functions combine into stability_score (demo-only). Records
+    /// from any aggregator but `mean` fail --check-demo-aggregation.
+    #[arg(
+        long,
+        default_value = "mean",
+        value_parser = PossibleValuesParser::new(AGGREGATORS.iter().map(|(name, help)| PossibleValue::new(*name).help(*help))),
+    )]
+    aggregator: String,
+    /// Weights for --aggregator weighted, in the order baseline, norm,
+    /// stability, meta_control (comma-separated).
+    #[arg(long, value_delimiter = ',', num_args = 1)]
+    weights: Option<Vec<f64>>,
+}
+
+impl EmitValues {
+    fn emit(&self) -> Result<RuntimeStateRecord, PmatrixError> {
+        let aggregator = aggregator(&self.aggregator, self.weights.as_deref())?;
+        let inputs = EmitInputs {
+            functions: Functions {
+                baseline: self.baseline,
+                norm: self.norm,
+                stability: self.stability,
+                meta_control: self.meta_control,
+            },
+            timestamp: self.timestamp,
+        };
+        emit_demo_record_aggregated(inputs, aggregator.as_ref())
+    }
+}
+
+#[derive(Args)]
+struct ValidateArgs {
+    /// Record or stream file (defaults to stdin).
+    path: Option<PathBuf>,
+    /// Ignore unknown fields instead of rejecting the record, listing
+    /// each ignored field on stderr.
+    #[arg(long, conflicts_with = "batch")]
+    lenient: bool,
+    /// Validate a stream of records (JSON array or NDJSON), including INV-T1.
+    #[arg(long)]
+    batch: bool,
+    /// With --batch, validate at most the first N records.
+    #[arg(long, value_name = "N", requires = "batch")]
+    stop_after: Option<usize>,
+    /// With --batch, stop after the first record that fails an invariant.
+    /// Combined with --stop-after, whichever comes first ends the run.
+    #[arg(long, requires = "batch")]
+    fail_fast: bool,
+    /// Validate the record at this RFC 6901 pointer in each document, e.g.
+    /// /state. With --batch, a pointer to an array validates its elements.
+    #[arg(long, value_name = "POINTER", conflicts_with = "lenient")]
+    json_pointer: Option<String>,
+    /// With --batch, also check INV-T2: strictly increasing timestamps
+    /// (INV-T1 itself allows duplicates).
+    #[arg(long, requires = "batch")]
+    strict_monotonic: bool,
+    /// Output format.
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    format: OutputFormat,
+    /// Print only the invariants (or, with --batch, the records) that fail
+    /// or warn, followed by the usual summary. Not supported with
+    /// --format markdown.
+    #[arg(long)]
+    only_failures: bool,
+    /// Invert the exit code: exit 0 only if the input is non-conforming or
+    /// does not parse. The reported statuses are unchanged.
+    #[arg(long, conflicts_with = "max_staleness")]
+    invert: bool,
+    /// Exit with code 5 if the latest record's timestamp is more than SECS
+    /// seconds before --now. Invariant failures take precedence.
+    #[arg(long, value_name = "SECS")]
+    max_staleness: Option<u64>,
+    /// Current Unix time for --max-staleness (default: the system clock).
+    #[arg(long, value_name = "TIMESTAMP", requires = "max_staleness")]
+    now: Option<u64>,
+    /// Fail range invariants on subnormal float values.
+    #[arg(long)]
+    strict_floats: bool,
+    /// DEMO-ONLY: also check INV-R6, that stability_score equals the demo
+    /// aggregation of the functions. Production records are expected to fail.
+    #[arg(long)]
+    check_demo_aggregation: bool,
+    /// Also check INV-C4: the score → mode → level chain agrees with the
+    /// level of risk_score partitioned directly.
+    #[arg(long)]
+    check_chain: bool,
+    /// Also run data quality heuristics such as INV-Q1 (all four functions
+    /// identical). Their failures are reported as WARN and do not affect the
+    /// exit code.
+    #[arg(long)]
+    quality_checks: bool,
+    /// Warn on stderr about scores and function values with more than N
+    /// significant digits (default 12, or `=N`), a sign of float noise upstream.
+    /// Does not affect the exit code.
+    #[arg(long, value_name = "N", num_args = 0..=1, require_equals = true, default_missing_value = "12")]
+    warn_lossy_precision: Option<usize>,
+    /// Append one JSON line per validated record to this file:
+    /// {fingerprint, timestamp, conforming, violation_bitmask}.
+    #[arg(long, value_name = "PATH")]
+    audit_log: Option<PathBuf>,
+    /// Named risk_score → mode partition for INV-C1.
+    #[arg(
+        long,
+        default_value = "default",
+        value_parser = PossibleValuesParser::new(
+            THRESHOLD_PROFILES.iter().map(|(name, _, help)| PossibleValue::new(*name).help(*help))
+        ),
+    )]
+    threshold_profile: String,
+    /// JSON file mapping each mode to its risk_level for INV-C2,
+    /// e.g. {"Optimal": "L1", ..., "Halt": "L5"}.
+    #[arg(long)]
+    level_map: Option<PathBuf>,
+    /// Round risk_score to N decimals before classifying it for INV-C1.
+    /// Changes classification semantics; use only to match a producer that
+    /// rounds the same way.
+    #[arg(long, value_name = "N")]
+    classify_precision: Option<u32>,
+    /// Also check INV-S6: schema_version lies in RANGE, comma-separated
+    /// comparators such as ">=1.0.0, <2.0.0".
+    #[arg(long, value_name = "RANGE")]
+    schema_version_range: Option<String>,
+    /// Before validating, compare the record's fields and their JSON types
+    /// with the D1-A §5 example record and report differences separately.
+    #[arg(long, conflicts_with_all = ["batch", "json_pointer"])]
+    compare_to_example: bool,
+    /// Check and report only these invariants (comma-separated IDs).
+    #[arg(
+        long,
+        value_delimiter = ',',
+        value_parser = PossibleValuesParser::new(
+            INVARIANTS.iter().chain(&OPTIONAL_INVARIANTS).chain(&QUALITY_INVARIANTS).map(|(id, _)| *id)
+        ),
+    )]
+    only: Vec<String>,
+    /// Shortcut for `--only INV-C1,INV-C2,INV-C3`.
+    #[arg(long, conflicts_with = "only")]
+    consistency_only: bool,
+    /// Check only the structural invariants INV-S1 to INV-S4 (and INV-S6
+    /// with --schema-version-range), skipping all numeric checks; a quick
+    /// pre-filter for malformed records.
+    #[arg(long, conflicts_with_all = ["only", "consistency_only"])]
+    structural_only: bool,
+    /// Instead of INV-C1 to INV-C3, check INV-C5: recompute mode and
+    /// risk_level from each stored risk_score and fail every record whose
+    /// stored values differ.
+    #[arg(long, conflicts_with_all = ["only", "consistency_only", "structural_only"])]
+    recompute_mode: bool,
+    /// With --recompute-mode, write every validated record with the
+    /// recomputed mode and risk_level to this file as NDJSON.
+    #[arg(long, value_name = "PATH", requires = "recompute_mode")]
+    corrected_out: Option<PathBuf>,
+}
+
+impl ValidateArgs {
+    fn options(&self) -> Result<ValidationOptions, PmatrixError> {
+        let level_map = match &self.level_map {
+            Some(path) => LevelMap::from_json(&read_input(Some(path), false)?)?,
+            None => LevelMap::default(),
+        };
+        Ok(ValidationOptions {
+            strict_floats: self.strict_floats,
+            demo_aggregation_eps: self.check_demo_aggregation.then_some(DEMO_AGGREGATION_EPS),
+            partition_map: builtin_profile(&self.threshold_profile).expect("clap restricts profile names"),
+            level_map,
+            check_chain: self.check_chain,
+            quality_checks: self.quality_checks,
+            classify_precision: self.classify_precision,
+            schema_version_range: self.schema_version_range.as_deref().map(SchemaVersionRange::parse).transpose()?,
+            recompute_classification: self.recompute_mode,
+        })
+    }
+
+    /// The invariant IDs selected for reporting, or None for all of them.
+    fn selection(&self) -> Option<Vec<&str>> {
+        if self.consistency_only {
+            Some(vec!["INV-C1", "INV-C2", "INV-C3"])
+        } else if self.structural_only {
+            Some(vec!["INV-S1", "INV-S2", "INV-S3", "INV-S4", "INV-S6"])
+        } else if self.only.is_empty() {
+            None
+        } else {
+            Some(self.only.iter().map(String::as_str).collect())
+        }
+    }
+
+    /// Checks `--max-staleness` against the latest record, returning the exit
+    /// code: `EXIT_STALE` if it is too old, otherwise 0.
+    fn staleness(&self, latest: Option<&RuntimeStateRecord>) -> i32 {
+        match (self.max_staleness, latest) {
+            (Some(max), Some(record)) => {
+                let now = self.now.unwrap_or_else(|| SystemClock.now_secs());
+                stale_exit_code(record, now, max)
+            }
+            _ => 0,
+        }
+    }
+
+    /// Validates one record, honoring `--only`/`--consistency-only`/`--structural-only`.
+    fn validate(&self, record: &RuntimeStateRecord, opts: &ValidationOptions) -> Vec<InvariantResult> {
+        if self.structural_only {
+            return validate_structural_with(record, opts);
+        }
+        let mut results = validate_record_with(record, opts);
+        if let Some(ids) = self.selection() {
+            results.retain(|r| ids.contains(&r.id));
+        }
+        if self.recompute_mode {
+            results.retain(|r| !["INV-C1", "INV-C2", "INV-C3"].contains(&r.id));
+        }
+        results
+    }
+
+    /// Writes `--corrected-out`: every record with the mode and risk_level
+    /// recomputed from its risk_score.
+    fn write_corrected(&self, opts: &ValidationOptions, records: &[RuntimeStateRecord]) -> Result<(), PmatrixError> {
+        let Some(path) = &self.corrected_out else {
+            return Ok(());
+        };
+        let mut corrected = String::new();
+        for record in records {
+            corrected.push_str(&serde_json::to_string(&reclassify_record(record, opts).as_ref().unwrap_or(record))?);
+            corrected.push('\n');
+        }
+        std::fs::write(path, corrected)?;
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Text,
+    Markdown,
+    /// NDJSON: one object per record, then a summary object.
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum LegendFormat {
+    Text,
+    Json,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum GroupBy {
+    Mode,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is unset or empty.
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    fn enabled(self) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum AlignBy {
+    Index,
+    Timestamp,
+}
+
+/// Environment fallbacks for the `emit` function flags, as (flag, variable).
+const EMIT_ENV_VARS: [(&str, &str); 4] = [
+    ("--baseline", "PMATRIX_BASELINE"),
+    ("--norm", "PMATRIX_NORM"),
+    ("--stability", "PMATRIX_STABILITY"),
+    ("--meta-control", "PMATRIX_META_CONTROL"),
+];
+
+/// Exit codes of the standard scheme (see `ExitCodeScheme`).
+const EXIT_VIOLATION: i32 = 1;
+const EXIT_INPUT: i32 = 2;
+const EXIT_IO: i32 = 3;
+const EXIT_USAGE: i32 = 4;
+/// Exit code when the latest record is older than `--max-staleness`.
+const EXIT_STALE: i32 = 5;
+
+/// How failures map to exit codes. Commands return codes of the standard
+/// scheme, and `main` translates them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ExitCodeScheme {
+    /// 0 success, 1 invariant violation, 2 input or parse error, 3 I/O
+    /// error, 4 usage or configuration error, 5 stale.
+    Standard,
+    /// 1 for every failure except usage errors (2) and staleness (3).
+    Legacy,
+}
+
+impl ExitCodeScheme {
+    // Translates a standard-scheme code.
+    fn map(self, code: i32) -> i32 {
+        match (self, code) {
+            (ExitCodeScheme::Standard, _) | (_, 0) => code,
+            (ExitCodeScheme::Legacy, EXIT_USAGE) => 2,
+            (ExitCodeScheme::Legacy, EXIT_STALE) => 3,
+            (ExitCodeScheme::Legacy, _) => 1,
+        }
+    }
+
+    // The scheme requested on the command line, for errors raised before
+    // the arguments have been parsed.
+    fn from_args() -> Self {
+        let args: Vec<String> = std::env::args().collect();
+        let legacy = args.windows(2).any(|w| w[0] == "--exit-code-scheme" && w[1] == "legacy")
+            || args.iter().any(|a| a == "--exit-code-scheme=legacy");
+        if legacy { ExitCodeScheme::Legacy } else { ExitCodeScheme::Standard }
+    }
+}
+
+/// Parses the command line, adding an environment-variable hint when an
+/// `emit` function value was supplied neither as a flag nor through the env.
+fn parse_cli() -> Cli {
+    let err = match Cli::try_parse() {
+        Ok(cli) => return cli,
+        Err(e) => e,
+    };
+    // --help and --version exit 0.
+    let code = if err.use_stderr() { ExitCodeScheme::from_args().map(EXIT_USAGE) } else { 0 };
+    if err.kind() != ErrorKind::MissingRequiredArgument {
+        let _ = err.print();
+        std::process::exit(code);
+    }
+
+    let missing: Vec<String> = match err.get(ContextKind::InvalidArg) {
+        Some(ContextValue::Strings(args)) => args.clone(),
+        _ => Vec::new(),
+    };
+    let _ = err.print();
+    for (flag, var) in EMIT_ENV_VARS {
+        if missing.iter().any(|a| a.starts_with(flag)) {
+            eprintln!("hint: {} may also be set via the {} environment variable", flag, var);
+        }
+    }
+    std::process::exit(code);
 }
 
 fn main() {
-    let cli = Cli::parse();
+    let cli = parse_cli();
+    let scheme = cli.exit_code_scheme;
+
+    let code = match run(cli) {
+        Ok(code) => code,
+        Err(e) => match &e {
+            PmatrixError::Encode(msg) => {
+                eprintln!("Error: {}", msg);
+                EXIT_INPUT
+            }
+            PmatrixError::Config(_) => {
+                eprintln!("Error: {}", e);
+                EXIT_USAGE
+            }
+            PmatrixError::Parse(err) => {
+                eprintln!("JSON parse error: {}", err);
+                eprintln!("The input must be a valid P-MATRIX runtime state record.");
+                EXIT_INPUT
+            }
+            // Input that was read but cannot be decoded: not UTF-8, or of an
+            // undetected or unsupported format.
+            PmatrixError::Io(err) if matches!(err.kind(), std::io::ErrorKind::InvalidData | std::io::ErrorKind::Unsupported) => {
+                eprintln!("Input error: {}", err);
+                EXIT_INPUT
+            }
+            PmatrixError::Io(err) => {
+                eprintln!("I/O error: {}", err);
+                EXIT_IO
+            }
+            // Per-invariant results have already been reported on stdout.
+            PmatrixError::Validation(_) => EXIT_VIOLATION,
+        },
+    };
+    std::process::exit(scheme.map(code));
+}
+
+/// Reports on stderr how old `record` is at `now` and returns `EXIT_STALE`
+/// if it is more than `max` seconds old, otherwise 0. A record from the
+/// future is never stale, but is noted.
+fn stale_exit_code(record: &RuntimeStateRecord, now: u64, max: u64) -> i32 {
+    if is_stale(record, now, max) {
+        eprintln!(
+            "Stale: latest record (timestamp {}) is {} s old, more than --max-staleness {}",
+            record.timestamp,
+            now - record.timestamp,
+            max
+        );
+        EXIT_STALE
+    } else {
+        let age = record_age_secs(record, now);
+        if age < 0 {
+            eprintln!("Note: latest record (timestamp {}) is {} s in the future", record.timestamp, age.unsigned_abs());
+        }
+        0
+    }
+}
+
+/// Maps invariant results to the command outcome: exit code 0 when every
+/// invariant passed, otherwise a validation error naming the failures.
+fn conformance(results: &[InvariantResult]) -> Result<i32, PmatrixError> {
+    let failed: Vec<&'static str> = results.iter().filter(|r| r.is_violation()).map(|r| r.id).collect();
+    if failed.is_empty() {
+        Ok(0)
+    } else {
+        Err(PmatrixError::Validation(failed))
+    }
+}
+
+/// Computes a record's fingerprint, or fails without the `canonical` feature.
+#[cfg(feature = "canonical")]
+fn fingerprint_of(record: &RuntimeStateRecord) -> Result<String, PmatrixError> {
+    Ok(pmatrix_encoder::fingerprint::record_fingerprint(record))
+}
+
+#[cfg(not(feature = "canonical"))]
+fn fingerprint_of(_record: &RuntimeStateRecord) -> Result<String, PmatrixError> {
+    Err(PmatrixError::Config("fingerprints require the `canonical` feature".into()))
+}
+
+/// Checks a record against an expected fingerprint, or fails without the
+/// `canonical` feature.
+#[cfg(feature = "canonical")]
+fn fingerprint_matches(record: &RuntimeStateRecord, expected: &str) -> Result<bool, PmatrixError> {
+    Ok(pmatrix_encoder::fingerprint::fingerprint_matches(record, expected))
+}
+
+#[cfg(not(feature = "canonical"))]
+fn fingerprint_matches(_record: &RuntimeStateRecord, _expected: &str) -> Result<bool, PmatrixError> {
+    Err(PmatrixError::Config("fingerprints require the `canonical` feature".into()))
+}
+
+/// Checks a record against a JSON Schema, or fails without the
+/// `json-schema` feature.
+#[cfg(feature = "json-schema")]
+fn schema_errors(schema: &serde_json::Value, instance: &serde_json::Value) -> Result<Vec<String>, PmatrixError> {
+    pmatrix_encoder::schema_check::schema_errors(schema, instance)
+}
 
+#[cfg(not(feature = "json-schema"))]
+fn schema_errors(_schema: &serde_json::Value, _instance: &serde_json::Value) -> Result<Vec<String>, PmatrixError> {
+    Err(PmatrixError::Config("check-schema requires the `json-schema` feature".into()))
+}
+
+/// Writes or prints a sealed record, or fails without the `canonical` feature.
+#[cfg(feature = "canonical")]
+fn run_seal(record: RuntimeStateRecord, out: Option<&Path>, inline: bool) -> Result<i32, PmatrixError> {
+    use pmatrix_encoder::fingerprint::{canonical_bytes, seal};
+
+    let sealed = seal(record);
+    match out {
+        Some(path) if !inline => {
+            let sidecar = path.with_extension("sha256");
+            let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+            std::fs::write(path, canonical_bytes(&sealed.record))?;
+            std::fs::write(&sidecar, format!("{}  {}\n", sealed.fingerprint, name))?;
+            println!("{}", sealed.fingerprint);
+        }
+        _ => println!("{}", serde_json::to_string(&sealed)?),
+    }
+    Ok(0)
+}
+
+#[cfg(not(feature = "canonical"))]
+fn run_seal(_record: RuntimeStateRecord, _out: Option<&Path>, _inline: bool) -> Result<i32, PmatrixError> {
+    Err(PmatrixError::Config("sealing requires the `canonical` feature".into()))
+}
+
+/// Parses a sealed `{record, fingerprint}` object, or fails without the
+/// `canonical` feature.
+#[cfg(feature = "canonical")]
+fn unseal(input: &str) -> Result<(RuntimeStateRecord, String), PmatrixError> {
+    let sealed: pmatrix_encoder::fingerprint::SealedRecord = serde_json::from_str(input)?;
+    Ok((sealed.record, sealed.fingerprint))
+}
+
+#[cfg(not(feature = "canonical"))]
+fn unseal(_input: &str) -> Result<(RuntimeStateRecord, String), PmatrixError> {
+    Err(PmatrixError::Config("fingerprints require the `canonical` feature".into()))
+}
+
+/// Appends audit events for validated records to `path`, or fails without the
+/// `canonical` feature.
+#[cfg(feature = "canonical")]
+fn append_audit_log(
+    path: &Path,
+    records: &[RuntimeStateRecord],
+    results: &[Vec<InvariantResult>],
+) -> Result<(), PmatrixError> {
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    for (record, results) in records.iter().zip(results) {
+        pmatrix_encoder::audit::append_audit_event(&mut file, record, results)?;
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "canonical"))]
+fn append_audit_log(
+    _path: &Path,
+    _records: &[RuntimeStateRecord],
+    _results: &[Vec<InvariantResult>],
+) -> Result<(), PmatrixError> {
+    Err(PmatrixError::Config("--audit-log requires the `canonical` feature".into()))
+}
+
+/// Prints the manifest of a batch, or fails without the `canonical` feature.
+#[cfg(feature = "canonical")]
+fn run_manifest(records: &[RuntimeStateRecord]) -> Result<i32, PmatrixError> {
+    let manifest = pmatrix_encoder::manifest::build_manifest(records);
+    println!("{}", serde_json::to_string_pretty(&manifest)?);
+    Ok(0)
+}
+
+#[cfg(not(feature = "canonical"))]
+fn run_manifest(_records: &[RuntimeStateRecord]) -> Result<i32, PmatrixError> {
+    Err(PmatrixError::Config("manifests require the `canonical` feature".into()))
+}
+
+/// Compares a batch with a stored manifest, or fails without the `canonical`
+/// feature.
+#[cfg(feature = "canonical")]
+fn run_verify_manifest(records: &[RuntimeStateRecord], stored: &str) -> Result<i32, PmatrixError> {
+    use pmatrix_encoder::manifest::{build_manifest, verify_manifest, Manifest};
+
+    let expected: Manifest = serde_json::from_str(stored)?;
+    let mismatched = verify_manifest(records, &expected);
+    if mismatched.is_empty() {
+        println!("Result: MANIFEST MATCHES — {} record(s).", records.len());
+        return Ok(0);
+    }
+    let actual = serde_json::to_value(build_manifest(records))?;
+    let expected = serde_json::to_value(&expected)?;
+    for field in &mismatched {
+        println!("[FAIL] {} — expected={}, actual={}", field, expected[field], actual[field]);
+    }
+    println!();
+    println!("Result: MANIFEST MISMATCH — {} field(s) differ.", mismatched.len());
+    Ok(1)
+}
+
+#[cfg(not(feature = "canonical"))]
+fn run_verify_manifest(_records: &[RuntimeStateRecord], _stored: &str) -> Result<i32, PmatrixError> {
+    Err(PmatrixError::Config("manifests require the `canonical` feature".into()))
+}
+
+/// Picks the report timestamp style: RFC 3339 when built with `chrono`.
+fn timestamp_style(timezone: Option<&str>) -> Result<TimestampStyle, PmatrixError> {
+    if !cfg!(feature = "chrono") {
+        return match timezone {
+            Some(_) => Err(PmatrixError::Config("--timezone requires the `chrono` feature".into())),
+            None => Ok(TimestampStyle::Epoch),
+        };
+    }
+    let utc_offset_secs = timezone.map(parse_utc_offset).transpose()?.unwrap_or(0);
+    Ok(TimestampStyle::Rfc3339 { utc_offset_secs })
+}
+
+/// Collects batch results and renders the markdown report once the batch is
+/// complete, since its summary comes first.
+struct MarkdownSink {
+    style: TimestampStyle,
+    records: Vec<RuntimeStateRecord>,
+    results: Vec<Vec<InvariantResult>>,
+}
+
+impl ResultSink for MarkdownSink {
+    fn record(&mut self, _index: usize, record: &RuntimeStateRecord, results: &[InvariantResult]) {
+        self.records.push(record.clone());
+        self.results.push(results.to_vec());
+    }
+
+    fn finish(&mut self, summary: &BatchSummary) {
+        print!(
+            "{}",
+            render_batch_markdown_with(&self.records, &self.results, summary.t1_violation, self.style)
+        );
+    }
+}
+
+/// Validates a batch into `sink`, honoring --warn-lossy-precision,
+/// --fail-fast and --audit-log. `records` is truncated to the records
+/// actually validated.
+fn validate_batch(
+    args: &ValidateArgs,
+    opts: &ValidationOptions,
+    records: &mut Vec<RuntimeStateRecord>,
+    sink: &mut dyn ResultSink,
+) -> Result<(Vec<Vec<InvariantResult>>, BatchSummary), PmatrixError> {
+    let mut results = Vec::with_capacity(records.len());
+    for (i, record) in records.iter().enumerate() {
+        if let Some(max_digits) = args.warn_lossy_precision {
+            warn_lossy(&format!("record #{} ", i), record, max_digits);
+        }
+        let mut result = args.validate(record, opts);
+        if args.strict_monotonic {
+            result.push(check_inv_t2(i.checked_sub(1).map(|p| &records[p]), record));
+        }
+        let failed = result.iter().any(|r| r.is_violation());
+        sink.record(i, record, &result);
+        results.push(result);
+        if args.fail_fast && failed {
+            break;
+        }
+    }
+    records.truncate(results.len());
+    if let Some(path) = &args.audit_log {
+        append_audit_log(path, records, &results)?;
+    }
+    let check_t1 = args.selection().is_none_or(|ids| ids.contains(&"INV-T1"));
+    let t1 = if check_t1 { validate_stream_t1(records) } else { None };
+    let summary = BatchSummary::from_results(&results, t1);
+    sink.finish(&summary);
+    Ok((results, summary))
+}
+
+/// Runs `validate` for a single record or, with `--batch`, a stream.
+fn run_validate(args: ValidateArgs, source: Source, style: TimestampStyle) -> Result<i32, PmatrixError> {
+    if !args.invert {
+        return validate_command(args, source, style);
+    }
+    // Only the exit code is inverted. A record that does not parse counts as
+    // non-conforming, since strict parsing enforces INV-S1/S2; I/O and
+    // configuration errors still fail.
+    match validate_command(args, source, style) {
+        Ok(_) => {
+            eprintln!("Expected non-conformance (--invert), but every invariant passed.");
+            Ok(1)
+        }
+        Err(PmatrixError::Validation(_)) => Ok(0),
+        Err(PmatrixError::Parse(e)) => {
+            eprintln!("JSON parse error: {}", e);
+            Ok(0)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Checks that `input` has the shape `convert --from` declares: `json` is a
+/// single record or an array, `ndjson` whitespace-separated records.
+fn check_convert_shape(input: &str, from: ConvertFormat) -> Result<(), PmatrixError> {
+    let is_array = input.trim_start().starts_with('[');
+    let values = || serde_json::Deserializer::from_str(input).into_iter::<serde::de::IgnoredAny>();
+    let msg = match from {
+        ConvertFormat::Json if !is_array && values().take(2).count() > 1 => {
+            "--from json expects one record or a JSON array, but the input holds several records; use --from ndjson"
+        }
+        ConvertFormat::Ndjson if is_array => "--from ndjson expects one record per line, but the input is a JSON array; use --from json",
+        _ => return Ok(()),
+    };
+    Err(PmatrixError::Parse(serde::de::Error::custom(msg)))
+}
+
+fn validate_command(args: ValidateArgs, source: Source, style: TimestampStyle) -> Result<i32, PmatrixError> {
+    let opts = args.options()?;
+    if args.only_failures && args.format == OutputFormat::Markdown {
+        return Err(PmatrixError::Config("--only-failures is not supported with --format markdown".to_string()));
+    }
+
+    if args.batch {
+        let limit = args.stop_after.unwrap_or(usize::MAX);
+        let mut records = match &args.json_pointer {
+            Some(pointer) => {
+                let mut records = parse_at_pointer_bounded(&source.read(args.path.as_deref())?, pointer, source.max_records)?;
+                records.truncate(limit);
+                records
+            }
+            None => source.records_limit(args.path.as_deref(), limit)?,
+        };
+        let (results, summary) = match args.format {
+            OutputFormat::Text if args.only_failures => {
+                validate_batch(&args, &opts, &mut records, &mut FailuresOnly(StdoutSink))?
+            }
+            OutputFormat::Text => validate_batch(&args, &opts, &mut records, &mut StdoutSink)?,
+            OutputFormat::Markdown => {
+                let mut sink = MarkdownSink { style, records: Vec::new(), results: Vec::new() };
+                validate_batch(&args, &opts, &mut records, &mut sink)?
+            }
+            OutputFormat::Json => {
+                let mut sink = JsonSink::new(std::io::BufWriter::new(std::io::stdout().lock()));
+                let outcome = if args.only_failures {
+                    validate_batch(&args, &opts, &mut records, &mut FailuresOnly(&mut sink))?
+                } else {
+                    validate_batch(&args, &opts, &mut records, &mut sink)?
+                };
+                sink.into_inner()?;
+                outcome
+            }
+        };
+
+        let mut failed: Vec<&'static str> = Vec::new();
+        for r in results.iter().flatten().filter(|r| r.is_violation()) {
+            if !failed.contains(&r.id) {
+                failed.push(r.id);
+            }
+        }
+        if summary.t1_violation.is_some() {
+            failed.push("INV-T1");
+        }
+        args.write_corrected(&opts, &records)?;
+        if !failed.is_empty() {
+            return Err(PmatrixError::Validation(failed));
+        }
+        return Ok(args.staleness(records.iter().max_by_key(|r| r.timestamp)));
+    }
+
+    let input = source.read(args.path.as_deref())?;
+    if args.compare_to_example {
+        let diffs = diff_shape_against_example(&serde_json::from_str(&input)?);
+        if diffs.is_empty() {
+            println!("Structure: same fields and types as the spec example.\n");
+        } else {
+            println!("Structure: {} difference(s) from the spec example:", diffs.len());
+            for d in &diffs {
+                println!("  [DIFF] {}", d);
+            }
+            println!();
+        }
+    }
+
+    let record = if let Some(pointer) = &args.json_pointer {
+        let mut records = parse_at_pointer_bounded(&input, pointer, source.max_records)?;
+        if records.len() != 1 {
+            return Err(PmatrixError::Config(format!(
+                "JSON pointer `{}` selected {} records; use --batch to validate several",
+                pointer,
+                records.len()
+            )));
+        }
+        records.remove(0)
+    } else if args.lenient {
+        let (record, ignored) = parse_lenient(&input)?;
+        for field in &ignored {
+            eprintln!("Note: ignored unknown field `{}`", field);
+        }
+        record
+    } else {
+        parse_strict(&input)?
+    };
+
+    if let Some(max_digits) = args.warn_lossy_precision {
+        warn_lossy("", &record, max_digits);
+    }
+    let results = args.validate(&record, &opts);
+    if let Some(path) = &args.audit_log {
+        append_audit_log(path, std::slice::from_ref(&record), std::slice::from_ref(&results))?;
+    }
+    match args.format {
+        OutputFormat::Text => {
+            if args.only_failures {
+                print!("{}", render_text_failures(&results));
+            } else {
+                print!("{}", render_text(&results));
+            }
+            if let Some(explanation) = explain_consistency_failure_with(&record, &opts).filter(|_| !args.recompute_mode) {
+                println!("Consistency: {}", explanation);
+            }
+        }
+        OutputFormat::Markdown => print!("{}", render_markdown_with(&record, &results, &opts, style)),
+        OutputFormat::Json => {
+            let mut sink = JsonSink::new(std::io::stdout().lock());
+            if !args.only_failures || results.iter().any(|r| !r.passed) {
+                sink.record(0, &record, &results);
+            }
+            sink.finish(&BatchSummary::from_results(std::slice::from_ref(&results), None));
+            drop(sink.into_inner()?);
+        }
+    }
+    args.write_corrected(&opts, std::slice::from_ref(&record))?;
+    conformance(&results)?;
+    Ok(args.staleness(Some(&record)))
+}
+
+/// Prints a warning for each value of `record` with too many significant digits.
+fn warn_lossy(prefix: &str, record: &RuntimeStateRecord, max_digits: usize) {
+    for v in lossy_values(record, max_digits) {
+        eprintln!(
+            "warning: {}{}={} has {} significant digits; likely {}",
+            prefix, v.field, v.value, v.digits, v.cleaned
+        );
+    }
+}
+
+/// Validates one line of a watched stream and prints its verdict. Returns
+/// the record if the line parsed.
+fn watch_line(line_no: usize, line: &str, t1: &mut T1Tracker) -> Option<RuntimeStateRecord> {
+    let record = match parse_strict(&clean_input(line)) {
+        Ok(r) => r,
+        Err(e) => {
+            println!("[FAIL] line {} — {}", line_no, e);
+            return None;
+        }
+    };
+
+    let failed: Vec<&str> = validate_record(&record)
+        .iter()
+        .filter(|r| r.is_violation())
+        .map(|r| r.id)
+        .collect();
+    if failed.is_empty() {
+        println!("[PASS] line {} — timestamp={}, mode={}", line_no, record.timestamp, record.mode);
+    } else {
+        println!("[FAIL] line {} — {}", line_no, failed.join(", "));
+    }
+
+    match t1.observe(record.timestamp) {
+        T1Outcome::Ok => {}
+        T1Outcome::Violation { previous } => println!(
+            "[FAIL] line {} — INV-T1: timestamp {} < previous {}",
+            line_no, record.timestamp, previous
+        ),
+        T1Outcome::Reset { previous } => println!(
+            "[NOTE] line {} — timestamps restarted after rotation ({} after {})",
+            line_no, record.timestamp, previous
+        ),
+    }
+    Some(record)
+}
+
+/// Runs a subcommand, returning the process exit code on success.
+fn run(cli: Cli) -> Result<i32, PmatrixError> {
+    let source = Source {
+        gzip: cli.gzip,
+        format: match cli.input_format {
+            FormatArg::Auto => None,
+            FormatArg::Json => Some(InputFormat::Json),
+        },
+        max_records: cli.max_records,
+        functions_array: cli.functions_array,
+    };
+    let style = timestamp_style(cli.timezone.as_deref())?;
     match cli.command {
-        Commands::Emit {
-            baseline,
-            norm,
-            stability,
-            meta_control,
-            timestamp,
+        Commands::Emit { values, count, array } => {
+            let record = values.emit()?;
+            if count == 1 && !array {
+                println!("{}", serde_json::to_string_pretty(&record)?);
+                return Ok(0);
+            }
+
+            let start = record.timestamp;
+            let records = (0..count).map(|i| record.clone().with_timestamp(start.saturating_add(i)));
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            if array {
+                write_records_array(records, &mut out)?;
+                out.write_all(b"\n")?;
+            } else {
+                write_records_ndjson(records, &mut out)?;
+            }
+            out.flush()?;
+            Ok(0)
+        }
+        Commands::Seal { values, out, inline } => run_seal(values.emit()?, out.as_deref(), inline),
+        Commands::Validate(args) => run_validate(args, source, style),
+        Commands::Inspect { path } => {
+            let record = parse_strict(&source.read(path.as_deref())?)?;
+            let results = validate_record(&record);
+            print!("{}", render_inspect(&record, &results));
+
+            conformance(&results)
+        }
+        Commands::Normalize { path, dry_run } => {
+            let records = source.records(path.as_deref())?;
+            let mut changed = 0;
+            for (i, record) in records.iter().enumerate() {
+                let normalized = normalize_record(record)?;
+                if !dry_run {
+                    println!("{}", serde_json::to_string(&normalized)?);
+                    continue;
+                }
+                let diffs = diff_records(record, &normalized, 0.0);
+                if !diffs.is_empty() {
+                    changed += 1;
+                }
+                for d in diffs {
+                    println!("[CHANGE] #{} {} — {} → {}", i, d.field, d.left, d.right);
+                }
+            }
+            if dry_run {
+                println!("{} of {} record(s) would change.", changed, records.len());
+            }
+            Ok(if changed > 0 { 1 } else { 0 })
+        }
+        Commands::Replay { path, eps } => {
+            let records = source.records(path.as_deref())?;
+            let (mut matched, mut conforming) = (0, 0);
+            for (i, record) in records.iter().enumerate() {
+                let verdict = if is_record_valid(record) {
+                    conforming += 1;
+                    "conforming"
+                } else {
+                    "malformed"
+                };
+                match replay_drift(record, eps) {
+                    Ok(drift) if drift.is_empty() => {
+                        matched += 1;
+                        println!("[MATCH] #{} ({})", i, verdict);
+                    }
+                    Ok(drift) => {
+                        let fields: Vec<String> =
+                            drift.iter().map(|d| format!("{} {} → {}", d.field, d.left, d.right)).collect();
+                        println!("[DRIFT] #{} ({}) — {}", i, verdict, fields.join(", "));
+                    }
+                    Err(e) => println!("[ERROR] #{} ({}) — cannot re-derive: {}", i, verdict, e),
+                }
+            }
+            let rate = if records.is_empty() { 100.0 } else { 100.0 * matched as f64 / records.len() as f64 };
+            println!();
+            println!(
+                "Replay: {} of {} record(s) match the demo model ({:.1}%); {} conforming.",
+                matched,
+                records.len(),
+                rate,
+                conforming
+            );
+            Ok(if matched == records.len() { 0 } else { 1 })
+        }
+        Commands::Bisect { path } => {
+            let records = source.records(path.as_deref())?;
+            let Some(i) = first_nonconforming(&records) else {
+                println!("All {} record(s) conform.", records.len());
+                return Ok(0);
+            };
+            let record = &records[i];
+            let failed: Vec<&str> =
+                validate_record(record).iter().filter(|r| r.is_violation()).map(|r| r.id).collect();
+            println!(
+                "First non-conforming record: #{} at timestamp {} — {}",
+                i,
+                format_timestamp(record.timestamp, style),
+                failed.join(", ")
+            );
+            println!("{} record(s) before it conform.", i);
+            Ok(1)
+        }
+        Commands::Doctor { path } => {
+            let mut input = read_input(path.as_deref(), source.gzip)?;
+            // Input that cannot be expanded is diagnosed as it is.
+            if source.functions_array {
+                input = expand_functions_arrays(&input).unwrap_or(input);
+            }
+            let findings = diagnose(&input);
+            print!("{}", render_diagnosis(&findings));
+            Ok(if findings.is_empty() { 0 } else { 1 })
+        }
+        Commands::Fingerprint { path } => {
+            let record = parse_strict(&source.read(path.as_deref())?)?;
+            println!("{}", fingerprint_of(&record)?);
+            Ok(0)
+        }
+        Commands::Manifest { path } => run_manifest(&source.records(path.as_deref())?),
+        Commands::VerifyManifest { path, manifest } => run_verify_manifest(
+            &source.records(path.as_deref())?,
+            &read_input(Some(&manifest), false)?,
+        ),
+        Commands::Verify { path, fingerprint, fingerprint_file } => {
+            let input = source.read(path.as_deref())?;
+            let (record, fingerprint) = match (fingerprint, fingerprint_file) {
+                (Some(fingerprint), _) => (parse_strict(&input)?, fingerprint),
+                (None, Some(file)) => {
+                    let sidecar = read_input(Some(&file), false)?;
+                    let fingerprint = sidecar.split_whitespace().next().unwrap_or_default().to_string();
+                    (parse_strict(&input)?, fingerprint)
+                }
+                (None, None) => unseal(&input)?,
+            };
+            let actual = fingerprint_of(&record)?;
+            let integrity = fingerprint_matches(&record, &fingerprint)?;
+            if integrity {
+                println!("[PASS] fingerprint — {}", fingerprint.trim());
+            } else {
+                println!(
+                    "[FAIL] fingerprint — expected={}, actual={}",
+                    fingerprint.trim(),
+                    actual
+                );
+            }
+
+            let results = validate_record(&record);
+            print!("{}", render_text(&results));
+            if !integrity {
+                println!("Integrity: FINGERPRINT MISMATCH — this is not the stored record.");
+            }
+            match conformance(&results)? {
+                0 if !integrity => Ok(1),
+                code => Ok(code),
+            }
+        }
+        Commands::Stats { path, streaming: true, stop_after, color, .. } => {
+            // Same NaN handling as the buffered path, one record at a time.
+            let records = source.stream(path.as_deref())?;
+            let policy = NanPolicy::default();
+            let checked = records.take(stop_after.unwrap_or(usize::MAX)).enumerate().map(|(i, r)| {
+                let record = r?;
+                policy.check(i, &record)?;
+                Ok(record)
+            });
+            let stats = stream_stats_iter(checked)?;
+            print!("{}", render_stats_colored(&stats, &[], &[], style, color.enabled()));
+            Ok(0)
+        }
+        Commands::Stats {
+            path,
+            percentiles,
+            flatline_run,
+            flatline_eps,
+            sustained,
+            sustained_level,
+            flapping,
+            flapping_changes,
+            group_by,
+            stop_after,
+            streaming: false,
+            color,
         } => {
-            match emit_demo_record(baseline, norm, stability, meta_control, timestamp) {
+            let records = source.records_limit(path.as_deref(), stop_after.unwrap_or(usize::MAX))?;
+            let policy = NanPolicy::default();
+            let stats = stream_stats_with(&records, policy)?;
+            print!(
+                "{}",
+                render_stats_colored(
+                    &stats,
+                    &risk_percentiles_with(&records, &percentiles, policy)?,
+                    &stability_percentiles_with(&records, &percentiles, policy)?,
+                    style,
+                    color.enabled(),
+                )
+            );
+            if let Some(min_run) = flatline_run {
+                let runs = match flatline_eps {
+                    Some(eps) => detect_flatline_within(&records, min_run, eps),
+                    None => detect_flatline(&records, min_run),
+                };
+                for (first, last) in runs {
+                    println!(
+                        "{:<16} records #{}–#{} ({} identical)",
+                        "flatline",
+                        first,
+                        last,
+                        last - first + 1
+                    );
+                }
+            }
+            if let Some(min_secs) = sustained {
+                for (first, last, secs) in sustained_high_risk(&records, &sustained_level, min_secs) {
+                    println!(
+                        "{:<16} records #{}–#{} ({}+ for {} s)",
+                        "sustained",
+                        first,
+                        last,
+                        sustained_level,
+                        secs
+                    );
+                }
+            }
+            if let Some(window) = flapping {
+                for (first, last, changes) in detect_flapping(&records, window, flapping_changes) {
+                    println!("{:<16} records #{}–#{} ({} mode changes)", "flapping", first, last, changes);
+                }
+            }
+            if let Some(GroupBy::Mode) = group_by {
+                print!("{}", render_mode_stats(&stats_by_mode(&records)));
+            }
+            Ok(0)
+        }
+        Commands::Lint { dir, glob, ignore } => {
+            if source.functions_array {
+                return Err(PmatrixError::Config("--functions-array is not supported by lint".into()));
+            }
+            let files = find_record_files(&dir, &glob, &ignore)?;
+            let mut bad = 0;
+            for path in &files {
+                let name = relative_path(&dir, path);
+                match lint_file_bounded(path, source.max_records) {
+                    FileVerdict::Conforming { records } => {
+                        println!("[PASS] {} ({} record(s))", name, records)
+                    }
+                    FileVerdict::NonConforming { records, failed } => {
+                        bad += 1;
+                        println!("[FAIL] {} ({} record(s)) — {}", name, records, failed.join(", "));
+                    }
+                    FileVerdict::Error(msg) => {
+                        bad += 1;
+                        println!("[ERROR] {} — {}", name, msg);
+                    }
+                }
+            }
+            println!();
+            if bad == 0 {
+                println!("Result: ALL {} FILE(S) CONFORMING.", files.len());
+                Ok(0)
+            } else {
+                println!("Result: {} of {} file(s) non-conforming or unreadable.", bad, files.len());
+                Ok(1)
+            }
+        }
+        Commands::Convert { path, from, to, no_validate } => {
+            let records = match from {
+                None => source.records(path.as_deref())?,
+                Some(from) => {
+                    let source = Source { format: Some(InputFormat::Json), ..source };
+                    let input = source.read(path.as_deref())?;
+                    check_convert_shape(&input, from)?;
+                    parse_stream_bounded(&input, usize::MAX, source.max_records)?
+                }
+            };
+            if !no_validate {
+                for (i, record) in records.iter().enumerate() {
+                    let failed: Vec<&'static str> =
+                        validate_record(record).iter().filter(|r| r.is_violation()).map(|r| r.id).collect();
+                    if !failed.is_empty() {
+                        eprintln!(
+                            "Error: record #{} fails {}; pass --no-validate to convert it anyway",
+                            i,
+                            failed.join(", ")
+                        );
+                        return Err(PmatrixError::Validation(failed));
+                    }
+                }
+                if let Some(i) = validate_stream_t1(&records) {
+                    eprintln!(
+                        "Error: timestamp decreases at record #{} (INV-T1); pass --no-validate to convert it anyway",
+                        i
+                    );
+                    return Err(PmatrixError::Validation(vec!["INV-T1"]));
+                }
+            }
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            if to == ConvertFormat::Json {
+                write_records_array(records.into_iter(), &mut out)?;
+                out.write_all(b"\n")?;
+            } else {
+                write_records_ndjson(records.into_iter(), &mut out)?;
+            }
+            out.flush()?;
+            Ok(0)
+        }
+        Commands::CheckSchema { path, schema } => {
+            let schema: serde_json::Value = serde_json::from_str(&read_input(Some(&schema), false)?)?;
+            let input = source.read(path.as_deref())?;
+            let errors = schema_errors(&schema, &serde_json::from_str(&input)?)?;
+            if errors.is_empty() {
+                println!("[PASS] JSON Schema");
+            } else {
+                println!("[FAIL] JSON Schema — {} error(s)", errors.len());
+                for e in &errors {
+                    println!("  {}", e);
+                }
+            }
+            let native_ok = match parse_strict(&input) {
                 Ok(record) => {
-                    let json = serde_json::to_string_pretty(&record).unwrap();
-                    println!("{}", json);
+                    let failed: Vec<&str> =
+                        validate_record(&record).iter().filter(|r| r.is_violation()).map(|r| r.id).collect();
+                    if failed.is_empty() {
+                        println!("[PASS] invariants");
+                    } else {
+                        println!("[FAIL] invariants — {}", failed.join(", "));
+                    }
+                    failed.is_empty()
                 }
                 Err(e) => {
-                    eprintln!("Error: {}", e);
-                    std::process::exit(1);
+                    println!("[FAIL] invariants — {}", e);
+                    false
                 }
+            };
+            match (errors.is_empty(), native_ok) {
+                (true, true) => println!("\nResult: AGREE — both accept the record."),
+                (false, false) => println!("\nResult: AGREE — both reject the record."),
+                (true, false) => println!("\nResult: DISAGREE — the schema accepts a record the invariants reject."),
+                (false, true) => println!("\nResult: DISAGREE — the schema rejects a record the invariants accept."),
             }
+            Ok(if errors.is_empty() == native_ok { 0 } else { 1 })
         }
-        Commands::Validate => {
-            let mut input = String::new();
-            if let Err(e) = io::stdin().read_to_string(&mut input) {
-                eprintln!("Error reading stdin: {}", e);
-                std::process::exit(1);
+        Commands::Smooth { path, window, dominant_mode } => {
+            let records = source.records(path.as_deref())?;
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            if dominant_mode {
+                for envelope in annotate_dominant_mode(&records, window) {
+                    serde_json::to_writer(&mut out, &envelope)?;
+                    out.write_all(b"\n")?;
+                }
+            } else {
+                for envelope in smooth(&records, window) {
+                    serde_json::to_writer(&mut out, &envelope)?;
+                    out.write_all(b"\n")?;
+                }
             }
-
-            let record: RuntimeStateRecord = match serde_json::from_str(&input) {
-                Ok(r) => r,
-                Err(e) => {
-                    eprintln!("JSON parse error: {}", e);
-                    eprintln!("The input must be a valid P-MATRIX runtime state record.");
-                    std::process::exit(1);
+            out.flush()?;
+            Ok(0)
+        }
+        Commands::Tally { path } => {
+            let records = source.records(path.as_deref())?;
+            print!("{}", render_tally(&mode_counts(&records)));
+            Ok(0)
+        }
+        Commands::Filter { path, min_risk, max_risk, mode, predicate } => {
+            let predicate = predicate.as_deref().map(Predicate::parse).transpose()?;
+            let records = source.records(path.as_deref())?;
+            let filter = RecordFilter { min_risk, max_risk, modes: mode };
+            let keep = |r: &RuntimeStateRecord| filter.matches(r) && predicate.as_ref().is_none_or(|p| p.matches(r));
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            write_records_ndjson(filter_records(records, keep), &mut out)?;
+            out.flush()?;
+            Ok(0)
+        }
+        Commands::Sample { path, k, seed } => {
+            let mut rng = SplitMix64::new(seed.unwrap_or_else(|| SystemClock.now_secs()));
+            let mut error = None;
+            let records = source.stream(path.as_deref())?.map_while(|r| {
+                r.map_err(|e| error = Some(e)).ok()
+            });
+            let sample = reservoir_sample(records, k, &mut rng);
+            if let Some(e) = error {
+                return Err(e);
+            }
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            write_records_ndjson(sample.into_iter(), &mut out)?;
+            out.flush()?;
+            Ok(0)
+        }
+        Commands::Redact { path, bucket, blank_scores } => {
+            let records = source.records(path.as_deref())?;
+            eprintln!("warning: redacted functions no longer match the scores (INV-R6 will fail)");
+            if blank_scores {
+                eprintln!("warning: blanked scores fail INV-C1/INV-C3 for modes other than Optimal");
+            }
+            let opts = RedactOptions { bucket, blank_scores };
+            let mut out = std::io::BufWriter::new(std::io::stdout().lock());
+            write_records_ndjson(records.iter().map(|r| redact_with(r, opts)), &mut out)?;
+            out.flush()?;
+            Ok(0)
+        }
+        Commands::Legend { format, threshold_profile, level_map } => {
+            let partition = builtin_profile(&threshold_profile).expect("clap restricts profile names");
+            let levels = match level_map {
+                Some(path) => LevelMap::from_json(&read_input(Some(&path), false)?)?,
+                None => LevelMap::default(),
+            };
+            let table = partition_table(&partition, &levels);
+            match format {
+                LegendFormat::Text => print!("{}", render_legend(&table)),
+                LegendFormat::Json => println!("{}", serde_json::to_string_pretty(&table)?),
+            }
+            Ok(0)
+        }
+        Commands::Info => {
+            println!("{}", serde_json::to_string_pretty(&crate_info())?);
+            Ok(0)
+        }
+        Commands::Completions { shell } => {
+            let mut cmd = Cli::command();
+            let name = cmd.get_name().to_string();
+            // Generate into a buffer so that write errors surface as I/O errors.
+            let mut script = Vec::new();
+            clap_complete::generate(shell, &mut cmd, name, &mut script);
+            std::io::stdout().write_all(&script)?;
+            Ok(0)
+        }
+        Commands::Watch { path, watch_interval_ms, max_staleness } => {
+            if source.functions_array {
+                return Err(PmatrixError::Config("--functions-array is not supported by watch".into()));
+            }
+            let mut tail = TailReader::new(&path);
+            let mut t1 = T1Tracker::default();
+            let mut line_no = 0usize;
+            let mut latest: Option<RuntimeStateRecord> = None;
+            loop {
+                for event in tail.poll()? {
+                    match event {
+                        TailEvent::Waiting => {
+                            println!("[WAIT] {} does not exist yet", path.display())
+                        }
+                        TailEvent::Rotated => {
+                            t1.rotated();
+                            line_no = 0;
+                            println!("[ROTATED] {} — reading from start", path.display());
+                        }
+                        TailEvent::Line(line) => {
+                            line_no += 1;
+                            if let Some(record) = watch_line(line_no, &line, &mut t1) {
+                                if latest.as_ref().is_none_or(|l| record.timestamp >= l.timestamp) {
+                                    latest = Some(record);
+                                }
+                            }
+                        }
+                    }
+                }
+                if let (Some(max), Some(record)) = (max_staleness, &latest) {
+                    let now = SystemClock.now_secs();
+                    if is_stale(record, now, max) {
+                        println!("[STALE] no record newer than timestamp {} for over {} s", record.timestamp, max);
+                        return Ok(stale_exit_code(record, now, max));
+                    }
                 }
+                thread::sleep(Duration::from_millis(watch_interval_ms));
+            }
+        }
+        Commands::DiffStreams { left, right, eps, align_by } => {
+            let a = source.records(Some(&left))?;
+            let b = source.records(Some(&right))?;
+            let alignment = match align_by {
+                AlignBy::Index => Alignment::Index,
+                AlignBy::Timestamp => Alignment::Timestamp,
             };
 
-            let results = validate_record(&record);
-            let mut all_passed = true;
-
-            for r in &results {
-                let status = if r.passed { "PASS" } else { "FAIL" };
-                if !r.passed {
-                    all_passed = false;
+            let diffs = diff_streams_aligned(&a, &b, eps, alignment);
+            for d in &diffs {
+                match d {
+                    StreamDiff::Field { left_index, right_index, diff } => println!(
+                        "[DIFF] #{}/#{} {} — left={}, right={}",
+                        left_index, right_index, diff.field, diff.left, diff.right
+                    ),
+                    StreamDiff::Unmatched { side, index, timestamp } => {
+                        let side = match side {
+                            Side::Left => "left",
+                            Side::Right => "right",
+                        };
+                        println!("[ONLY] {} #{} — timestamp={}", side, index, timestamp);
+                    }
+                    StreamDiff::LengthMismatch { left_len, right_len } => {
+                        println!("[LENGTH] left={}, right={}", left_len, right_len)
+                    }
                 }
-                println!("[{}] {} — {}", status, r.id, r.detail);
             }
 
             println!();
-            if all_passed {
-                println!("Result: ALL INVARIANTS SATISFIED — record is conforming.");
+            if diffs.is_empty() {
+                println!("Result: STREAMS MATCH — {} record(s) compared.", a.len());
+                Ok(0)
             } else {
-                println!("Result: INVARIANT VIOLATION(S) DETECTED — record is malformed.");
-                std::process::exit(1);
+                println!("Result: {} DIVERGENCE(S) DETECTED.", diffs.len());
+                Ok(1)
             }
         }
     }
@@ -0,0 +1,70 @@
+// manifest.rs — Batch Manifests
+//
+// A manifest describes a batch file for archival: how many records it holds,
+// the time span they cover, a fingerprint of the whole sequence and the
+// number of records in each mode. Recomputing the manifest of an archived
+// batch and comparing it with the stored one detects changes to the batch.
+
+use crate::fingerprint::{canonical_bytes, to_hex};
+use crate::schema::RuntimeStateRecord;
+use crate::stats::mode_counts;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+
+/// Summary of a batch of records.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub record_count: usize,
+    /// None for an empty batch.
+    pub first_timestamp: Option<u64>,
+    /// None for an empty batch.
+    pub last_timestamp: Option<u64>,
+    /// Lowercase hex SHA-256 of the records' canonical forms, each followed
+    /// by a newline (i.e. of the batch as canonical NDJSON).
+    pub fingerprint: String,
+    /// Records per mode string; modes with no records are absent.
+    pub mode_counts: BTreeMap<String, usize>,
+}
+
+/// Builds the manifest of a batch of records.
+pub fn build_manifest(records: &[RuntimeStateRecord]) -> Manifest {
+    let mut hasher = Sha256::new();
+    for r in records {
+        hasher.update(canonical_bytes(r));
+        hasher.update(b"\n");
+    }
+    Manifest {
+        record_count: records.len(),
+        first_timestamp: records.first().map(|r| r.timestamp),
+        last_timestamp: records.last().map(|r| r.timestamp),
+        fingerprint: to_hex(&hasher.finalize()),
+        mode_counts: mode_counts(records),
+    }
+}
+
+/// Recomputes the manifest of `records` and returns the names of the fields
+/// that differ from `expected`, in manifest order. Fingerprints are compared
+/// case-insensitively. An empty result means the batch matches.
+pub fn verify_manifest(records: &[RuntimeStateRecord], expected: &Manifest) -> Vec<&'static str> {
+    let actual = build_manifest(records);
+    let mut mismatched = Vec::new();
+    if actual.record_count != expected.record_count {
+        mismatched.push("record_count");
+    }
+    if actual.first_timestamp != expected.first_timestamp {
+        mismatched.push("first_timestamp");
+    }
+    if actual.last_timestamp != expected.last_timestamp {
+        mismatched.push("last_timestamp");
+    }
+    if !actual.fingerprint.eq_ignore_ascii_case(&expected.fingerprint) {
+        mismatched.push("fingerprint");
+    }
+    if actual.mode_counts != expected.mode_counts {
+        mismatched.push("mode_counts");
+    }
+    mismatched
+}
@@ -6,20 +6,101 @@
 // Boundary convention: lower-inclusive, upper-exclusive for L1–L4.
 // L5 is closed at both ends: [0.8, 1.0].
 
+use crate::error::PmatrixError;
+use crate::schema::{MODES, RISK_LEVELS};
+
+use serde::de::{Deserializer, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
 /// Maps a risk_score to the corresponding operating mode string.
 ///
 /// Returns None if risk_score is outside [0.0, 1.0].
 pub fn demo_partition_map(risk_score: f64) -> Option<&'static str> {
-    if risk_score < 0.0 || risk_score > 1.0 || risk_score.is_nan() {
+    if !(0.0..=1.0).contains(&risk_score) {
+        return None;
+    }
+    // Count the thresholds at or below the score instead of branching on
+    // each in turn. Equivalent to the threshold chain, but free of
+    // data-dependent branches, so unordered scores classify several times
+    // faster (see benches/partition_map.rs).
+    let i = DEFAULT_BOUNDARIES.iter().map(|&t| (risk_score >= t) as usize).sum::<usize>();
+    Some(MODE_FOR_BUCKET[i])
+}
+
+/// Classifies `risk_score` by exact comparison with the boundaries; this is
+/// what `demo_partition_map` and INV-C1 do.
+///
+/// The boundaries are the f64 values nearest to 0.2, 0.4, 0.6 and 0.8, so a
+/// score one ULP below a boundary stays in the lower mode: `0.2` is Normal
+/// but `0.19999999999999998` is Optimal. Arithmetic that "should" land on a
+/// boundary often falls just short of it — `1.0 - 0.8` is
+/// `0.19999999999999996` — and classifies one mode lower than its printed
+/// decimal suggests. See `partition_map_tolerant` for analytics that should
+/// not depend on that.
+pub fn partition_map_exact(risk_score: f64) -> Option<&'static str> {
+    demo_partition_map(risk_score)
+}
+
+/// Suggested tolerance for `partition_map_tolerant`: a few ULPs at the
+/// boundaries, far below any meaningful difference in risk.
+pub const BOUNDARY_EPS: f64 = 1e-12;
+
+/// Like `partition_map_exact`, but a score at most `eps` below a boundary
+/// is classified as if it were on it, so arithmetic noise cannot demote it.
+///
+/// Not normative: INV-C1 compares exactly, so a record whose mode was
+/// chosen with this map fails INV-C1 when its score is just below a
+/// boundary. Round or recompute the score instead when emitting records.
+pub fn partition_map_tolerant(risk_score: f64, eps: f64) -> Option<&'static str> {
+    if !(0.0..=1.0).contains(&risk_score) {
+        return None;
+    }
+    let i = DEFAULT_BOUNDARIES.iter().map(|&t| (risk_score + eps >= t) as usize).sum::<usize>();
+    Some(MODE_FOR_BUCKET[i])
+}
+
+/// Rounds `value` half away from zero to `decimals` decimal places.
+///
+/// From 17 decimals on, every f64 is returned unchanged: it already has no
+/// more significant digits than that.
+pub fn round_to_decimals(value: f64, decimals: u32) -> f64 {
+    if decimals >= 17 {
+        return value;
+    }
+    let scale = 10f64.powi(decimals as i32);
+    (value * scale).round() / scale
+}
+
+/// Classifies `risk_score` after rounding it to `decimals` places, so that
+/// `0.5999999999` classifies as `0.6` (Alert) at 4 decimals.
+///
+/// This changes classification semantics and only makes sense when it
+/// matches the rounding the producer applied before choosing the mode.
+/// Returns None if the rounded score is outside [0.0, 1.0].
+pub fn classify_rounded(risk_score: f64, decimals: u32) -> Option<&'static str> {
+    demo_partition_map(round_to_decimals(risk_score, decimals))
+}
+
+/// The normative partition boundaries (D1-A §3.4): the inclusive lower
+/// bounds of Normal, Caution, Alert and Halt.
+pub const DEFAULT_BOUNDARIES: [f64; 4] = [0.2, 0.4, 0.6, 0.8];
+
+/// The mode of each bucket delimited by `DEFAULT_BOUNDARIES`, lowest first.
+/// Bucket `i` holds the scores that reach exactly `i` boundaries; aligned
+/// with `MODES`.
+pub const MODE_FOR_BUCKET: [&str; 5] = MODES;
+
+/// Maps a risk_score directly to its risk_level, without going through the
+/// mode string.
+///
+/// Returns None if risk_score is outside [0.0, 1.0].
+pub fn risk_score_to_level(risk_score: f64) -> Option<&'static str> {
+    if !(0.0..=1.0).contains(&risk_score) {
         return None;
     }
-    Some(match () {
-        _ if risk_score < 0.2 => "Optimal",
-        _ if risk_score < 0.4 => "Normal",
-        _ if risk_score < 0.6 => "Caution",
-        _ if risk_score < 0.8 => "Alert",
-        _ => "Halt",
-    })
+    let i = DEFAULT_BOUNDARIES.iter().map(|&t| (risk_score >= t) as usize).sum::<usize>();
+    Some(RISK_LEVELS[i])
 }
 
 /// Maps a mode string to the corresponding risk_level string.
@@ -35,3 +116,359 @@ pub fn mode_to_risk_level(mode: &str) -> Option<&'static str> {
         _ => None,
     }
 }
+
+/// Returns the risk_score interval `(lower, upper)` assigned to a mode.
+///
+/// The lower bound is inclusive; the upper bound is exclusive except for
+/// Halt, whose interval [0.8, 1.0] is closed. Returns None for unknown modes.
+pub fn mode_interval(mode: &str) -> Option<(f64, f64)> {
+    match mode {
+        "Optimal" => Some((0.0, DEFAULT_BOUNDARIES[0])),
+        "Normal" => Some((DEFAULT_BOUNDARIES[0], DEFAULT_BOUNDARIES[1])),
+        "Caution" => Some((DEFAULT_BOUNDARIES[1], DEFAULT_BOUNDARIES[2])),
+        "Alert" => Some((DEFAULT_BOUNDARIES[2], DEFAULT_BOUNDARIES[3])),
+        "Halt" => Some((DEFAULT_BOUNDARIES[3], 1.0)),
+        _ => None,
+    }
+}
+
+/// Formats a mode's interval using the boundary convention, e.g. "[0.4, 0.6)".
+pub fn format_interval(mode: &str) -> Option<String> {
+    let (lower, upper) = mode_interval(mode)?;
+    let close = if mode == "Halt" { ']' } else { ')' };
+    Some(format!("[{}, {}{}", lower, upper, close))
+}
+
+/// A mode → risk_level mapping consulted by INV-C2.
+///
+/// The default is the canonical 1:1 ordering (Optimal→L1 … Halt→L5). Custom
+/// profiles may remap modes, e.g. Alert→L3, but must cover all five modes
+/// with defined risk levels.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelMap {
+    levels: [&'static str; 5],
+}
+
+impl Default for LevelMap {
+    fn default() -> Self {
+        LevelMap { levels: RISK_LEVELS }
+    }
+}
+
+impl LevelMap {
+    /// Builds a level map from (mode, risk_level) pairs.
+    ///
+    /// Fails if a mode or level is unknown, or if any of the five modes is
+    /// missing or listed twice.
+    pub fn new<'a>(pairs: impl IntoIterator<Item = (&'a str, &'a str)>) -> Result<Self, PmatrixError> {
+        let mut levels: [Option<&'static str>; 5] = [None; 5];
+        for (mode, level) in pairs {
+            let i = MODES.iter().position(|m| *m == mode).ok_or_else(|| {
+                PmatrixError::Config(format!("level map: unknown mode `{}`", mode))
+            })?;
+            let level = RISK_LEVELS.iter().find(|l| **l == level).ok_or_else(|| {
+                PmatrixError::Config(format!("level map: unknown risk_level `{}` for {}", level, mode))
+            })?;
+            if levels[i].replace(level).is_some() {
+                return Err(PmatrixError::Config(format!("level map: mode {} listed twice", mode)));
+            }
+        }
+
+        let mut out = RISK_LEVELS;
+        for (i, level) in levels.iter().enumerate() {
+            out[i] = level.ok_or_else(|| {
+                PmatrixError::Config(format!("level map: missing mode {}", MODES[i]))
+            })?;
+        }
+        Ok(LevelMap { levels: out })
+    }
+
+    /// Parses a level map from a JSON object such as `{"Optimal": "L1", ...}`.
+    /// Every key is kept, so a mode given twice is rejected as by `new`.
+    pub fn from_json(input: &str) -> Result<Self, PmatrixError> {
+        let JsonEntries(entries) = serde_json::from_str(input)?;
+        LevelMap::new(entries.iter().map(|(m, l)| (m.as_str(), l.as_str())))
+    }
+
+    /// Returns the risk_level for a mode, or None if the mode is unknown.
+    pub fn level_for(&self, mode: &str) -> Option<&'static str> {
+        MODES.iter().position(|m| *m == mode).map(|i| self.levels[i])
+    }
+}
+
+// The entries of a JSON object of strings in input order, duplicates
+// included (a map type would keep only the last of each key).
+struct JsonEntries(Vec<(String, String)>);
+
+impl<'de> Deserialize<'de> for JsonEntries {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntriesVisitor;
+
+        impl<'de> Visitor<'de> for EntriesVisitor {
+            type Value = JsonEntries;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an object mapping modes to risk levels")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JsonEntries, A::Error> {
+                let mut entries = Vec::new();
+                while let Some(entry) = map.next_entry()? {
+                    entries.push(entry);
+                }
+                Ok(JsonEntries(entries))
+            }
+        }
+
+        deserializer.deserialize_map(EntriesVisitor)
+    }
+}
+
+/// One entry of a partition map: risk_scores in `[lower, upper)` (or
+/// `[lower, upper]` when `upper_closed`) classify as `mode`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PartitionInterval {
+    pub mode: &'static str,
+    pub lower: f64,
+    pub upper: f64,
+    pub upper_closed: bool,
+}
+
+/// A risk_score → mode partition consulted by INV-C1.
+///
+/// The default is the normative five-way split at 0.2/0.4/0.6/0.8. The
+/// intervals are public so that editors can adjust boundaries and re-check
+/// them with `validate_partition_coverage`, which also rejects intervals out
+/// of order, as `map` requires; the constructor sorts and checks them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PartitionMap {
+    pub intervals: Vec<PartitionInterval>,
+}
+
+impl Default for PartitionMap {
+    fn default() -> Self {
+        let intervals = MODES
+            .iter()
+            .map(|&mode| {
+                let (lower, upper) = mode_interval(mode).expect("MODES all have intervals");
+                PartitionInterval { mode, lower, upper, upper_closed: mode == "Halt" }
+            })
+            .collect();
+        PartitionMap { intervals }
+    }
+}
+
+impl PartitionMap {
+    /// Builds a partition map, rejecting intervals that do not tile [0, 1].
+    /// The intervals are stored sorted by lower bound, as `map` requires.
+    pub fn new(mut intervals: Vec<PartitionInterval>) -> Result<Self, CoverageError> {
+        intervals.sort_by(|a, b| a.lower.total_cmp(&b.lower));
+        let map = PartitionMap { intervals };
+        validate_partition_coverage(&map)?;
+        Ok(map)
+    }
+
+    /// Builds a partition map from the lower bounds of Normal, Caution, Alert
+    /// and Halt. Optimal starts at 0.0 and Halt is closed at 1.0.
+    pub fn from_thresholds(thresholds: [f64; 4]) -> Result<Self, CoverageError> {
+        let bounds = [0.0, thresholds[0], thresholds[1], thresholds[2], thresholds[3], 1.0];
+        let intervals = MODES
+            .iter()
+            .enumerate()
+            .map(|(i, &mode)| PartitionInterval {
+                mode,
+                lower: bounds[i],
+                upper: bounds[i + 1],
+                upper_closed: mode == "Halt",
+            })
+            .collect();
+        PartitionMap::new(intervals)
+    }
+
+    /// Returns the mode for a risk_score, or None if no interval contains it.
+    /// Intervals are lower-inclusive and upper-exclusive, except a closed
+    /// upper end.
+    ///
+    /// Finds the interval by binary search over the lower bounds, so the
+    /// intervals must be sorted by lower bound, as every constructor leaves
+    /// them and `validate_partition_coverage` requires. On the default map
+    /// the result is identical to `demo_partition_map` for every f64.
+    pub fn classify(&self, risk_score: f64) -> Option<&'static str> {
+        self.map(risk_score)
+    }
+
+    /// The lookup behind `classify`, in O(log n) for maps with many
+    /// intervals.
+    pub fn map(&self, risk_score: f64) -> Option<&'static str> {
+        debug_assert!(
+            self.intervals.windows(2).all(|w| w[0].lower <= w[1].lower),
+            "PartitionMap::map requires intervals sorted by lower bound"
+        );
+        // The last interval starting at or below the score is the only
+        // candidate; NaN starts no interval.
+        let i = self.intervals.partition_point(|i| i.lower <= risk_score).checked_sub(1)?;
+        let candidate = &self.intervals[i];
+        (risk_score < candidate.upper || (candidate.upper_closed && risk_score == candidate.upper))
+            .then_some(candidate.mode)
+    }
+}
+
+/// One row of the partition legend returned by `partition_table`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LegendEntry {
+    pub mode: &'static str,
+    /// The risk_level of `mode` under the level map.
+    pub level: &'static str,
+    pub lower: f64,
+    pub upper: f64,
+    /// True if `upper` itself belongs to the interval.
+    pub upper_inclusive: bool,
+}
+
+/// Lists the intervals of `partition` from lowest to highest with the mode
+/// and risk_level each classifies as, so that UIs can render exactly the
+/// thresholds validation uses. The intervals are listed in stored order,
+/// which is sorted for every map that passes `validate_partition_coverage`.
+pub fn partition_table(partition: &PartitionMap, levels: &LevelMap) -> Vec<LegendEntry> {
+    partition
+        .intervals
+        .iter()
+        .map(|i| LegendEntry {
+            mode: i.mode,
+            level: levels.level_for(i.mode).unwrap_or("?"),
+            lower: i.lower,
+            upper: i.upper,
+            upper_inclusive: i.upper_closed,
+        })
+        .collect()
+}
+
+/// Named partition presets: name, lower bounds of Normal/Caution/Alert/Halt,
+/// and a one-line description.
+pub const THRESHOLD_PROFILES: [(&str, [f64; 4], &str); 2] = [
+    ("default", DEFAULT_BOUNDARIES, "normative 0.2/0.4/0.6/0.8 split (D1-A §3.4)"),
+    ("conservative", [0.15, 0.3, 0.45, 0.6], "lower cut points; reaches Halt from 0.6"),
+];
+
+/// Returns the partition map of a named preset from `THRESHOLD_PROFILES`,
+/// or None for an unknown name.
+pub fn builtin_profile(name: &str) -> Option<PartitionMap> {
+    THRESHOLD_PROFILES
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .map(|(_, t, _)| PartitionMap::from_thresholds(*t).expect("built-in profiles tile [0, 1]"))
+}
+
+/// Why a partition map fails to tile [0, 1] exactly.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CoverageError {
+    /// The map has no intervals.
+    Empty,
+    /// An interval is not one of the five modes.
+    UnknownMode(String),
+    /// A boundary is NaN or infinite.
+    NonFinite { mode: &'static str },
+    /// An interval has `lower >= upper`.
+    EmptyInterval { mode: &'static str, lower: f64, upper: f64 },
+    /// An interval starting at `lower` is listed after one starting at `after`.
+    Unsorted { lower: f64, after: f64 },
+    /// The lowest interval does not start at 0.0.
+    StartsAbove { lower: f64 },
+    /// No interval covers `(from, to)`.
+    Gap { from: f64, to: f64 },
+    /// Two intervals both contain the boundary `at`.
+    Overlap { at: f64 },
+    /// An interval other than the last is closed at its upper boundary.
+    ClosedInterior { at: f64 },
+    /// The highest interval does not end at 1.0.
+    EndsBelow { upper: f64 },
+    /// The highest interval excludes 1.0.
+    OpenAtOne,
+}
+
+impl fmt::Display for CoverageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoverageError::Empty => write!(f, "partition map has no intervals"),
+            CoverageError::UnknownMode(m) => write!(f, "unknown mode `{}`", m),
+            CoverageError::NonFinite { mode } => write!(f, "{} has a non-finite boundary", mode),
+            CoverageError::EmptyInterval { mode, lower, upper } => {
+                write!(f, "{} interval [{}, {}) is empty", mode, lower, upper)
+            }
+            CoverageError::Unsorted { lower, after } => {
+                write!(f, "interval starting at {} is listed after one starting at {}", lower, after)
+            }
+            CoverageError::StartsAbove { lower } => {
+                write!(f, "gap at [0, {}): lowest interval must start at 0", lower)
+            }
+            CoverageError::Gap { from, to } => write!(f, "gap between {} and {}", from, to),
+            CoverageError::Overlap { at } => write!(f, "intervals overlap at {}", at),
+            CoverageError::ClosedInterior { at } => {
+                write!(f, "boundary {} is closed; only 1.0 may be a closed endpoint", at)
+            }
+            CoverageError::EndsBelow { upper } => {
+                write!(f, "gap at ({}, 1]: highest interval must end at 1", upper)
+            }
+            CoverageError::OpenAtOne => write!(f, "1.0 is not covered: highest interval must be closed"),
+        }
+    }
+}
+
+impl std::error::Error for CoverageError {}
+
+impl From<CoverageError> for PmatrixError {
+    fn from(e: CoverageError) -> Self {
+        PmatrixError::Config(format!("partition map: {}", e))
+    }
+}
+
+/// Checks that a partition map's intervals exactly tile [0, 1].
+///
+/// Intervals must be sorted by lower bound, as `PartitionMap::map` requires.
+/// Every boundary must be finite, each interval non-empty, adjacent intervals
+/// must meet with no gap or overlap, and only the highest interval may (and
+/// must) be closed, at 1.0.
+pub fn validate_partition_coverage(map: &PartitionMap) -> Result<(), CoverageError> {
+    let sorted = &map.intervals;
+    for i in sorted {
+        if !MODES.contains(&i.mode) {
+            return Err(CoverageError::UnknownMode(i.mode.to_string()));
+        }
+        if !i.lower.is_finite() || !i.upper.is_finite() {
+            return Err(CoverageError::NonFinite { mode: i.mode });
+        }
+        if i.lower >= i.upper {
+            return Err(CoverageError::EmptyInterval { mode: i.mode, lower: i.lower, upper: i.upper });
+        }
+    }
+    if let Some(w) = sorted.windows(2).find(|w| w[1].lower < w[0].lower) {
+        return Err(CoverageError::Unsorted { lower: w[1].lower, after: w[0].lower });
+    }
+
+    let (first, last) = match (sorted.first(), sorted.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => return Err(CoverageError::Empty),
+    };
+    if first.lower != 0.0 {
+        return Err(CoverageError::StartsAbove { lower: first.lower });
+    }
+    for pair in sorted.windows(2) {
+        let (prev, next) = (&pair[0], &pair[1]);
+        if next.lower > prev.upper {
+            return Err(CoverageError::Gap { from: prev.upper, to: next.lower });
+        }
+        if next.lower < prev.upper {
+            return Err(CoverageError::Overlap { at: next.lower });
+        }
+        if prev.upper_closed {
+            return Err(CoverageError::ClosedInterior { at: prev.upper });
+        }
+    }
+    if last.upper != 1.0 {
+        return Err(CoverageError::EndsBelow { upper: last.upper });
+    }
+    if !last.upper_closed {
+        return Err(CoverageError::OpenAtOne);
+    }
+    Ok(())
+}
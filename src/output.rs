@@ -0,0 +1,51 @@
+// output.rs — Record Output Helpers
+//
+// Writes record streams as a JSON array or as NDJSON without collecting
+// them first, so memory use does not grow with the stream length.
+
+use crate::error::PmatrixError;
+use crate::schema::RuntimeStateRecord;
+
+use std::io::Write;
+
+// Serializer I/O failures are reported as I/O errors, not parse errors.
+fn write_record<W: Write>(w: &mut W, record: &RuntimeStateRecord) -> Result<(), PmatrixError> {
+    serde_json::to_writer(w, record)
+        .map_err(|e| if e.is_io() { PmatrixError::Io(e.into()) } else { PmatrixError::Parse(e) })
+}
+
+/// Streams records as a compact JSON array: `[`, comma-separated records, `]`.
+///
+/// Returns the number of records written.
+pub fn write_records_array<W: Write>(
+    records: impl Iterator<Item = RuntimeStateRecord>,
+    w: &mut W,
+) -> Result<usize, PmatrixError> {
+    w.write_all(b"[")?;
+    let mut count = 0;
+    for record in records {
+        if count > 0 {
+            w.write_all(b",")?;
+        }
+        write_record(w, &record)?;
+        count += 1;
+    }
+    w.write_all(b"]")?;
+    Ok(count)
+}
+
+/// Streams records as NDJSON, one compact record per line.
+///
+/// Returns the number of records written.
+pub fn write_records_ndjson<W: Write>(
+    records: impl Iterator<Item = RuntimeStateRecord>,
+    w: &mut W,
+) -> Result<usize, PmatrixError> {
+    let mut count = 0;
+    for record in records {
+        write_record(w, &record)?;
+        w.write_all(b"\n")?;
+        count += 1;
+    }
+    Ok(count)
+}
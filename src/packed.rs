@@ -0,0 +1,135 @@
+// packed.rs — Fixed-Width Binary Record Layout
+//
+// A dense, fixed-size encoding for archives and memory-mapped analytics.
+// Every record packs into exactly `PACKED_LEN` bytes, so record `i` of a
+// file of packed records starts at byte `i * PACKED_LEN`.
+//
+// Layout (all multi-byte fields little-endian):
+//
+//   offset  size  field
+//   0       1     flags: bit 0 set if sequence_number is present; other bits 0
+//   1       1     spec_version major   ("pmatrix-MAJOR.MINOR")
+//   2       1     spec_version minor
+//   3       1     schema_version major ("MAJOR.MINOR.PATCH")
+//   4       1     schema_version minor
+//   5       1     schema_version patch
+//   6       1     mode, as its index in MODES (0 = Optimal … 4 = Halt)
+//   7       1     risk_level, as its index in RISK_LEVELS (0 = L1 … 4 = L5)
+//   8       8     timestamp (u64)
+//   16      8     functions.baseline (f64)
+//   24      8     functions.norm (f64)
+//   32      8     functions.stability (f64)
+//   40      8     functions.meta_control (f64)
+//   48      8     stability_score (f64)
+//   56      8     risk_score (f64)
+//   64      8     sequence_number (u64), 0 when absent
+
+use crate::error::PmatrixError;
+use crate::schema::{Functions, RuntimeStateRecord, MODES, RISK_LEVELS};
+
+/// Size in bytes of one packed record.
+pub const PACKED_LEN: usize = 72;
+
+const HAS_SEQUENCE: u8 = 1;
+
+/// Packs a record into the fixed-width layout described in this module.
+///
+/// Floats are stored bit for bit, so NaN payloads and `-0.0` survive.
+/// Fails with `PmatrixError::Encode` if the mode or risk_level is not one of
+/// the five defined values, or if a version is not in canonical form with
+/// every component in 0..=255 (such as `pmatrix-3.5` and `1.0.0`).
+pub fn to_packed(record: &RuntimeStateRecord) -> Result<[u8; PACKED_LEN], PmatrixError> {
+    let unpackable = |what: String| PmatrixError::Encode(format!("record cannot be packed: {}", what));
+    let spec = record
+        .spec_version
+        .strip_prefix("pmatrix-")
+        .and_then(version_parts::<2>)
+        .ok_or_else(|| unpackable(format!("spec_version `{}` is not pmatrix-MAJOR.MINOR", record.spec_version)))?;
+    let schema = version_parts::<3>(&record.schema_version)
+        .ok_or_else(|| unpackable(format!("schema_version `{}` is not MAJOR.MINOR.PATCH", record.schema_version)))?;
+    let mode = MODES
+        .iter()
+        .position(|m| *m == record.mode)
+        .ok_or_else(|| unpackable(format!("unknown mode `{}`", record.mode)))?;
+    let level = RISK_LEVELS
+        .iter()
+        .position(|l| *l == record.risk_level)
+        .ok_or_else(|| unpackable(format!("unknown risk_level `{}`", record.risk_level)))?;
+
+    let mut out = [0u8; PACKED_LEN];
+    out[0] = if record.sequence_number.is_some() { HAS_SEQUENCE } else { 0 };
+    out[1..3].copy_from_slice(&spec);
+    out[3..6].copy_from_slice(&schema);
+    out[6] = mode as u8;
+    out[7] = level as u8;
+    out[8..16].copy_from_slice(&record.timestamp.to_le_bytes());
+    let f = &record.functions;
+    let floats = [f.baseline, f.norm, f.stability, f.meta_control, record.stability_score, record.risk_score];
+    for (i, v) in floats.iter().enumerate() {
+        out[16 + 8 * i..24 + 8 * i].copy_from_slice(&v.to_le_bytes());
+    }
+    out[64..72].copy_from_slice(&record.sequence_number.unwrap_or(0).to_le_bytes());
+    Ok(out)
+}
+
+/// Decodes a record packed by `to_packed`.
+///
+/// Fails with `PmatrixError::Parse` if `bytes` is not exactly `PACKED_LEN`
+/// long, a reserved flag bit is set, the mode or level byte is out of range,
+/// or the sequence_number field is non-zero while its flag is clear. The
+/// record itself is not validated against the invariants.
+pub fn from_packed(bytes: &[u8]) -> Result<RuntimeStateRecord, PmatrixError> {
+    let invalid = |msg: String| PmatrixError::Parse(serde::de::Error::custom(format!("packed record: {}", msg)));
+    let bytes: &[u8; PACKED_LEN] = bytes
+        .try_into()
+        .map_err(|_| invalid(format!("expected {} bytes, got {}", PACKED_LEN, bytes.len())))?;
+    if bytes[0] & !HAS_SEQUENCE != 0 {
+        return Err(invalid(format!("reserved flag bits set in {:#04x}", bytes[0])));
+    }
+    let mode = *MODES.get(bytes[6] as usize).ok_or_else(|| invalid(format!("mode byte {} is out of range", bytes[6])))?;
+    let level = *RISK_LEVELS
+        .get(bytes[7] as usize)
+        .ok_or_else(|| invalid(format!("risk_level byte {} is out of range", bytes[7])))?;
+    let u64_at = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().expect("8-byte field"));
+    let f64_at = |at: usize| f64::from_bits(u64_at(at));
+    let sequence = u64_at(64);
+    let sequence_number = if bytes[0] & HAS_SEQUENCE != 0 {
+        Some(sequence)
+    } else if sequence == 0 {
+        None
+    } else {
+        return Err(invalid(format!("sequence_number {} is set but flagged absent", sequence)));
+    };
+
+    Ok(RuntimeStateRecord {
+        spec_version: format!("pmatrix-{}.{}", bytes[1], bytes[2]),
+        schema_version: format!("{}.{}.{}", bytes[3], bytes[4], bytes[5]),
+        timestamp: u64_at(8),
+        sequence_number,
+        functions: Functions {
+            baseline: f64_at(16),
+            norm: f64_at(24),
+            stability: f64_at(32),
+            meta_control: f64_at(40),
+        },
+        stability_score: f64_at(48),
+        risk_score: f64_at(56),
+        mode: mode.to_string(),
+        risk_level: level.to_string(),
+    })
+}
+
+// Splits a dotted version into exactly N canonical decimal bytes, so that
+// decoding reproduces the string exactly.
+fn version_parts<const N: usize>(version: &str) -> Option<[u8; N]> {
+    let mut parts = [0u8; N];
+    let mut split = version.split('.');
+    for slot in &mut parts {
+        let p = split.next()?;
+        if p.is_empty() || !p.bytes().all(|b| b.is_ascii_digit()) || (p.len() > 1 && p.starts_with('0')) {
+            return None;
+        }
+        *slot = p.parse().ok()?;
+    }
+    split.next().is_none().then_some(parts)
+}
@@ -0,0 +1,57 @@
+// precision.rs — Lossy Float Detection
+//
+// Flags score and function values whose shortest round-trip representation
+// has more significant digits than a producer would deliberately write, such
+// as 0.42000000000000004 from 0.1 + 0.32. Such values usually come from
+// accumulated arithmetic noise and can flip results at partition boundaries.
+// This is a data-quality hint, not an invariant.
+
+use crate::schema::RuntimeStateRecord;
+
+/// A value with more significant digits than allowed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LossyValue {
+    pub field: &'static str,
+    pub value: f64,
+    /// Significant digits of the value's shortest round-trip form.
+    pub digits: usize,
+    /// The value rounded to the allowed number of significant digits.
+    pub cleaned: f64,
+}
+
+/// Returns the number of significant digits in the shortest representation
+/// that round-trips to `v`. Zero and non-finite values have none.
+pub fn significant_digits(v: f64) -> usize {
+    if v == 0.0 || !v.is_finite() {
+        return 0;
+    }
+    let repr = format!("{:e}", v.abs());
+    let mantissa = repr.split('e').next().unwrap_or_default();
+    mantissa.bytes().filter(u8::is_ascii_digit).count()
+}
+
+/// Lists the function values and scores of a record, in schema order, whose
+/// shortest round-trip form has more than `max_digits` significant digits.
+pub fn lossy_values(record: &RuntimeStateRecord, max_digits: usize) -> Vec<LossyValue> {
+    let f = &record.functions;
+    [
+        ("functions.baseline", f.baseline),
+        ("functions.norm", f.norm),
+        ("functions.stability", f.stability),
+        ("functions.meta_control", f.meta_control),
+        ("stability_score", record.stability_score),
+        ("risk_score", record.risk_score),
+    ]
+    .into_iter()
+    .filter_map(|(field, value)| {
+        let digits = significant_digits(value);
+        (digits > max_digits).then(|| LossyValue { field, value, digits, cleaned: round_to(value, max_digits) })
+    })
+    .collect()
+}
+
+// Rounds to `digits` significant digits via the decimal representation, so
+// the result is the nearest float to the rounded decimal.
+fn round_to(v: f64, digits: usize) -> f64 {
+    format!("{:.*e}", digits.saturating_sub(1), v).parse().unwrap_or(v)
+}
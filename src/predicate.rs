@@ -0,0 +1,317 @@
+// predicate.rs — Record Predicate Expressions
+//
+// A small expression language for selecting records, e.g.
+//
+//   risk_score > 0.6 && mode != Optimal
+//   risk_level >= L4 || (sequence_number < 100 && !(mode == Caution))
+//
+// A predicate is a comparison `field op value`, combined with `&&`, `||`,
+// `!` and parentheses; `&&` binds tighter than `||`. Numeric fields compare
+// with numbers. mode and risk_level compare with a mode or level name:
+// `==`/`!=` compare the stored string, and the ordering operators compare
+// severity (Optimal < … < Halt, L1 < … < L5).
+
+use crate::error::PmatrixError;
+use crate::schema::{RuntimeStateRecord, MODES, RISK_LEVELS};
+
+type Accessor = fn(&RuntimeStateRecord) -> Option<f64>;
+
+/// Numeric fields a predicate can compare, with their accessors. The four
+/// functions may also be written with a `functions.` prefix.
+const NUMERIC_FIELDS: [(&str, Accessor); 8] = [
+    ("timestamp", |r| Some(r.timestamp as f64)),
+    ("sequence_number", |r| r.sequence_number.map(|n| n as f64)),
+    ("baseline", |r| Some(r.functions.baseline)),
+    ("norm", |r| Some(r.functions.norm)),
+    ("stability", |r| Some(r.functions.stability)),
+    ("meta_control", |r| Some(r.functions.meta_control)),
+    ("stability_score", |r| Some(r.stability_score)),
+    ("risk_score", |r| Some(r.risk_score)),
+];
+
+/// A parsed predicate over runtime state records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predicate {
+    expr: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    /// `field` indexes NUMERIC_FIELDS.
+    Number { field: usize, op: CmpOp, value: f64 },
+    Mode { op: CmpOp, value: &'static str },
+    Level { op: CmpOp, value: &'static str },
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    // Applies the operator to an ordering; None (NaN) satisfies only `!=`.
+    fn holds(self, ord: Option<std::cmp::Ordering>) -> bool {
+        use std::cmp::Ordering::*;
+        match ord {
+            None => self == CmpOp::Ne,
+            Some(o) => match self {
+                CmpOp::Eq => o == Equal,
+                CmpOp::Ne => o != Equal,
+                CmpOp::Lt => o == Less,
+                CmpOp::Le => o != Greater,
+                CmpOp::Gt => o == Greater,
+                CmpOp::Ge => o != Less,
+            },
+        }
+    }
+}
+
+impl Predicate {
+    /// Parses an expression such as `risk_score > 0.6 && mode != Optimal`.
+    ///
+    /// Fails with `PmatrixError::Config` naming the column of the first
+    /// problem: an unknown field, mode or level, a missing operator or
+    /// value, or unbalanced parentheses.
+    pub fn parse(source: &str) -> Result<Self, PmatrixError> {
+        let tokens = tokenize(source)?;
+        let mut parser = Parser { source, tokens: &tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            return Err(parser.error(token.column, &format!("unexpected `{}`", token.text)));
+        }
+        Ok(Predicate { expr })
+    }
+
+    /// Returns true if the record satisfies the predicate.
+    ///
+    /// A NaN value satisfies only `!=`, and an absent sequence_number
+    /// satisfies no comparison. A mode or level outside the defined set
+    /// satisfies `==`/`!=` by string but no ordering comparison.
+    pub fn matches(&self, record: &RuntimeStateRecord) -> bool {
+        eval(&self.expr, record)
+    }
+}
+
+fn eval(expr: &Expr, r: &RuntimeStateRecord) -> bool {
+    match expr {
+        Expr::Number { field, op, value } => (NUMERIC_FIELDS[*field].1)(r).is_some_and(|v| op.holds(v.partial_cmp(value))),
+        Expr::Mode { op, value } => compare_ordinal(&MODES, &r.mode, *op, value),
+        Expr::Level { op, value } => compare_ordinal(&RISK_LEVELS, &r.risk_level, *op, value),
+        Expr::Not(e) => !eval(e, r),
+        Expr::And(a, b) => eval(a, r) && eval(b, r),
+        Expr::Or(a, b) => eval(a, r) || eval(b, r),
+    }
+}
+
+fn compare_ordinal(order: &[&str], stored: &str, op: CmpOp, value: &str) -> bool {
+    match op {
+        CmpOp::Eq => stored == value,
+        CmpOp::Ne => stored != value,
+        _ => {
+            let rank = |s: &str| order.iter().position(|o| *o == s);
+            match (rank(stored), rank(value)) {
+                (Some(a), Some(b)) => op.holds(Some(a.cmp(&b))),
+                _ => false,
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Kind {
+    Word,
+    Number(f64),
+    Cmp(CmpOp),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: Kind,
+    text: String,
+    /// 1-based character column.
+    column: usize,
+}
+
+fn config_error(source: &str, column: usize, msg: &str) -> PmatrixError {
+    PmatrixError::Config(format!("predicate `{}`: {} at column {}", source, msg, column))
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, PmatrixError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        let (kind, len) = match two.as_str() {
+            "&&" => (Kind::And, 2),
+            "||" => (Kind::Or, 2),
+            "==" => (Kind::Cmp(CmpOp::Eq), 2),
+            "!=" => (Kind::Cmp(CmpOp::Ne), 2),
+            "<=" => (Kind::Cmp(CmpOp::Le), 2),
+            ">=" => (Kind::Cmp(CmpOp::Ge), 2),
+            _ => match c {
+                '<' => (Kind::Cmp(CmpOp::Lt), 1),
+                '>' => (Kind::Cmp(CmpOp::Gt), 1),
+                '!' => (Kind::Not, 1),
+                '(' => (Kind::Open, 1),
+                ')' => (Kind::Close, 1),
+                '=' => return Err(config_error(source, column, "`=` is not an operator; use `==`")),
+                '"' | '\'' => {
+                    let end = chars[i + 1..]
+                        .iter()
+                        .position(|&q| q == c)
+                        .ok_or_else(|| config_error(source, column, "unterminated quoted value"))?;
+                    let text: String = chars[i + 1..i + 1 + end].iter().collect();
+                    tokens.push(Token { kind: Kind::Word, text, column });
+                    i += end + 2;
+                    continue;
+                }
+                _ if c.is_ascii_digit() || c == '-' || c == '.' => {
+                    let mut len = 1;
+                    while let Some(&n) = chars.get(i + len) {
+                        let exponent_sign = (n == '-' || n == '+') && matches!(chars[i + len - 1], 'e' | 'E');
+                        if !(n.is_ascii_digit() || matches!(n, '.' | 'e' | 'E') || exponent_sign) {
+                            break;
+                        }
+                        len += 1;
+                    }
+                    let text: String = chars[i..i + len].iter().collect();
+                    let value = text
+                        .parse()
+                        .map_err(|_| config_error(source, column, &format!("`{}` is not a number", text)))?;
+                    (Kind::Number(value), len)
+                }
+                _ if c.is_alphanumeric() || c == '_' => {
+                    let len = chars[i..].iter().take_while(|&&n| n.is_alphanumeric() || matches!(n, '_' | '.')).count();
+                    (Kind::Word, len)
+                }
+                _ => return Err(config_error(source, column, &format!("unexpected character `{}`", c))),
+            },
+        };
+        tokens.push(Token { kind, text: chars[i..i + len].iter().collect(), column });
+        i += len;
+    }
+    Ok(tokens)
+}
+
+// Recursive descent over the grammar
+//   or      := and ("||" and)*
+//   and     := unary ("&&" unary)*
+//   unary   := "!" unary | "(" or ")" | field cmp value
+struct Parser<'a> {
+    source: &'a str,
+    tokens: &'a [Token],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<&'a Token> {
+        let token = self.tokens.get(self.pos);
+        self.pos += 1;
+        token
+    }
+
+    // Column just past the input, for errors at the end.
+    fn end(&self) -> usize {
+        self.source.chars().count() + 1
+    }
+
+    fn error(&self, column: usize, msg: &str) -> PmatrixError {
+        config_error(self.source, column, msg)
+    }
+
+    fn or(&mut self) -> Result<Expr, PmatrixError> {
+        let mut expr = self.and()?;
+        while self.peek().is_some_and(|t| t.kind == Kind::Or) {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, PmatrixError> {
+        let mut expr = self.unary()?;
+        while self.peek().is_some_and(|t| t.kind == Kind::And) {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, PmatrixError> {
+        let Some(token) = self.next() else {
+            return Err(self.error(self.end(), "expected a comparison"));
+        };
+        match token.kind {
+            Kind::Not => Ok(Expr::Not(Box::new(self.unary()?))),
+            Kind::Open => {
+                let expr = self.or()?;
+                match self.next() {
+                    Some(t) if t.kind == Kind::Close => Ok(expr),
+                    _ => Err(self.error(token.column, "unmatched `(`")),
+                }
+            }
+            Kind::Word => self.comparison(token),
+            _ => Err(self.error(token.column, &format!("expected a field name, found `{}`", token.text))),
+        }
+    }
+
+    fn comparison(&mut self, field: &Token) -> Result<Expr, PmatrixError> {
+        let name = match field.text.strip_prefix("functions.") {
+            Some(f @ ("baseline" | "norm" | "stability" | "meta_control")) => f,
+            _ => field.text.as_str(),
+        };
+        let op = match self.next() {
+            Some(Token { kind: Kind::Cmp(op), .. }) => *op,
+            Some(t) => return Err(self.error(t.column, &format!("expected a comparison operator after `{}`", field.text))),
+            None => return Err(self.error(self.end(), &format!("expected a comparison operator after `{}`", field.text))),
+        };
+        let value = self.next();
+        let (column, text) = value.map_or((self.end(), ""), |t| (t.column, t.text.as_str()));
+        if let Some(index) = NUMERIC_FIELDS.iter().position(|(n, _)| *n == name) {
+            return match value {
+                Some(Token { kind: Kind::Number(v), .. }) => Ok(Expr::Number { field: index, op, value: *v }),
+                _ => Err(self.error(column, &format!("expected a number to compare `{}` with", field.text))),
+            };
+        }
+        let (allowed, what): (&[&'static str], &str) = match name {
+            "mode" => (&MODES, "mode"),
+            "risk_level" => (&RISK_LEVELS, "risk_level"),
+            _ => {
+                let mut names: Vec<&str> = NUMERIC_FIELDS.iter().map(|(n, _)| *n).collect();
+                names.extend(["mode", "risk_level"]);
+                let msg = format!("unknown field `{}`; expected one of {}", field.text, names.join(", "));
+                return Err(self.error(field.column, &msg));
+            }
+        };
+        let Some(value) = allowed.iter().copied().find(|a| *a == text).filter(|_| value.is_some_and(|t| t.kind == Kind::Word))
+        else {
+            let msg = format!("expected a {} ({}), found `{}`", what, allowed.join(", "), text);
+            return Err(self.error(column, &msg));
+        };
+        Ok(if name == "mode" { Expr::Mode { op, value } } else { Expr::Level { op, value } })
+    }
+}
@@ -0,0 +1,58 @@
+// redact.rs — Record Redaction
+//
+// Hides the four function values of a record so that a log can be shared
+// without the underlying posture data. Mode, risk_level, timestamp and the
+// other metadata are preserved.
+
+use crate::schema::{Functions, RuntimeStateRecord};
+
+/// How `redact_with` replaces function values and scores.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RedactOptions {
+    /// Round each function value to the midpoint of its fifth of [0.0, 1.0]
+    /// (0.1, 0.3, 0.5, 0.7 or 0.9) instead of zeroing it.
+    pub bucket: bool,
+    /// Also set stability_score and risk_score to 0.0.
+    pub blank_scores: bool,
+}
+
+/// Zeroes the four function values, keeping every other field.
+///
+/// The result no longer matches its scores, so it fails INV-R6 when that
+/// check is enabled; the normative invariants are unaffected.
+pub fn redact(record: &RuntimeStateRecord) -> RuntimeStateRecord {
+    redact_with(record, RedactOptions::default())
+}
+
+/// Redacts function values (and optionally the scores) as `opts` specifies.
+///
+/// With `blank_scores`, INV-C1 and INV-C3 fail for every mode except Optimal,
+/// since mode and risk_level are preserved.
+pub fn redact_with(record: &RuntimeStateRecord, opts: RedactOptions) -> RuntimeStateRecord {
+    let hide = |v: f64| if opts.bucket { bucket(v) } else { 0.0 };
+    let f = &record.functions;
+    let mut redacted = RuntimeStateRecord {
+        functions: Functions {
+            baseline: hide(f.baseline),
+            norm: hide(f.norm),
+            stability: hide(f.stability),
+            meta_control: hide(f.meta_control),
+        },
+        ..record.clone()
+    };
+    if opts.blank_scores {
+        redacted.stability_score = 0.0;
+        redacted.risk_score = 0.0;
+    }
+    redacted
+}
+
+// Midpoint of the fifth of [0.0, 1.0] containing v. Out-of-range and NaN
+// values zero out rather than leak through.
+fn bucket(v: f64) -> f64 {
+    if !(0.0..=1.0).contains(&v) {
+        return 0.0;
+    }
+    let i = ((v * 5.0) as usize).min(4);
+    (2 * i + 1) as f64 / 10.0
+}
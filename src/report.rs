@@ -0,0 +1,532 @@
+// report.rs — Human-Readable Validation Reports
+//
+// Renders records and their invariant results for reviewers. These helpers
+// only format existing results; they never change conformance outcomes.
+
+use crate::demo::dominant_risk_factor;
+use crate::doctor::Finding;
+use crate::error::PmatrixError;
+use crate::invariants::{
+    explain_consistency_failure_with, invariant_description, InvariantResult, ValidationOptions,
+};
+use crate::mode::{demo_partition_map, format_interval, mode_to_risk_level, LegendEntry};
+use crate::schema::{RuntimeStateRecord, MODES};
+use crate::stats::{stream_stats, ModeStats, ScoreSummary, StreamStats};
+use crate::summary::BatchSummary;
+
+use std::collections::BTreeMap;
+
+/// How human-readable reports render timestamps. JSON output always keeps
+/// raw epoch seconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimestampStyle {
+    /// Raw epoch seconds.
+    #[default]
+    Epoch,
+    /// Epoch seconds followed by the RFC 3339 time at a UTC offset given in
+    /// seconds. Requires the `chrono` feature; without it this renders as `Epoch`.
+    Rfc3339 { utc_offset_secs: i32 },
+}
+
+/// Parses a UTC offset for `TimestampStyle::Rfc3339`: `UTC`, `Z`, `+HH:MM`,
+/// `-HH:MM` or `±HHMM`. Returns the offset in seconds.
+pub fn parse_utc_offset(text: &str) -> Result<i32, PmatrixError> {
+    if text.eq_ignore_ascii_case("utc") || text == "Z" {
+        return Ok(0);
+    }
+    let bad = || PmatrixError::Config(format!("timezone `{}`: expected UTC or an offset like +02:00", text));
+    let (sign, rest) = match text.as_bytes().first() {
+        Some(b'+') => (1, &text[1..]),
+        Some(b'-') => (-1, &text[1..]),
+        _ => return Err(bad()),
+    };
+    let (hh, mm) = match rest.len() {
+        5 if rest.as_bytes()[2] == b':' => (&rest[..2], &rest[3..]),
+        4 => (&rest[..2], &rest[2..]),
+        _ => return Err(bad()),
+    };
+    if !(hh.bytes().chain(mm.bytes())).all(|b| b.is_ascii_digit()) {
+        return Err(bad());
+    }
+    let (hours, minutes): (i32, i32) = (hh.parse().map_err(|_| bad())?, mm.parse().map_err(|_| bad())?);
+    if hours > 23 || minutes > 59 {
+        return Err(bad());
+    }
+    Ok(sign * (hours * 3600 + minutes * 60))
+}
+
+/// Formats a timestamp for a human-readable report, e.g.
+/// "1707500000 (2024-02-09T17:33:20Z)". Epochs that cannot be represented
+/// as a date are shown as "1707500000 (out of range)".
+pub fn format_timestamp(timestamp: u64, style: TimestampStyle) -> String {
+    match style {
+        TimestampStyle::Epoch => timestamp.to_string(),
+        TimestampStyle::Rfc3339 { utc_offset_secs } => match rfc3339(timestamp, utc_offset_secs) {
+            Some(time) => format!("{} ({})", timestamp, time),
+            None if cfg!(feature = "chrono") => format!("{} (out of range)", timestamp),
+            None => timestamp.to_string(),
+        },
+    }
+}
+
+#[cfg(feature = "chrono")]
+fn rfc3339(timestamp: u64, utc_offset_secs: i32) -> Option<String> {
+    use chrono::{DateTime, FixedOffset, SecondsFormat};
+    let offset = FixedOffset::east_opt(utc_offset_secs)?;
+    let utc = DateTime::from_timestamp(i64::try_from(timestamp).ok()?, 0)?;
+    Some(utc.with_timezone(&offset).to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+#[cfg(not(feature = "chrono"))]
+fn rfc3339(_timestamp: u64, _utc_offset_secs: i32) -> Option<String> {
+    None
+}
+
+/// Describes how a record's risk_score is classified by the partition map,
+/// e.g. "risk_score=0.42 ∈ [0.4, 0.6) → Caution / L3".
+pub fn classification_note(record: &RuntimeStateRecord) -> String {
+    match demo_partition_map(record.risk_score) {
+        Some(mode) => format!(
+            "risk_score={} ∈ {} → {} / {}",
+            record.risk_score,
+            format_interval(mode).unwrap_or_default(),
+            mode,
+            mode_to_risk_level(mode).unwrap_or("?"),
+        ),
+        None => format!("risk_score={} is outside [0.0, 1.0]; no mode applies", record.risk_score),
+    }
+}
+
+/// Renders the record as pretty JSON followed by a `//` comment block that
+/// summarizes classification and invariant outcomes. Failed invariants are
+/// listed individually.
+pub fn render_inspect(record: &RuntimeStateRecord, results: &[InvariantResult]) -> String {
+    render_inspect_with(record, results, &ValidationOptions::default())
+}
+
+/// Like `render_inspect`, explaining consistency failures with the partition,
+/// level map and classify precision of `opts`, which `results` were
+/// validated with.
+pub fn render_inspect_with(record: &RuntimeStateRecord, results: &[InvariantResult], opts: &ValidationOptions) -> String {
+    let json = serde_json::to_string_pretty(record).unwrap_or_default();
+    let passed = results.iter().filter(|r| r.passed).count();
+
+    let mut out = json;
+    out.push_str("\n\n// ---- inspection ----\n");
+    out.push_str(&format!("// classification: {}\n", classification_note(record)));
+    out.push_str(&format!(
+        "// recorded:       mode={}, risk_level={}\n",
+        record.mode, record.risk_level
+    ));
+    if let Some(explanation) = explain_consistency_failure_with(record, opts) {
+        out.push_str(&format!("// consistency:    {}\n", explanation));
+    }
+    let (factor, value) = dominant_risk_factor(&record.functions);
+    out.push_str(&format!(
+        "// demo hint:      risk driven primarily by low '{}' = {:.2} (demo model only)\n",
+        factor, value
+    ));
+    out.push_str(&format!("// invariants:     {}/{} passed\n", passed, results.len()));
+    for r in results.iter().filter(|r| !r.passed) {
+        out.push_str(&format!("// [{}] {} — {}\n", status(r), r.id, r.detail));
+    }
+    let verdict = if failed_ids(results).is_empty() { "CONFORMING" } else { "MALFORMED" };
+    out.push_str(&format!("// result:         {}\n", verdict));
+    out
+}
+
+fn summary_line(name: &str, summary: &Option<ScoreSummary>, pcts: &[(f64, f64)]) -> String {
+    let mut line = match summary {
+        Some(s) => format!("{:<16} min={} mean={} max={}", name, s.min, s.mean, s.max),
+        None => format!("{:<16} n/a", name),
+    };
+    for (p, v) in pcts {
+        line.push_str(&format!(" p{}={}", p, v));
+    }
+    line.push('\n');
+    line
+}
+
+/// Renders stream statistics with optional risk and stability percentiles.
+pub fn render_stats(
+    stats: &StreamStats,
+    risk_pcts: &[(f64, f64)],
+    stability_pcts: &[(f64, f64)],
+) -> String {
+    render_stats_with(stats, risk_pcts, stability_pcts, TimestampStyle::Epoch)
+}
+
+/// Like `render_stats`, rendering timestamps in the given style.
+pub fn render_stats_with(
+    stats: &StreamStats,
+    risk_pcts: &[(f64, f64)],
+    stability_pcts: &[(f64, f64)],
+    style: TimestampStyle,
+) -> String {
+    render_stats_colored(stats, risk_pcts, stability_pcts, style, false)
+}
+
+/// Like `render_stats_with`, optionally highlighting the result with ANSI
+/// colors for a terminal: the risk_score line is green while the mean risk
+/// classifies as Optimal or Normal, yellow for Caution and red for Alert or
+/// Halt, and the INV-T1 line is bold red. Without `color` the output is
+/// identical to `render_stats_with`.
+pub fn render_stats_colored(
+    stats: &StreamStats,
+    risk_pcts: &[(f64, f64)],
+    stability_pcts: &[(f64, f64)],
+    style: TimestampStyle,
+    color: bool,
+) -> String {
+    let paint = |line: String, code: &str| {
+        if color {
+            format!("\x1b[{}m{}\x1b[0m\n", code, line.trim_end_matches('\n'))
+        } else {
+            line
+        }
+    };
+    let mut out = format!("{:<16} {}\n", "records", stats.count);
+    if let (Some(first), Some(last)) = (stats.first_timestamp, stats.last_timestamp) {
+        out.push_str(&format!(
+            "{:<16} {} – {}\n",
+            "timestamps",
+            format_timestamp(first, style),
+            format_timestamp(last, style)
+        ));
+    }
+    if let (Some(min), Some(max)) = (stats.min_timestamp, stats.max_timestamp) {
+        if (Some(min), Some(max)) != (stats.first_timestamp, stats.last_timestamp) {
+            out.push_str(&format!(
+                "{:<16} {} – {}\n",
+                "time span",
+                format_timestamp(min, style),
+                format_timestamp(max, style)
+            ));
+        }
+    }
+    let risk_line = summary_line("risk_score", &stats.risk, risk_pcts);
+    out.push_str(&match stats.risk.and_then(|r| demo_partition_map(r.mean)) {
+        Some("Optimal" | "Normal") => paint(risk_line, "32"),
+        Some("Caution") => paint(risk_line, "33"),
+        Some(_) => paint(risk_line, "31"),
+        None => risk_line,
+    });
+    out.push_str(&summary_line("stability_score", &stats.stability, stability_pcts));
+    if stats.t1_violations > 0 {
+        let line = format!("{:<16} {} record(s) earlier than their predecessor\n", "INV-T1", stats.t1_violations);
+        out.push_str(&paint(line, "1;31"));
+    }
+    out
+}
+
+/// Renders `doctor::diagnose` findings as a numbered list with a hint under
+/// each, followed by a count, or a single line when there are none.
+pub fn render_diagnosis(findings: &[Finding]) -> String {
+    if findings.is_empty() {
+        return "No problems found; the record conforms.\n".to_string();
+    }
+    let mut out = String::new();
+    for (i, f) in findings.iter().enumerate() {
+        out.push_str(&format!("{}. {}\n   hint: {}\n", i + 1, f.problem, f.hint));
+    }
+    out.push_str(&format!("{} problem(s) found.\n", findings.len()));
+    out
+}
+
+/// Renders per-mode counts one per line in severity order (Optimal → Halt),
+/// including modes with no records, then any unknown modes alphabetically and
+/// a total line.
+pub fn render_tally(counts: &BTreeMap<String, usize>) -> String {
+    let unknown = counts.iter().filter(|(m, _)| !MODES.contains(&m.as_str()));
+    let mut out = String::new();
+    for mode in MODES {
+        out.push_str(&format!("{:<8} {}\n", mode, counts.get(mode).unwrap_or(&0)));
+    }
+    for (mode, n) in unknown {
+        out.push_str(&format!("{:<8} {}\n", mode, n));
+    }
+    out.push_str(&format!("{:<8} {}\n", "total", counts.values().sum::<usize>()));
+    out
+}
+
+/// Renders a `partition_table` one interval per line, e.g.
+/// "Caution  L3  [0.4, 0.6)".
+pub fn render_legend(entries: &[LegendEntry]) -> String {
+    entries
+        .iter()
+        .map(|e| {
+            let close = if e.upper_inclusive { ']' } else { ')' };
+            format!("{:<8} {:<3} [{}, {}{}\n", e.mode, e.level, e.lower, e.upper, close)
+        })
+        .collect()
+}
+
+/// Renders `stats_by_mode` one mode per line, Optimal to Halt (including
+/// modes with no records), then unknown mode strings.
+pub fn render_mode_stats(stats: &BTreeMap<String, ModeStats>) -> String {
+    let unknown = stats.keys().filter(|m| !MODES.contains(&m.as_str())).map(String::as_str);
+    let mut out = String::new();
+    for mode in MODES.into_iter().chain(unknown) {
+        match stats.get(mode) {
+            Some(s) => out.push_str(&format!(
+                "{:<8} count={} mean_risk={} time={} s\n",
+                mode, s.count, s.mean_risk, s.duration_secs
+            )),
+            None => out.push_str(&format!("{:<8} count=0 mean_risk=- time=0 s\n", mode)),
+        }
+    }
+    out
+}
+
+fn status(r: &InvariantResult) -> &'static str {
+    if r.passed {
+        "PASS"
+    } else if r.is_warning() {
+        "WARN"
+    } else {
+        "FAIL"
+    }
+}
+
+fn failed_ids(results: &[InvariantResult]) -> Vec<&'static str> {
+    results.iter().filter(|r| r.is_violation()).map(|r| r.id).collect()
+}
+
+fn warning_ids(results: &[InvariantResult]) -> Vec<&'static str> {
+    results.iter().filter(|r| r.is_warning()).map(|r| r.id).collect()
+}
+
+/// Renders single-record results as plain text, one line per invariant,
+/// followed by the overall verdict.
+pub fn render_text(results: &[InvariantResult]) -> String {
+    let mut out = String::new();
+    for r in results {
+        out.push_str(&format!("[{}] {} — {}\n", status(r), r.id, r.detail));
+    }
+    out.push('\n');
+    if failed_ids(results).is_empty() {
+        out.push_str("Result: ALL INVARIANTS SATISFIED — record is conforming.\n");
+    } else {
+        out.push_str("Result: INVARIANT VIOLATION(S) DETECTED — record is malformed.\n");
+    }
+    out
+}
+
+/// Like `render_text`, but omits the invariants that passed; FAIL and WARN
+/// lines and the overall verdict are unchanged.
+pub fn render_text_failures(results: &[InvariantResult]) -> String {
+    let failing: Vec<InvariantResult> = results.iter().filter(|r| !r.passed).cloned().collect();
+    render_text(&failing)
+}
+
+/// Renders batch results as plain text: one verdict line per record, the
+/// stream-level INV-T1 outcome, and the overall verdict.
+///
+/// `t1_violation` is the result of `validate_stream_t1`.
+pub fn render_batch_text(results: &[Vec<InvariantResult>], t1_violation: Option<usize>) -> String {
+    let mut out: String = results.iter().enumerate().map(|(i, rs)| render_batch_record_line(i, rs)).collect();
+    out.push_str(&render_batch_summary(&BatchSummary::from_results(results, t1_violation)));
+    out
+}
+
+/// Renders the verdict line of one batch record, as `render_batch_text` does.
+pub fn render_batch_record_line(index: usize, results: &[InvariantResult]) -> String {
+    let failed = failed_ids(results);
+    let warnings = warning_ids(results);
+    if failed.is_empty() && warnings.is_empty() {
+        format!("[PASS] record #{}\n", index)
+    } else if failed.is_empty() {
+        format!("[WARN] record #{} — {}\n", index, warnings.join(", "))
+    } else {
+        format!("[FAIL] record #{} — {}\n", index, failed.join(", "))
+    }
+}
+
+/// Renders the part of `render_batch_text` after the per-record lines: the
+/// INV-T1 outcome, failures by invariant, the conformance rate with the first
+/// failure, and the overall verdict.
+pub fn render_batch_summary(summary: &BatchSummary) -> String {
+    let mut out = String::new();
+    match summary.t1_violation {
+        None => out.push_str("[PASS] INV-T1 — timestamps non-decreasing across the stream.\n"),
+        Some(i) => out.push_str(&format!("[FAIL] INV-T1 — timestamp decreases at record #{}.\n", i)),
+    }
+
+    let ranked = summary.ranked_failures();
+    if !ranked.is_empty() {
+        out.push_str("\nFailures by invariant:\n");
+        for (id, n) in &ranked {
+            out.push_str(&format!("  {} — {} record(s)\n", id, n));
+        }
+    }
+
+    out.push_str(&format!(
+        "\nConformance: {}/{} record(s) ({:.1}%)",
+        summary.conforming,
+        summary.records,
+        summary.conformance_rate()
+    ));
+    match summary.first_failure {
+        Some(f) => out.push_str(&format!("; first failure at record #{} ({}).\n", f.index, f.id)),
+        None => out.push_str(".\n"),
+    }
+
+    out.push('\n');
+    if summary.is_conforming() {
+        out.push_str(&format!("Result: ALL {} RECORD(S) CONFORMING.\n", summary.records));
+    } else {
+        out.push_str(&format!(
+            "Result: {}/{} record(s) conforming — stream is malformed.\n",
+            summary.conforming, summary.records
+        ));
+    }
+    out
+}
+
+// Escapes text for use inside a markdown table cell.
+fn md_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+fn md_results_table(results: &[InvariantResult]) -> String {
+    let mut out = String::from("| ID | Description | Status | Detail |\n|----|-------------|--------|--------|\n");
+    for r in results {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            r.id,
+            md_cell(invariant_description(r.id).unwrap_or("")),
+            status(r),
+            md_cell(&r.detail),
+        ));
+    }
+    out
+}
+
+/// Renders single-record results as a markdown conformance report with a
+/// results table and a verdict section. Invariant descriptions follow the
+/// D1-A §4 invariant definitions.
+pub fn render_markdown(record: &RuntimeStateRecord, results: &[InvariantResult]) -> String {
+    render_markdown_with(record, results, &ValidationOptions::default(), TimestampStyle::Epoch)
+}
+
+/// Like `render_markdown`, rendering timestamps in the given style and
+/// explaining consistency failures with the options `results` were
+/// validated with.
+pub fn render_markdown_with(
+    record: &RuntimeStateRecord,
+    results: &[InvariantResult],
+    opts: &ValidationOptions,
+    style: TimestampStyle,
+) -> String {
+    let mut out = String::from("# P-MATRIX Conformance Report\n\n");
+    out.push_str(&format!("- **Spec version:** `{}`\n", md_cell(&record.spec_version)));
+    out.push_str(&format!("- **Schema version:** `{}`\n", md_cell(&record.schema_version)));
+    out.push_str(&format!("- **Timestamp:** {}\n", format_timestamp(record.timestamp, style)));
+    out.push_str(&format!("- **Classification:** {}\n", md_cell(&classification_note(record))));
+    if let Some(explanation) = explain_consistency_failure_with(record, opts) {
+        out.push_str(&format!("- **Consistency:** {}\n", md_cell(&explanation)));
+    }
+    out.push('\n');
+
+    out.push_str("## Invariant Results (D1-A §4)\n\n");
+    out.push_str(&md_results_table(results));
+
+    let failed = failed_ids(results);
+    out.push_str("\n## Verdict\n\n");
+    if failed.is_empty() {
+        out.push_str(&format!("**CONFORMING** — all {} invariants satisfied.\n", results.len()));
+    } else {
+        out.push_str(&format!("**MALFORMED** — violated: {}.\n", failed.join(", ")));
+    }
+    out
+}
+
+/// Renders batch results as a markdown report: a stats summary, a table of
+/// non-conforming records, and a verdict section.
+///
+/// `t1_violation` is the result of `validate_stream_t1`.
+pub fn render_batch_markdown(
+    records: &[RuntimeStateRecord],
+    results: &[Vec<InvariantResult>],
+    t1_violation: Option<usize>,
+) -> String {
+    render_batch_markdown_with(records, results, t1_violation, TimestampStyle::Epoch)
+}
+
+/// Like `render_batch_markdown`, rendering timestamps in the given style.
+pub fn render_batch_markdown_with(
+    records: &[RuntimeStateRecord],
+    results: &[Vec<InvariantResult>],
+    t1_violation: Option<usize>,
+    style: TimestampStyle,
+) -> String {
+    let stats = stream_stats(records);
+    let summary = BatchSummary::from_results(results, t1_violation);
+
+    let mut out = String::from("# P-MATRIX Batch Conformance Report\n\n## Summary\n\n");
+    out.push_str("| Metric | Value |\n|--------|-------|\n");
+    out.push_str(&format!("| Records | {} |\n", stats.count));
+    out.push_str(&format!("| Conforming records | {} |\n", summary.conforming));
+    if let (Some(first), Some(last)) = (stats.first_timestamp, stats.last_timestamp) {
+        out.push_str(&format!(
+            "| First – last timestamp | {} – {} |\n",
+            format_timestamp(first, style),
+            format_timestamp(last, style)
+        ));
+    }
+    for (name, summary) in [("risk_score", &stats.risk), ("stability_score", &stats.stability)] {
+        if let Some(s) = summary {
+            out.push_str(&format!("| {} (min / mean / max) | {} / {} / {} |\n", name, s.min, s.mean, s.max));
+        }
+    }
+    let t1 = match t1_violation {
+        None => "PASS".to_string(),
+        Some(i) => format!("FAIL at record #{}", i),
+    };
+    out.push_str(&format!("| INV-T1 (stream) | {} |\n", t1));
+
+    out.push_str("\n## Non-Conforming Records\n\n");
+    let bad: Vec<(usize, Vec<&'static str>)> = results
+        .iter()
+        .enumerate()
+        .map(|(i, rs)| (i, failed_ids(rs)))
+        .filter(|(_, f)| !f.is_empty())
+        .collect();
+    if bad.is_empty() {
+        out.push_str("None.\n");
+    } else {
+        out.push_str("| Index | Timestamp | Failed Invariants |\n|-------|-----------|-------------------|\n");
+        for (i, failed) in &bad {
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                i,
+                format_timestamp(records[*i].timestamp, style),
+                failed.join(", ")
+            ));
+        }
+
+        out.push_str("\n## Failures by Invariant\n\n");
+        out.push_str("| ID | Description | Failing Records |\n|----|-------------|-----------------|\n");
+        for (id, n) in summary.ranked_failures() {
+            out.push_str(&format!(
+                "| {} | {} | {} |\n",
+                id,
+                md_cell(invariant_description(id).unwrap_or("")),
+                n
+            ));
+        }
+    }
+
+    out.push_str("\n## Verdict\n\n");
+    if bad.is_empty() && t1_violation.is_none() {
+        out.push_str(&format!("**CONFORMING** — all {} record(s) satisfy every invariant.\n", records.len()));
+    } else {
+        out.push_str(&format!(
+            "**MALFORMED** — {} of {} record(s) non-conforming{}.\n",
+            bad.len(),
+            records.len(),
+            if t1_violation.is_some() { "; INV-T1 violated" } else { "" },
+        ));
+    }
+    out
+}
@@ -0,0 +1,74 @@
+// sample.rs — Representative Record Sampling
+//
+// Draws a uniform random sample from a stream of any length while holding
+// only the sample in memory. A sample is not a valid stream: its records are
+// not in input order, so INV-T1 does not apply to it.
+
+use crate::schema::RuntimeStateRecord;
+
+/// A source of uniformly distributed random 64-bit values.
+pub trait Rng {
+    fn next_u64(&mut self) -> u64;
+
+    /// Returns a uniform value in `0..n`, without modulo bias. `n` must be
+    /// non-zero.
+    fn below(&mut self, n: u64) -> u64 {
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let x = self.next_u64();
+            if x < zone {
+                return x % n;
+            }
+        }
+    }
+}
+
+/// The SplitMix64 generator: small, fast and fully determined by its seed,
+/// so the same seed always draws the same sample. Not cryptographically
+/// secure.
+#[derive(Debug, Clone)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        SplitMix64 { state: seed }
+    }
+}
+
+impl Rng for SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Draws a uniform sample of up to `k` records with reservoir sampling
+/// (Algorithm R). Every record is equally likely to be chosen, and memory use
+/// is bounded by `k`. Streams shorter than `k` are returned whole, in order;
+/// otherwise the sample order is arbitrary.
+pub fn reservoir_sample<I: Iterator<Item = RuntimeStateRecord>>(
+    iter: I,
+    k: usize,
+    rng: &mut impl Rng,
+) -> Vec<RuntimeStateRecord> {
+    let mut reservoir = Vec::with_capacity(k);
+    if k == 0 {
+        return reservoir;
+    }
+    for (i, record) in iter.enumerate() {
+        if i < k {
+            reservoir.push(record);
+        } else {
+            let j = rng.below(i as u64 + 1) as usize;
+            if j < k {
+                reservoir[j] = record;
+            }
+        }
+    }
+    reservoir
+}
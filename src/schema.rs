@@ -3,12 +3,26 @@
 // This module defines the canonical runtime state record structure.
 // Field names, types, and constraints follow D1-A_Runtime_State_Schema_v1_0_frozen.md exactly.
 
+use serde::de::value::MapAccessDeserializer;
+use serde::de::{Deserializer, MapAccess, Visitor};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+use crate::error::PmatrixError;
 
 /// The four evaluation functions that characterize an agent's runtime posture.
 /// Each produces a normalized scalar in [0.0, 1.0].
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-#[serde(deny_unknown_fields)]
+///
+/// For ingestion from producers that spell `meta_control` differently, the
+/// aliases `meta_ctrl`, `metaControl` and `meta-control` are accepted on input.
+/// Output always uses the canonical name, and any other key is still rejected.
+/// Only the named object form deserializes; for the compact array form, see
+/// `input::expand_functions_array`.
+///
+/// Equality and hashing compare values by `canonical_bits`: `-0.0` equals
+/// `0.0` and every NaN equals every other NaN, so `Eq` and `Hash` agree.
+#[derive(Debug, Clone, Serialize)]
 pub struct Functions {
     pub baseline: f64,
     pub norm: f64,
@@ -16,14 +30,137 @@ pub struct Functions {
     pub meta_control: f64,
 }
 
+// The object form of `Functions`. A derived `Deserialize` on `Functions`
+// itself would also accept a positional four-element array.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NamedFunctions {
+    baseline: f64,
+    norm: f64,
+    stability: f64,
+    // Must list exactly `META_CONTROL_ALIASES`; serde attributes cannot
+    // refer to the constant.
+    #[serde(alias = "meta_ctrl", alias = "metaControl", alias = "meta-control")]
+    meta_control: f64,
+}
+
+/// The input aliases of `functions.meta_control`, as accepted by the codec.
+/// `parse_lenient` and `doctor` use this list rather than their own.
+pub const META_CONTROL_ALIASES: [&str; 3] = ["meta_ctrl", "metaControl", "meta-control"];
+
+impl<'de> Deserialize<'de> for Functions {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ObjectOnly;
+
+        impl<'de> Visitor<'de> for ObjectOnly {
+            type Value = Functions;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a functions object with baseline, norm, stability and meta_control")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Functions, A::Error> {
+                let NamedFunctions { baseline, norm, stability, meta_control } =
+                    NamedFunctions::deserialize(MapAccessDeserializer::new(map))?;
+                Ok(Functions { baseline, norm, stability, meta_control })
+            }
+        }
+
+        deserializer.deserialize_map(ObjectOnly)
+    }
+}
+
+impl Functions {
+    /// Arithmetic mean of the four values.
+    pub fn mean(&self) -> f64 {
+        (self.baseline + self.norm + self.stability + self.meta_control) / 4.0
+    }
+
+    /// Smallest of the four values. NaN values are ignored unless all are NaN.
+    pub fn min(&self) -> f64 {
+        self.values().into_iter().fold(f64::NAN, f64::min)
+    }
+
+    /// Largest of the four values. NaN values are ignored unless all are NaN.
+    pub fn max(&self) -> f64 {
+        self.values().into_iter().fold(f64::NAN, f64::max)
+    }
+
+    /// `max() - min()`: how far the four values disagree. A wide range can
+    /// indicate a faulty input.
+    pub fn range(&self) -> f64 {
+        self.max() - self.min()
+    }
+
+    /// The four values in field order: baseline, norm, stability,
+    /// meta_control.
+    pub fn values(&self) -> [f64; 4] {
+        [self.baseline, self.norm, self.stability, self.meta_control]
+    }
+}
+
+impl PartialEq for Functions {
+    fn eq(&self, other: &Self) -> bool {
+        self.values().map(canonical_bits) == other.values().map(canonical_bits)
+    }
+}
+
+impl Eq for Functions {}
+
+impl Hash for Functions {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.values().map(canonical_bits).hash(state);
+    }
+}
+
+/// Bit pattern of `v` used for record equality and hashing: `-0.0` maps to
+/// the bits of `0.0` and every NaN to the bits of `f64::NAN`, other values
+/// to their own bits.
+pub fn canonical_bits(v: f64) -> u64 {
+    if v.is_nan() {
+        f64::NAN.to_bits()
+    } else if v == 0.0 {
+        0
+    } else {
+        v.to_bits()
+    }
+}
+
+/// Builds functions from `[baseline, norm, stability, meta_control]`.
+///
+/// The order matters: a transposed array still yields in-range values, so
+/// the resulting record is valid but wrong.
+impl From<[f64; 4]> for Functions {
+    fn from([baseline, norm, stability, meta_control]: [f64; 4]) -> Self {
+        Functions { baseline, norm, stability, meta_control }
+    }
+}
+
+/// Returns `[baseline, norm, stability, meta_control]`, the order accepted by
+/// `From<[f64; 4]>`.
+impl From<Functions> for [f64; 4] {
+    fn from(f: Functions) -> Self {
+        f.values()
+    }
+}
+
 /// A single P-MATRIX runtime state record.
 /// Represents the operational posture of an autonomous agent at one instant in time.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+///
+/// Records are `Eq` and `Hash` so that they can be deduplicated in a
+/// `HashSet`. Every field takes part, timestamp included; floats compare by
+/// `canonical_bits`, so records differing only in `-0.0` versus `0.0` or in
+/// NaN payloads are equal.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
 pub struct RuntimeStateRecord {
     pub spec_version: String,
     pub schema_version: String,
     pub timestamp: u64,
+    /// Optional per-emitter record counter (schema 1.1.0 and later).
+    /// Omitted from output when absent, so 1.0.0 records are unchanged.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sequence_number: Option<u64>,
     pub functions: Functions,
     pub stability_score: f64,
     pub risk_score: f64,
@@ -31,6 +168,73 @@ pub struct RuntimeStateRecord {
     pub risk_level: String,
 }
 
+impl RuntimeStateRecord {
+    // The fields in schema order, with floats as canonical bits.
+    fn canonical_key(&self) -> (&str, &str, u64, Option<u64>, &Functions, u64, u64, &str, &str) {
+        (
+            &self.spec_version,
+            &self.schema_version,
+            self.timestamp,
+            self.sequence_number,
+            &self.functions,
+            canonical_bits(self.stability_score),
+            canonical_bits(self.risk_score),
+            &self.mode,
+            &self.risk_level,
+        )
+    }
+}
+
+impl PartialEq for RuntimeStateRecord {
+    fn eq(&self, other: &Self) -> bool {
+        self.canonical_key() == other.canonical_key()
+    }
+}
+
+impl Eq for RuntimeStateRecord {}
+
+impl Hash for RuntimeStateRecord {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.canonical_key().hash(state);
+    }
+}
+
+/// Fluent mutators returning modified copies of a record.
+///
+/// `with_functions` re-derives stability_score, risk_score, mode and
+/// risk_level through the demonstration pipeline. All other `with_*`
+/// methods are raw setters: they change only the named field and may
+/// therefore produce a non-conforming record.
+impl RuntimeStateRecord {
+    /// Raw setter: replaces the timestamp, leaving every other field intact.
+    pub fn with_timestamp(mut self, timestamp: u64) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    /// Raw setter: sets sequence_number. Only schema 1.1.0 and later records
+    /// may carry it (INV-S2).
+    pub fn with_sequence_number(mut self, sequence_number: u64) -> Self {
+        self.sequence_number = Some(sequence_number);
+        self
+    }
+
+    /// Re-deriving setter: replaces the function values and recomputes the
+    /// dependent fields with the demonstration aggregation logic.
+    ///
+    /// Fails under the same conditions as `emit_demo_record`.
+    pub fn with_functions(self, f: Functions) -> Result<Self, PmatrixError> {
+        crate::normalize_record(&self.with_raw_functions(f))
+    }
+
+    /// Raw setter: replaces the function values without re-deriving scores,
+    /// mode or risk_level.
+    pub fn with_raw_functions(mut self, f: Functions) -> Self {
+        self.functions = f;
+        self
+    }
+}
+
 /// The five discrete operating modes.
 pub const MODES: [&str; 5] = ["Optimal", "Normal", "Caution", "Alert", "Halt"];
 
@@ -42,3 +246,106 @@ pub const SPEC_VERSION: &str = "pmatrix-3.5";
 
 /// Current schema version (D1-A §3.1).
 pub const SCHEMA_VERSION: &str = "1.0.0";
+
+/// Returns the example record of D1-A §5: functions 0.25/0.70/0.30/0.20,
+/// stability_score 0.58, risk_score 0.42, Caution/L3, timestamp 1707500000.
+///
+/// A conforming reference record for conformance suites and ports. Its scores
+/// come from the specification, not from the demo aggregation, so it fails
+/// the opt-in INV-R6 check.
+pub fn spec_example_record() -> RuntimeStateRecord {
+    RuntimeStateRecord {
+        spec_version: SPEC_VERSION.to_string(),
+        schema_version: SCHEMA_VERSION.to_string(),
+        timestamp: 1707500000,
+        sequence_number: None,
+        functions: Functions {
+            baseline: 0.25,
+            norm: 0.70,
+            stability: 0.30,
+            meta_control: 0.20,
+        },
+        stability_score: 0.58,
+        risk_score: 0.42,
+        mode: "Caution".to_string(),
+        risk_level: "L3".to_string(),
+    }
+}
+
+/// First schema version that defines the optional `sequence_number` field.
+pub const SEQUENCE_SCHEMA_VERSION: &str = "1.1.0";
+
+/// Returns true if `schema_version` is at least `SEQUENCE_SCHEMA_VERSION`.
+/// Versions that are not valid semver never qualify.
+pub fn supports_sequence_number(schema_version: &str) -> bool {
+    let mut parts = schema_version.split('.').map(|p| p.parse::<u32>());
+    match (parts.next(), parts.next(), parts.next(), parts.next()) {
+        (Some(Ok(major)), Some(Ok(minor)), Some(Ok(_)), None) => (major, minor) >= (1, 1),
+        _ => false,
+    }
+}
+
+// Boundary-biased generation for fuzzing. Generated values are deliberately
+// NOT guaranteed to be valid: out-of-range, NaN and infinite values and
+// unknown strings appear regularly so that negative paths are exercised.
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impls {
+    use super::{Functions, RuntimeStateRecord, MODES, RISK_LEVELS, SCHEMA_VERSION, SPEC_VERSION};
+    use arbitrary::{Arbitrary, Result, Unstructured};
+
+    const EDGES: [f64; 6] = [0.0, 0.2, 0.4, 0.6, 0.8, 1.0];
+
+    // A score in [0, 1] most of the time, often exactly on or one ULP off a
+    // partition boundary, and occasionally outside the range or not finite.
+    fn score(u: &mut Unstructured<'_>) -> Result<f64> {
+        Ok(match u.int_in_range(0u8..=15)? {
+            0..=7 => u32::arbitrary(u)? as f64 / u32::MAX as f64,
+            8..=9 => *u.choose(&EDGES)?,
+            10 => u.choose(&EDGES)?.next_down(),
+            11 => u.choose(&EDGES)?.next_up(),
+            12 => -f64::MIN_POSITIVE / 2.0,
+            13 => 1.0 + f64::EPSILON,
+            14 => f64::NAN,
+            _ => f64::from_bits(u64::arbitrary(u)?),
+        })
+    }
+
+    // One of the defined values most of the time, otherwise any string.
+    fn label(u: &mut Unstructured<'_>, defined: &[&str]) -> Result<String> {
+        if u.ratio(7u8, 8u8)? {
+            Ok(u.choose(defined)?.to_string())
+        } else {
+            String::arbitrary(u)
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for Functions {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            Ok(Functions {
+                baseline: score(u)?,
+                norm: score(u)?,
+                stability: score(u)?,
+                meta_control: score(u)?,
+            })
+        }
+    }
+
+    impl<'a> Arbitrary<'a> for RuntimeStateRecord {
+        fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+            let functions = Functions::arbitrary(u)?;
+            let stability_score = score(u)?;
+            let risk_score = if u.ratio(3u8, 4u8)? { 1.0 - stability_score } else { score(u)? };
+            Ok(RuntimeStateRecord {
+                spec_version: label(u, &[SPEC_VERSION])?,
+                schema_version: label(u, &[SCHEMA_VERSION, super::SEQUENCE_SCHEMA_VERSION])?,
+                timestamp: if u.ratio(1u8, 16u8)? { 0 } else { u64::arbitrary(u)? },
+                sequence_number: Option::<u64>::arbitrary(u)?,
+                functions,
+                stability_score,
+                risk_score,
+                mode: label(u, &MODES)?,
+                risk_level: label(u, &RISK_LEVELS)?,
+            })
+        }
+    }
+}
@@ -0,0 +1,30 @@
+// schema_check.rs — JSON Schema Cross-Check
+//
+// Evaluates a record against an externally maintained JSON Schema so that
+// its verdict can be compared with the native invariants. Evaluation is
+// delegated to the `jsonschema` crate, an implementation independent of this
+// crate's own checks. Only local references (`$ref` into `$defs` and the
+// like) resolve; remote schemas are not fetched.
+
+use crate::error::PmatrixError;
+
+use serde_json::Value;
+
+/// Evaluates `instance` against `schema` and returns one message per
+/// violated constraint, prefixed with the JSON Pointer of the failing value.
+/// An empty list means the instance is valid.
+///
+/// The draft is taken from `$schema`, defaulting to 2020-12. Fails with
+/// `PmatrixError::Config` on a malformed schema or an unresolvable `$ref`.
+pub fn schema_errors(schema: &Value, instance: &Value) -> Result<Vec<String>, PmatrixError> {
+    let validator =
+        jsonschema::validator_for(schema).map_err(|e| PmatrixError::Config(format!("JSON Schema: {}", e)))?;
+    Ok(validator
+        .iter_errors(instance)
+        .map(|e| format!("{}: {}", pointer(&e.instance_path().to_string()), e))
+        .collect())
+}
+
+fn pointer(path: &str) -> &str {
+    if path.is_empty() { "/" } else { path }
+}
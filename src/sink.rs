@@ -0,0 +1,189 @@
+// sink.rs — Pluggable Destinations for Validation Results
+//
+// The batch validation loop pushes each record's results into a
+// `ResultSink` and closes it with a `BatchSummary`, so that embedders can
+// deliver results to stdout, a file, a channel or metrics without touching
+// the loop itself.
+
+use crate::invariants::{validate_all_with, validate_stream_t1, ErrorCode, InvariantResult, ValidationOptions};
+use crate::report::{render_batch_record_line, render_batch_summary};
+use crate::schema::RuntimeStateRecord;
+
+pub use crate::summary::{BatchSummary, FirstFailure};
+
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// Receives validation results one record at a time.
+pub trait ResultSink {
+    /// Called once per validated record, in input order.
+    fn record(&mut self, index: usize, record: &RuntimeStateRecord, results: &[InvariantResult]);
+    /// Called once after the last record.
+    fn finish(&mut self, summary: &BatchSummary);
+}
+
+impl<S: ResultSink + ?Sized> ResultSink for &mut S {
+    fn record(&mut self, index: usize, record: &RuntimeStateRecord, results: &[InvariantResult]) {
+        (**self).record(index, record, results);
+    }
+
+    fn finish(&mut self, summary: &BatchSummary) {
+        (**self).finish(summary);
+    }
+}
+
+/// Validates each record with `opts`, pushes the results into `sink`, checks
+/// INV-T1 over the batch and finishes the sink with the summary, which is
+/// also returned.
+pub fn validate_into_sink(
+    records: &[RuntimeStateRecord],
+    opts: &ValidationOptions,
+    sink: &mut dyn ResultSink,
+) -> BatchSummary {
+    let mut results = Vec::with_capacity(records.len());
+    for (i, record) in records.iter().enumerate() {
+        let result = validate_all_with(record, opts);
+        sink.record(i, record, &result);
+        results.push(result);
+    }
+    let summary = BatchSummary::from_results(&results, validate_stream_t1(records));
+    sink.finish(&summary);
+    summary
+}
+
+/// Prints the plain-text batch report of `render_batch_text` to stdout,
+/// one line per record as it arrives.
+#[derive(Debug, Default)]
+pub struct StdoutSink;
+
+impl ResultSink for StdoutSink {
+    fn record(&mut self, index: usize, _record: &RuntimeStateRecord, results: &[InvariantResult]) {
+        print!("{}", render_batch_record_line(index, results));
+    }
+
+    fn finish(&mut self, summary: &BatchSummary) {
+        print!("{}", render_batch_summary(summary));
+    }
+}
+
+/// Writes NDJSON: one object per record, then `{"summary": ...}`.
+///
+/// Write errors do not interrupt validation; the first one is kept and
+/// returned by `into_inner`.
+#[derive(Debug)]
+pub struct JsonSink<W: Write> {
+    writer: W,
+    error: Option<io::Error>,
+}
+
+#[derive(Serialize)]
+struct JsonRecordLine<'a> {
+    index: usize,
+    timestamp: u64,
+    conforming: bool,
+    failed: Vec<&'a str>,
+    warnings: Vec<&'a str>,
+    /// Every failed check, violations and warnings, in validation order.
+    failures: Vec<JsonFailure<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonFailure<'a> {
+    id: &'a str,
+    code: Option<ErrorCode>,
+    detail: &'a str,
+}
+
+impl<W: Write> JsonSink<W> {
+    pub fn new(writer: W) -> Self {
+        JsonSink { writer, error: None }
+    }
+
+    /// Returns the writer, or the first write error.
+    pub fn into_inner(self) -> Result<W, io::Error> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(self.writer),
+        }
+    }
+
+    fn write_line<T: Serialize>(&mut self, value: &T) {
+        if self.error.is_some() {
+            return;
+        }
+        let result = serde_json::to_writer(&mut self.writer, value)
+            .map_err(io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"));
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+}
+
+impl<W: Write> ResultSink for JsonSink<W> {
+    fn record(&mut self, index: usize, record: &RuntimeStateRecord, results: &[InvariantResult]) {
+        let failed: Vec<&str> = results.iter().filter(|r| r.is_violation()).map(|r| r.id).collect();
+        let line = JsonRecordLine {
+            index,
+            timestamp: record.timestamp,
+            conforming: failed.is_empty(),
+            failed,
+            warnings: results.iter().filter(|r| r.is_warning()).map(|r| r.id).collect(),
+            failures: results
+                .iter()
+                .filter(|r| !r.passed)
+                .map(|r| JsonFailure { id: r.id, code: r.code, detail: &r.detail })
+                .collect(),
+        };
+        self.write_line(&line);
+    }
+
+    fn finish(&mut self, summary: &BatchSummary) {
+        self.write_line(&serde_json::json!({ "summary": summary }));
+        if let Err(e) = self.writer.flush() {
+            self.error.get_or_insert(e);
+        }
+    }
+}
+
+/// Forwards to the wrapped sink only the records that fail or warn on at
+/// least one invariant; the summary still covers every record.
+#[derive(Debug, Default)]
+pub struct FailuresOnly<S>(pub S);
+
+impl<S: ResultSink> ResultSink for FailuresOnly<S> {
+    fn record(&mut self, index: usize, record: &RuntimeStateRecord, results: &[InvariantResult]) {
+        if results.iter().any(|r| !r.passed) {
+            self.0.record(index, record, results);
+        }
+    }
+
+    fn finish(&mut self, summary: &BatchSummary) {
+        self.0.finish(summary);
+    }
+}
+
+/// Counts records without producing output; the summary is kept once the
+/// batch finishes.
+#[derive(Debug, Clone, Default)]
+pub struct CountingSink {
+    /// Records seen so far.
+    pub records: usize,
+    /// Records seen so far with no violations.
+    pub conforming: usize,
+    /// The summary passed to `finish`, if it has been called.
+    pub summary: Option<BatchSummary>,
+}
+
+impl ResultSink for CountingSink {
+    fn record(&mut self, _index: usize, _record: &RuntimeStateRecord, results: &[InvariantResult]) {
+        self.records += 1;
+        if !results.iter().any(|r| r.is_violation()) {
+            self.conforming += 1;
+        }
+    }
+
+    fn finish(&mut self, summary: &BatchSummary) {
+        self.summary = Some(summary.clone());
+    }
+}
@@ -0,0 +1,647 @@
+// stats.rs — Stream Statistics
+//
+// Aggregate statistics over a sequence of runtime state records.
+// These are descriptive only and never affect conformance.
+
+use crate::error::PmatrixError;
+use crate::mode::demo_partition_map;
+use crate::schema::{Functions, RuntimeStateRecord, MODES, RISK_LEVELS};
+
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+
+/// Default percentiles reported for score distributions.
+pub const DEFAULT_PERCENTILES: [f64; 3] = [50.0, 90.0, 99.0];
+
+/// Minimum, arithmetic mean and maximum of a score series.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreSummary {
+    pub min: f64,
+    pub mean: f64,
+    pub max: f64,
+}
+
+/// Aggregate statistics over a stream of records.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StreamStats {
+    pub count: usize,
+    pub first_timestamp: Option<u64>,
+    pub last_timestamp: Option<u64>,
+    /// Smallest timestamp seen; differs from `first_timestamp` only if the
+    /// stream is out of order.
+    pub min_timestamp: Option<u64>,
+    /// Largest timestamp seen.
+    pub max_timestamp: Option<u64>,
+    /// None for an empty stream.
+    pub risk: Option<ScoreSummary>,
+    /// None for an empty stream.
+    pub stability: Option<ScoreSummary>,
+    /// Records per mode string, as returned by `mode_counts`.
+    pub mode_counts: BTreeMap<String, usize>,
+    /// Records whose timestamp is earlier than their predecessor's (INV-T1).
+    pub t1_violations: usize,
+}
+
+impl StreamStats {
+    /// Yields every mode with its record count: the five defined modes in
+    /// severity order (Optimal → Halt, including those with no records),
+    /// then any unknown mode strings alphabetically.
+    pub fn mode_breakdown(&self) -> impl Iterator<Item = (&str, usize)> + '_ {
+        let known = MODES.iter().map(|&m| (m, self.mode_counts.get(m).copied().unwrap_or(0)));
+        let unknown = self
+            .mode_counts
+            .iter()
+            .filter(|(m, _)| !MODES.contains(&m.as_str()))
+            .map(|(m, &n)| (m.as_str(), n));
+        known.chain(unknown)
+    }
+
+    /// Returns the most frequent mode, or None for an empty stream. Ties go
+    /// to the more severe mode, and any defined mode wins a tie with an
+    /// unknown one.
+    pub fn dominant_mode(&self) -> Option<&str> {
+        let mut best: Option<(&str, usize)> = None;
+        for (mode, n) in self.mode_breakdown() {
+            let known = MODES.contains(&mode);
+            if n > 0 && best.is_none_or(|(_, b)| n > b || (n == b && known)) {
+                best = Some((mode, n));
+            }
+        }
+        best.map(|(mode, _)| mode)
+    }
+}
+
+/// How the `_with` sorting and statistics functions treat NaN scores, which
+/// only malformed records contain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NanPolicy {
+    /// Fail with `PmatrixError::Parse` naming the first NaN, as for any
+    /// other malformed input.
+    #[default]
+    Error,
+    /// Order NaN below every number: it is the minimum and sorts first.
+    Lowest,
+    /// Order NaN above every number: it is the maximum and sorts last.
+    Highest,
+    /// Leave NaN values (or the records holding them) out entirely.
+    Skip,
+}
+
+impl NanPolicy {
+    // Total order on f64 with NaN placed as the policy says. Skip and Error
+    // never see NaN here.
+    fn cmp(self, a: f64, b: f64) -> Ordering {
+        match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) if self == NanPolicy::Lowest => Ordering::Less,
+            (true, false) => Ordering::Greater,
+            (false, true) if self == NanPolicy::Lowest => Ordering::Greater,
+            (false, true) => Ordering::Less,
+            (false, false) => a.total_cmp(&b),
+        }
+    }
+
+    // Checks `values` against the policy and returns them sorted, without
+    // NaN under Skip.
+    fn sorted(self, values: &[f64], name: &str) -> Result<Vec<f64>, PmatrixError> {
+        if self == NanPolicy::Error {
+            if let Some(i) = values.iter().position(|v| v.is_nan()) {
+                return Err(nan_error(name, i));
+            }
+        }
+        let mut sorted: Vec<f64> = values.iter().copied().filter(|v| self != NanPolicy::Skip || !v.is_nan()).collect();
+        sorted.sort_by(|&a, &b| self.cmp(a, b));
+        Ok(sorted)
+    }
+
+    /// Under `Error`, fails if record #`index` has a NaN risk_score or
+    /// stability_score; the other policies accept every record. Lets a
+    /// lazily read stream be checked one record at a time.
+    pub fn check(self, index: usize, record: &RuntimeStateRecord) -> Result<(), PmatrixError> {
+        if self != NanPolicy::Error {
+            return Ok(());
+        }
+        for (name, value) in [("risk_score", record.risk_score), ("stability_score", record.stability_score)] {
+            if value.is_nan() {
+                return Err(nan_error(name, index));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn nan_error(name: &str, index: usize) -> PmatrixError {
+    PmatrixError::Parse(serde::de::Error::custom(format!("{} #{} is NaN", name, index)))
+}
+
+// Running min, sum and max of one score series.
+#[derive(Debug, Clone, Copy)]
+struct RunningSummary {
+    min: f64,
+    sum: f64,
+    max: f64,
+}
+
+impl RunningSummary {
+    fn observe(summary: &mut Option<Self>, v: f64) {
+        match summary {
+            Some(s) => {
+                s.min = s.min.min(v);
+                s.sum += v;
+                s.max = s.max.max(v);
+            }
+            None => *summary = Some(RunningSummary { min: v, sum: v, max: v }),
+        }
+    }
+
+    fn finish(self, count: usize) -> ScoreSummary {
+        ScoreSummary { min: self.min, mean: self.sum / count as f64, max: self.max }
+    }
+}
+
+/// Computes `StreamStats` one record at a time, holding no records in memory.
+///
+/// Feeding a stream through `observe` and calling `finish` gives the same
+/// result as `stream_stats` over the collected records.
+#[derive(Debug, Clone, Default)]
+pub struct StatsAccumulator {
+    count: usize,
+    first_timestamp: Option<u64>,
+    last_timestamp: Option<u64>,
+    min_timestamp: Option<u64>,
+    max_timestamp: Option<u64>,
+    risk: Option<RunningSummary>,
+    stability: Option<RunningSummary>,
+    mode_counts: BTreeMap<String, usize>,
+    t1_violations: usize,
+}
+
+impl StatsAccumulator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds one record to the running statistics.
+    pub fn observe(&mut self, record: &RuntimeStateRecord) {
+        let ts = record.timestamp;
+        self.count += 1;
+        if self.last_timestamp.is_some_and(|prev| ts < prev) {
+            self.t1_violations += 1;
+        }
+        self.first_timestamp.get_or_insert(ts);
+        self.last_timestamp = Some(ts);
+        self.min_timestamp = Some(self.min_timestamp.map_or(ts, |m| m.min(ts)));
+        self.max_timestamp = Some(self.max_timestamp.map_or(ts, |m| m.max(ts)));
+        RunningSummary::observe(&mut self.risk, record.risk_score);
+        RunningSummary::observe(&mut self.stability, record.stability_score);
+        *self.mode_counts.entry(record.mode.clone()).or_insert(0) += 1;
+    }
+
+    /// Returns the statistics of every record observed so far.
+    pub fn finish(self) -> StreamStats {
+        StreamStats {
+            count: self.count,
+            first_timestamp: self.first_timestamp,
+            last_timestamp: self.last_timestamp,
+            min_timestamp: self.min_timestamp,
+            max_timestamp: self.max_timestamp,
+            risk: self.risk.map(|s| s.finish(self.count)),
+            stability: self.stability.map(|s| s.finish(self.count)),
+            mode_counts: self.mode_counts,
+            t1_violations: self.t1_violations,
+        }
+    }
+}
+
+/// Computes aggregate statistics over a stream of records.
+pub fn stream_stats(records: &[RuntimeStateRecord]) -> StreamStats {
+    let mut acc = StatsAccumulator::new();
+    for r in records {
+        acc.observe(r);
+    }
+    acc.finish()
+}
+
+/// Like `stream_stats`, reading records lazily (e.g. from
+/// `input::read_records`) so that memory use does not grow with the stream.
+///
+/// Stops at the first error and returns it.
+pub fn stream_stats_iter<I>(records: I) -> Result<StreamStats, PmatrixError>
+where
+    I: IntoIterator<Item = Result<RuntimeStateRecord, PmatrixError>>,
+{
+    let mut acc = StatsAccumulator::new();
+    for r in records {
+        acc.observe(&r?);
+    }
+    Ok(acc.finish())
+}
+
+/// Counts records per mode string. Unknown mode strings are counted under
+/// their own key; modes with no records are absent.
+pub fn mode_counts(records: &[RuntimeStateRecord]) -> BTreeMap<String, usize> {
+    let mut counts = BTreeMap::new();
+    for r in records {
+        *counts.entry(r.mode.clone()).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// Signed difference `to - from` between two timestamps, negative if `to`
+/// is earlier. Never overflows: differences beyond the i64 range saturate.
+pub fn timestamp_delta_secs(from: u64, to: u64) -> i64 {
+    let delta = to as i128 - from as i128;
+    delta.clamp(i64::MIN as i128, i64::MAX as i128) as i64
+}
+
+/// Age of a record at `now` (Unix seconds): `now - timestamp`, negative if
+/// the record is timestamped in the future. Saturates at the i64 range.
+pub fn record_age_secs(record: &RuntimeStateRecord, now: u64) -> i64 {
+    timestamp_delta_secs(record.timestamp, now)
+}
+
+/// True if the record is more than `max_age_secs` old at `now`. A record
+/// from the future is never stale.
+pub fn is_stale(record: &RuntimeStateRecord, now: u64, max_age_secs: u64) -> bool {
+    now.checked_sub(record.timestamp).is_some_and(|age| age > max_age_secs)
+}
+
+/// Statistics of the records in one mode, as returned by `stats_by_mode`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModeStats {
+    pub count: usize,
+    /// Mean risk_score of the records in the mode.
+    pub mean_risk: f64,
+    /// Time spent in the mode: the sum of the timestamp deltas from each of
+    /// its records to the next record, saturating at `u64::MAX`.
+    pub duration_secs: u64,
+}
+
+/// Groups records by mode string and summarizes each group.
+///
+/// A record is taken to last until the next record's timestamp, so the final
+/// record of the stream contributes no time, and neither does a record
+/// followed by an earlier timestamp. Modes with no records are absent.
+pub fn stats_by_mode(records: &[RuntimeStateRecord]) -> BTreeMap<String, ModeStats> {
+    let mut sums: BTreeMap<String, (usize, f64, u64)> = BTreeMap::new();
+    for (i, r) in records.iter().enumerate() {
+        let held = records.get(i + 1).map_or(0, |next| next.timestamp.saturating_sub(r.timestamp));
+        let entry = sums.entry(r.mode.clone()).or_default();
+        *entry = (entry.0 + 1, entry.1 + r.risk_score, entry.2.saturating_add(held));
+    }
+    sums.into_iter()
+        .map(|(mode, (count, risk, duration_secs))| {
+            (mode, ModeStats { count, mean_risk: risk / count as f64, duration_secs })
+        })
+        .collect()
+}
+
+// Like `summarize`, with NaN handled by `policy`. The mean is taken over the
+// numeric values only; Lowest and Highest make NaN the min or max.
+fn summarize_with(values: &[f64], policy: NanPolicy, name: &str) -> Result<Option<ScoreSummary>, PmatrixError> {
+    let sorted = policy.sorted(values, name)?;
+    let numeric: Vec<f64> = sorted.iter().copied().filter(|v| !v.is_nan()).collect();
+    let (Some(&min), Some(&max)) = (sorted.first(), sorted.last()) else {
+        return Ok(None);
+    };
+    let mean = numeric.iter().sum::<f64>() / numeric.len() as f64;
+    Ok(Some(ScoreSummary { min, mean, max }))
+}
+
+/// Like `stream_stats`, with NaN scores handled by `policy`.
+///
+/// Under `Skip` a NaN score is left out of its summary; under `Lowest` and
+/// `Highest` it becomes the min or max, while the mean is taken over the
+/// numeric scores only (and is NaN if there are none).
+pub fn stream_stats_with(records: &[RuntimeStateRecord], policy: NanPolicy) -> Result<StreamStats, PmatrixError> {
+    let risks: Vec<f64> = records.iter().map(|r| r.risk_score).collect();
+    let stabilities: Vec<f64> = records.iter().map(|r| r.stability_score).collect();
+    Ok(StreamStats {
+        risk: summarize_with(&risks, policy, "risk_score")?,
+        stability: summarize_with(&stabilities, policy, "stability_score")?,
+        ..stream_stats(records)
+    })
+}
+
+/// Sorts records by ascending risk_score. Equal scores are ordered by
+/// risk_level (L1 first, unknown levels last), then by timestamp, then by
+/// input order, so the result is the same on every run. NaN scores are
+/// handled by `policy`; `Skip` drops those records.
+pub fn sort_by_risk(
+    records: Vec<RuntimeStateRecord>,
+    policy: NanPolicy,
+) -> Result<Vec<RuntimeStateRecord>, PmatrixError> {
+    if policy == NanPolicy::Error {
+        if let Some(i) = records.iter().position(|r| r.risk_score.is_nan()) {
+            return Err(nan_error("risk_score", i));
+        }
+    }
+    let mut sorted: Vec<_> = records
+        .into_iter()
+        .filter(|r| policy != NanPolicy::Skip || !r.risk_score.is_nan())
+        .collect();
+    // sort_by is stable, so records equal on every key keep input order.
+    sorted.sort_by(|a, b| {
+        policy
+            .cmp(a.risk_score, b.risk_score)
+            .then_with(|| level_ordinal(&a.risk_level).cmp(&level_ordinal(&b.risk_level)))
+            .then(a.timestamp.cmp(&b.timestamp))
+    });
+    Ok(sorted)
+}
+
+// Position of a risk level in RISK_LEVELS; unknown levels sort after L5.
+fn level_ordinal(level: &str) -> usize {
+    RISK_LEVELS.iter().position(|l| *l == level).unwrap_or(RISK_LEVELS.len())
+}
+
+/// Computes the requested percentiles of a series of values.
+///
+/// Uses linear interpolation between closest ranks: for `n` sorted values the
+/// p-th percentile sits at rank `p / 100 * (n - 1)`, interpolating between the
+/// two neighbouring values (the default method of NumPy and R type 7).
+/// Percentiles are clamped into [0, 100]. Returns `(p, value)` pairs in the
+/// order requested, or an empty Vec for empty input.
+pub fn percentiles(values: &[f64], ps: &[f64]) -> Vec<(f64, f64)> {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    interpolate(&sorted, ps)
+}
+
+/// Like `percentiles`, with NaN values handled by `policy`. Under `Lowest`
+/// and `Highest` a percentile that falls on or next to a NaN is NaN.
+pub fn percentiles_with(values: &[f64], ps: &[f64], policy: NanPolicy) -> Result<Vec<(f64, f64)>, PmatrixError> {
+    Ok(interpolate(&policy.sorted(values, "value")?, ps))
+}
+
+fn interpolate(sorted: &[f64], ps: &[f64]) -> Vec<(f64, f64)> {
+    if sorted.is_empty() {
+        return Vec::new();
+    }
+    let last = (sorted.len() - 1) as f64;
+    ps.iter()
+        .map(|&p| {
+            let rank = p.clamp(0.0, 100.0) / 100.0 * last;
+            let lo = rank.floor() as usize;
+            let hi = rank.ceil() as usize;
+            let value = sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64);
+            (p, value)
+        })
+        .collect()
+}
+
+/// Computes the requested percentiles of `risk_score` over a stream.
+pub fn risk_percentiles(records: &[RuntimeStateRecord], ps: &[f64]) -> Vec<(f64, f64)> {
+    let values: Vec<f64> = records.iter().map(|r| r.risk_score).collect();
+    percentiles(&values, ps)
+}
+
+/// Computes the requested percentiles of `stability_score` over a stream.
+pub fn stability_percentiles(records: &[RuntimeStateRecord], ps: &[f64]) -> Vec<(f64, f64)> {
+    let values: Vec<f64> = records.iter().map(|r| r.stability_score).collect();
+    percentiles(&values, ps)
+}
+
+/// Like `risk_percentiles`, with NaN scores handled by `policy`.
+pub fn risk_percentiles_with(
+    records: &[RuntimeStateRecord],
+    ps: &[f64],
+    policy: NanPolicy,
+) -> Result<Vec<(f64, f64)>, PmatrixError> {
+    let values: Vec<f64> = records.iter().map(|r| r.risk_score).collect();
+    Ok(interpolate(&policy.sorted(&values, "risk_score")?, ps))
+}
+
+/// Like `stability_percentiles`, with NaN scores handled by `policy`.
+pub fn stability_percentiles_with(
+    records: &[RuntimeStateRecord],
+    ps: &[f64],
+    policy: NanPolicy,
+) -> Result<Vec<(f64, f64)>, PmatrixError> {
+    let values: Vec<f64> = records.iter().map(|r| r.stability_score).collect();
+    Ok(interpolate(&policy.sorted(&values, "stability_score")?, ps))
+}
+
+/// Finds runs of at least `min_run` consecutive records whose functions are
+/// bitwise identical, a sign of a frozen emitter.
+///
+/// Returns `(first, last)` index pairs, both inclusive, in stream order.
+/// `min_run` values below 2 are treated as 2.
+pub fn detect_flatline(records: &[RuntimeStateRecord], min_run: usize) -> Vec<(usize, usize)> {
+    flatline_runs(records, min_run, |a, b| {
+        a.values().iter().zip(b.values()).all(|(x, y)| x.to_bits() == y.to_bits())
+    })
+}
+
+/// Like `detect_flatline`, but treats functions as identical when every value
+/// is within `eps` of the value in the first record of the run. Comparing
+/// with the run's first record keeps slow drift from extending a run forever.
+pub fn detect_flatline_within(records: &[RuntimeStateRecord], min_run: usize, eps: f64) -> Vec<(usize, usize)> {
+    flatline_runs(records, min_run, |a, b| {
+        a.values().iter().zip(b.values()).all(|(x, y)| (x - y).abs() <= eps)
+    })
+}
+
+/// Trailing moving average of risk_score: entry `i` is the mean over record
+/// `i` and up to `window - 1` records before it, so the first entries
+/// average fewer records. A window of 0 is treated as 1, and a window longer
+/// than the stream averages everything seen so far.
+pub fn rolling_risk_mean(records: &[RuntimeStateRecord], window: usize) -> Vec<f64> {
+    let window = window.max(1);
+    // A running sum of the finite scores in the window. NaN and infinities
+    // would stay in the sum after leaving the window, so while the window
+    // holds any, its mean is summed directly instead.
+    let mut sum = 0.0;
+    let mut non_finite = 0usize;
+    let mut out = Vec::with_capacity(records.len());
+    for (i, record) in records.iter().enumerate() {
+        match record.risk_score {
+            v if v.is_finite() => sum += v,
+            _ => non_finite += 1,
+        }
+        if let Some(old) = i.checked_sub(window) {
+            match records[old].risk_score {
+                v if v.is_finite() => sum -= v,
+                _ => non_finite -= 1,
+            }
+        }
+        let slice = &records[(i + 1).saturating_sub(window)..=i];
+        let total = if non_finite == 0 { sum } else { slice.iter().map(|r| r.risk_score).sum() };
+        out.push(total / slice.len() as f64);
+    }
+    out
+}
+
+/// Classifies each value of `rolling_risk_mean` into a mode with the
+/// normative partition. Entries are None only where the mean is NaN or out
+/// of range.
+pub fn rolling_risk_modes(records: &[RuntimeStateRecord], window: usize) -> Vec<Option<&'static str>> {
+    rolling_risk_mean(records, window).into_iter().map(demo_partition_map).collect()
+}
+
+/// A record with its smoothed risk, written by `smooth` as an envelope so the
+/// record itself stays schema-conforming. `smooth` borrows the record;
+/// deserializing yields an owned one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SmoothedRecord<'a> {
+    pub record: Cow<'a, RuntimeStateRecord>,
+    /// The `rolling_risk_mean` entry for this record.
+    pub smoothed_risk_score: f64,
+    /// The mode of the smoothed risk, as given by `rolling_risk_modes`.
+    pub smoothed_mode: Option<String>,
+}
+
+/// Pairs each record with its trailing `window` risk mean and that mean's mode.
+pub fn smooth(records: &[RuntimeStateRecord], window: usize) -> Vec<SmoothedRecord<'_>> {
+    let modes = rolling_risk_modes(records, window);
+    records
+        .iter()
+        .zip(rolling_risk_mean(records, window))
+        .zip(modes)
+        .map(|((record, mean), mode)| SmoothedRecord {
+            record: Cow::Borrowed(record),
+            smoothed_risk_score: mean,
+            smoothed_mode: mode.map(str::to_string),
+        })
+        .collect()
+}
+
+/// The most frequent mode over a trailing window: entry `i` counts the modes
+/// of record `i` and up to `window - 1` records before it, so the first
+/// entries look at fewer records. Ties go to the more severe mode. Modes
+/// outside `MODES` are not counted, and an entry is None only if its window
+/// holds no known mode. A window of 0 is treated as 1.
+pub fn rolling_dominant_mode(records: &[RuntimeStateRecord], window: usize) -> Vec<Option<&'static str>> {
+    let window = window.max(1);
+    let index = |r: &RuntimeStateRecord| MODES.iter().position(|m| *m == r.mode);
+    let mut counts = [0usize; MODES.len()];
+    let mut out = Vec::with_capacity(records.len());
+    for (i, record) in records.iter().enumerate() {
+        if let Some(m) = index(record) {
+            counts[m] += 1;
+        }
+        if let Some(m) = i.checked_sub(window).and_then(|old| index(&records[old])) {
+            counts[m] -= 1;
+        }
+        // max_by_key keeps the last maximum, which is the most severe mode.
+        let (m, &n) = counts.iter().enumerate().max_by_key(|&(_, n)| n).expect("five modes");
+        out.push((n > 0).then_some(MODES[m]));
+    }
+    out
+}
+
+/// A record with the dominant mode of its trailing window, written by
+/// `smooth --dominant-mode`. Borrows the record like `SmoothedRecord`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DominantModeRecord<'a> {
+    pub record: Cow<'a, RuntimeStateRecord>,
+    /// The `rolling_dominant_mode` entry for this record.
+    pub dominant_mode: Option<String>,
+}
+
+/// Pairs each record with the dominant mode of its trailing `window`.
+pub fn annotate_dominant_mode(records: &[RuntimeStateRecord], window: usize) -> Vec<DominantModeRecord<'_>> {
+    records
+        .iter()
+        .zip(rolling_dominant_mode(records, window))
+        .map(|(record, mode)| DominantModeRecord { record: Cow::Borrowed(record), dominant_mode: mode.map(str::to_string) })
+        .collect()
+}
+
+/// Finds sustained high-risk periods: maximal runs of consecutive records
+/// whose risk_level is `min_level` or more severe (in `RISK_LEVELS` order)
+/// and whose timestamps span at least `min_duration_secs`.
+///
+/// Returns `(first, last, duration_secs)` with both indices inclusive, in
+/// stream order. The duration is the last timestamp minus the first (zero if
+/// the stream runs backwards). Records with an unknown risk_level end a run;
+/// an unknown `min_level` matches nothing.
+pub fn sustained_high_risk(
+    records: &[RuntimeStateRecord],
+    min_level: &str,
+    min_duration_secs: u64,
+) -> Vec<(usize, usize, u64)> {
+    let Some(threshold) = RISK_LEVELS.iter().position(|l| *l == min_level) else {
+        return Vec::new();
+    };
+    let high = |r: &RuntimeStateRecord| RISK_LEVELS.iter().position(|l| *l == r.risk_level).is_some_and(|i| i >= threshold);
+    let mut periods = Vec::new();
+    let mut start = None;
+    for i in 0..=records.len() {
+        match (start, i < records.len() && high(&records[i])) {
+            (None, true) => start = Some(i),
+            (Some(first), false) => {
+                let duration = records[i - 1].timestamp.saturating_sub(records[first].timestamp);
+                if duration >= min_duration_secs {
+                    periods.push((first, i - 1, duration));
+                }
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    periods
+}
+
+/// Lists every mode change: `(index, from, to)` for each record whose mode
+/// differs from its predecessor's, in stream order.
+pub fn mode_transitions(records: &[RuntimeStateRecord]) -> Vec<(usize, &str, &str)> {
+    records
+        .windows(2)
+        .enumerate()
+        .filter(|(_, pair)| pair[0].mode != pair[1].mode)
+        .map(|(i, pair)| (i + 1, pair[0].mode.as_str(), pair[1].mode.as_str()))
+        .collect()
+}
+
+/// Finds mode flapping: windows of `window` consecutive records that contain
+/// at least `min_changes` mode changes (as listed by `mode_transitions`), a
+/// sign of a score hovering around a partition boundary.
+///
+/// Overlapping qualifying windows are merged, so each incident is reported
+/// once. Returns `(first, last, changes)` with both indices inclusive and the
+/// number of changes between them, in stream order. A stream shorter than
+/// `window` is one window. `window` values below 2 are treated as 2 and
+/// `min_changes` values below 1 as 1.
+pub fn detect_flapping(records: &[RuntimeStateRecord], window: usize, min_changes: usize) -> Vec<(usize, usize, usize)> {
+    let (window, min_changes) = (window.max(2), min_changes.max(1));
+    let changes: Vec<usize> = mode_transitions(records).iter().map(|(i, _, _)| *i).collect();
+    // Changes at indices in (first, last], i.e. between records of the span.
+    let count = |first: usize, last: usize| changes.partition_point(|&i| i <= last) - changes.partition_point(|&i| i <= first);
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for first in 0..=records.len().saturating_sub(window) {
+        let last = (first + window).min(records.len()).saturating_sub(1);
+        if count(first, last) < min_changes {
+            continue;
+        }
+        match spans.last_mut() {
+            Some(span) if first <= span.1 => span.1 = last,
+            _ => spans.push((first, last)),
+        }
+    }
+    spans.into_iter().map(|(first, last)| (first, last, count(first, last))).collect()
+}
+
+fn flatline_runs(
+    records: &[RuntimeStateRecord],
+    min_run: usize,
+    same: impl Fn(&Functions, &Functions) -> bool,
+) -> Vec<(usize, usize)> {
+    let min_run = min_run.max(2);
+    let mut runs = Vec::new();
+    let mut start = 0;
+    for i in 1..=records.len() {
+        if i < records.len() && same(&records[start].functions, &records[i].functions) {
+            continue;
+        }
+        if i - start >= min_run {
+            runs.push((start, i - 1));
+        }
+        start = i;
+    }
+    runs
+}
@@ -0,0 +1,93 @@
+// summary.rs — Batch Validation Summary
+//
+// The outcome of validating a whole batch. Sinks receive it when a batch
+// finishes, and the text and markdown reports render it, so it lives apart
+// from both.
+
+use crate::invariants::{invariant_failure_counts, InvariantResult};
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+
+/// Outcome of a whole batch, passed to `ResultSink::finish`.
+///
+/// Serializes with two derived fields as well: `conformance_rate` and
+/// `t1_held`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchSummary {
+    /// Number of records validated.
+    pub records: usize,
+    /// Records with no violations (warnings do not count against them).
+    pub conforming: usize,
+    /// The first non-conforming record, if any.
+    pub first_failure: Option<FirstFailure>,
+    /// Index of the first record that breaks INV-T1, if any.
+    pub t1_violation: Option<usize>,
+    /// Failing records per invariant, as returned by `invariant_failure_counts`.
+    pub failures: BTreeMap<&'static str, usize>,
+}
+
+/// Where a batch first fails: a record index and the first invariant, in
+/// validation order, that the record violates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FirstFailure {
+    pub index: usize,
+    pub id: &'static str,
+}
+
+impl BatchSummary {
+    /// Summarizes per-record results and the outcome of `validate_stream_t1`.
+    pub fn from_results(results: &[Vec<InvariantResult>], t1_violation: Option<usize>) -> Self {
+        let first_failure = results.iter().enumerate().find_map(|(index, rs)| {
+            rs.iter().find(|r| r.is_violation()).map(|r| FirstFailure { index, id: r.id })
+        });
+        BatchSummary {
+            records: results.len(),
+            conforming: results.iter().filter(|rs| !rs.iter().any(|r| r.is_violation())).count(),
+            first_failure,
+            t1_violation,
+            failures: invariant_failure_counts(results),
+        }
+    }
+
+    /// True if every record conforms and INV-T1 holds.
+    pub fn is_conforming(&self) -> bool {
+        self.conforming == self.records && self.t1_held()
+    }
+
+    /// True if timestamps never decrease across the batch.
+    pub fn t1_held(&self) -> bool {
+        self.t1_violation.is_none()
+    }
+
+    /// Percentage of conforming records; 100 for an empty batch.
+    pub fn conformance_rate(&self) -> f64 {
+        if self.records == 0 {
+            100.0
+        } else {
+            100.0 * self.conforming as f64 / self.records as f64
+        }
+    }
+
+    /// Per-invariant failure counts, most frequent first (ties by ID).
+    pub fn ranked_failures(&self) -> Vec<(&'static str, usize)> {
+        let mut ranked: Vec<_> = self.failures.iter().map(|(&id, &n)| (id, n)).collect();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked
+    }
+}
+
+impl Serialize for BatchSummary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut s = serializer.serialize_struct("BatchSummary", 7)?;
+        s.serialize_field("records", &self.records)?;
+        s.serialize_field("conforming", &self.conforming)?;
+        s.serialize_field("conformance_rate", &self.conformance_rate())?;
+        s.serialize_field("first_failure", &self.first_failure)?;
+        s.serialize_field("t1_held", &self.t1_held())?;
+        s.serialize_field("t1_violation", &self.t1_violation)?;
+        s.serialize_field("failures", &self.failures)?;
+        s.end()
+    }
+}
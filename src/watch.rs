@@ -0,0 +1,151 @@
+// watch.rs — Live NDJSON Tailing
+//
+// Follows a growing NDJSON record file the way `tail -F` does: new complete
+// lines are yielded as they are appended, and truncation or replacement of the
+// file (log rotation) causes it to be re-opened from the start.
+
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Something observed while polling a tailed file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TailEvent {
+    /// A complete line (without its terminator).
+    Line(String),
+    /// The file was truncated or replaced; reading restarts from its beginning.
+    Rotated,
+    /// The file does not exist (yet). Reported once per disappearance.
+    Waiting,
+}
+
+/// Incremental reader over a file that may grow, rotate or not yet exist.
+pub struct TailReader {
+    path: PathBuf,
+    file: Option<File>,
+    pos: u64,
+    file_id: Option<u64>,
+    partial: Vec<u8>,
+    waiting: bool,
+    opened_before: bool,
+}
+
+#[cfg(unix)]
+fn file_id(meta: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(meta.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_meta: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+impl TailReader {
+    /// Creates a reader that starts at the beginning of `path`.
+    pub fn new(path: &Path) -> Self {
+        TailReader {
+            path: path.to_path_buf(),
+            file: None,
+            pos: 0,
+            file_id: None,
+            partial: Vec::new(),
+            waiting: false,
+            opened_before: false,
+        }
+    }
+
+    /// Reads whatever has been appended since the last poll.
+    ///
+    /// A trailing line without a newline is held back until it is completed.
+    pub fn poll(&mut self) -> io::Result<Vec<TailEvent>> {
+        let mut events = Vec::new();
+
+        let meta = match fs::metadata(&self.path) {
+            Ok(m) => m,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                self.file = None;
+                if !self.waiting {
+                    self.waiting = true;
+                    events.push(TailEvent::Waiting);
+                }
+                return Ok(events);
+            }
+            Err(e) => return Err(e),
+        };
+        self.waiting = false;
+
+        // A file that disappeared and came back counts as a rotation too.
+        let reappeared = self.file.is_none() && self.opened_before;
+        let replaced = self.file.is_some() && file_id(&meta) != self.file_id;
+        let truncated = self.file.is_some() && meta.len() < self.pos;
+        if reappeared || replaced || truncated {
+            self.file = None;
+            events.push(TailEvent::Rotated);
+        }
+
+        if self.file.is_none() {
+            self.file = Some(File::open(&self.path)?);
+            self.file_id = file_id(&meta);
+            self.opened_before = true;
+            self.pos = 0;
+            self.partial.clear();
+        }
+
+        let file = self.file.as_mut().expect("file opened above");
+        file.seek(SeekFrom::Start(self.pos))?;
+        let n = file.read_to_end(&mut self.partial)?;
+        self.pos += n as u64;
+
+        while let Some(idx) = self.partial.iter().position(|&b| b == b'\n') {
+            let bytes: Vec<u8> = self.partial.drain(..=idx).collect();
+            let line = String::from_utf8_lossy(&bytes);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if !line.trim().is_empty() {
+                events.push(TailEvent::Line(line.to_string()));
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Tracks INV-T1 across a live stream, including across file rotation.
+///
+/// After a rotation the first record is compared with the last record seen
+/// before it; if its timestamp is earlier, the emitter is assumed to have
+/// legitimately restarted and tracking resets instead of reporting a violation.
+#[derive(Debug, Default)]
+pub struct T1Tracker {
+    last: Option<u64>,
+    rotated: bool,
+}
+
+/// Outcome of observing one timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum T1Outcome {
+    Ok,
+    /// Timestamp is earlier than the previous one.
+    Violation { previous: u64 },
+    /// Timestamps restarted after a rotation; tracking was reset.
+    Reset { previous: u64 },
+}
+
+impl T1Tracker {
+    /// Notes that the underlying file rotated.
+    pub fn rotated(&mut self) {
+        self.rotated = true;
+    }
+
+    /// Observes the next timestamp in the stream.
+    pub fn observe(&mut self, timestamp: u64) -> T1Outcome {
+        let after_rotation = std::mem::take(&mut self.rotated);
+        let outcome = match self.last {
+            Some(prev) if timestamp < prev && after_rotation => T1Outcome::Reset { previous: prev },
+            Some(prev) if timestamp < prev => T1Outcome::Violation { previous: prev },
+            _ => T1Outcome::Ok,
+        };
+        // Like validate_stream_t1, each record is compared with its predecessor.
+        self.last = Some(timestamp);
+        outcome
+    }
+}
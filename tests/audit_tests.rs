@@ -0,0 +1,52 @@
+// audit_tests.rs — Validation Audit Log Tests
+//
+// Tests cover:
+// - Violation bitmask encoding
+// - Audit events, one flushed JSON line per record
+#![cfg(feature = "canonical")]
+
+use pmatrix_encoder::audit::*;
+use pmatrix_encoder::fingerprint::record_fingerprint;
+use pmatrix_encoder::invariants::*;
+use pmatrix_encoder::schema::spec_example_record;
+
+#[test]
+fn test_violation_bitmask() {
+    let mut record = spec_example_record();
+    assert_eq!(violation_bitmask(&validate_all(&record)), 0);
+    record.timestamp = 0;
+    assert_eq!(violation_bitmask(&validate_all(&record)), 1 << 3); // INV-R4
+    record.mode = "Halt".into();
+    // INV-C1 (bit 4), INV-C2 (5) and INV-C3 (6) also fail.
+    assert_eq!(violation_bitmask(&validate_all(&record)), 0b111_1000);
+    let opts = ValidationOptions { demo_aggregation_eps: Some(1e-9), ..Default::default() };
+    let mask = violation_bitmask(&validate_all_with(&spec_example_record(), &opts));
+    assert_eq!(mask, 1 << 12); // INV-R6
+}
+
+#[test]
+fn test_append_audit_event_lines() {
+    let good = spec_example_record();
+    let mut bad = good.clone();
+    bad.timestamp = 0;
+
+    let mut log = Vec::new();
+    for record in [&good, &bad] {
+        append_audit_event(&mut log, record, &validate_all(record)).unwrap();
+    }
+    let text = String::from_utf8(log).unwrap();
+    let events: Vec<AuditEvent> = text.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(
+        events[0],
+        AuditEvent {
+            fingerprint: record_fingerprint(&good),
+            timestamp: 1707500000,
+            conforming: true,
+            violation_bitmask: 0,
+        }
+    );
+    assert!(!events[1].conforming);
+    assert_eq!(events[1].violation_bitmask, 1 << 3);
+    assert!(text.starts_with(r#"{"fingerprint":""#), "{}", text);
+}
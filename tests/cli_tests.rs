@@ -0,0 +1,479 @@
+// cli_tests.rs — End-to-End CLI Tests
+//
+// Runs the built binary with std::process, so argument parsing, stdin
+// handling and exit codes are covered as well as the library.
+//
+// Tests cover:
+// - emit with valid, out-of-range and missing arguments
+// - emit --aggregator and its --weights
+// - validate with conforming, non-conforming, empty and malformed stdin
+// - Exit codes: 0 conforming, 1 violation, 2 bad input, 3 I/O error, 4 usage error
+// - Undecodable input exiting 2 rather than 3
+// - --input-format sniffing for streamed input (sample, stats --streaming)
+// - The --max-records cap for loaded, pointer-selected and streamed input
+// - The legacy exit-code scheme
+// - NDJSON output of validate --format json
+// - Inverted exit codes of validate --invert
+// - Exit code 5 for stale input with validate --max-staleness
+// - Failures-only output of validate --only-failures
+// - Schema version ranges with validate --schema-version-range
+// - The global --functions-array flag
+// - INV-C5 failures and corrected output of validate --recompute-mode
+// - INV-T2 failures with validate --strict-monotonic
+// - replay match rate and drift reporting
+// - bisect locating the first non-conforming record
+// - check-schema verdicts, or its error without the json-schema feature
+// - doctor on malformed and conforming records
+// - stats --color: plain when piped, ANSI when forced
+// - filter --where predicates and their parse errors
+// - convert between JSON and NDJSON, rejecting encodings it cannot write,
+//   input of the wrong --from shape, and non-conforming or unordered streams
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+const ENV_VARS: [&str; 4] = ["PMATRIX_BASELINE", "PMATRIX_NORM", "PMATRIX_STABILITY", "PMATRIX_META_CONTROL"];
+
+/// Runs the CLI with `args`, feeding `stdin` to it.
+fn run(args: &[&str], stdin: &str) -> Output {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_pmatrix-encoder"));
+    for var in ENV_VARS {
+        cmd.env_remove(var);
+    }
+    let mut child = cmd
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn pmatrix-encoder");
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(out: &Output) -> String {
+    String::from_utf8(out.stdout.clone()).unwrap()
+}
+
+fn stderr(out: &Output) -> String {
+    String::from_utf8(out.stderr.clone()).unwrap()
+}
+
+fn emit_record(timestamp: u64) -> String {
+    let ts = timestamp.to_string();
+    let args = ["emit", "--baseline", "0.5", "--norm", "0.5", "--stability", "0.5", "--meta-control", "0.5", "--timestamp", &ts];
+    let out = run(&args, "");
+    assert_eq!(out.status.code(), Some(0), "{}", stderr(&out));
+    stdout(&out)
+}
+
+// ============================================================
+// emit
+// ============================================================
+
+#[test]
+fn test_emit_valid_args() {
+    let record: serde_json::Value = serde_json::from_str(&emit_record(1707500000)).unwrap();
+    assert_eq!(record["timestamp"], 1707500000);
+    assert_eq!(record["functions"]["meta_control"], 0.5);
+}
+
+#[test]
+fn test_emit_out_of_range_fails() {
+    let out = run(&["emit", "--baseline", "2", "--norm", "0.5", "--stability", "0.5", "--meta-control", "0.5"], "");
+    assert_eq!(out.status.code(), Some(2));
+    assert!(stdout(&out).is_empty());
+    assert!(stderr(&out).contains("baseline = 2 is outside [0.0, 1.0]"), "{}", stderr(&out));
+}
+
+#[test]
+fn test_emit_aggregator() {
+    let args = ["emit", "--baseline", "0.2", "--norm", "0.8", "--stability", "0.8", "--meta-control", "0.8", "--timestamp", "1000"];
+    let out = run(&[&args[..], &["--aggregator", "min"]].concat(), "");
+    let record: serde_json::Value = serde_json::from_str(&stdout(&out)).unwrap();
+    assert_eq!((record["stability_score"].as_f64(), record["mode"].as_str()), (Some(0.2), Some("Halt")));
+
+    let out = run(&[&args[..], &["--aggregator", "weighted"]].concat(), "");
+    assert_eq!(out.status.code(), Some(4));
+    assert!(stderr(&out).contains("requires weights"), "{}", stderr(&out));
+    let out = run(&[&args[..], &["--aggregator", "weighted", "--weights", "1,1,1,1"]].concat(), "");
+    assert_eq!(out.status.code(), Some(0), "{}", stderr(&out));
+}
+
+#[test]
+fn test_emit_missing_args_is_usage_error() {
+    let out = run(&["emit", "--baseline", "0.5"], "");
+    assert_eq!(out.status.code(), Some(4));
+    assert!(stderr(&out).contains("PMATRIX_NORM"), "{}", stderr(&out));
+}
+
+// ============================================================
+// validate
+// ============================================================
+
+#[test]
+fn test_validate_conforming_stdin() {
+    let out = run(&["validate"], &emit_record(1000));
+    assert_eq!(out.status.code(), Some(0), "{}", stdout(&out));
+    assert!(stdout(&out).contains("ALL INVARIANTS SATISFIED"));
+}
+
+#[test]
+fn test_validate_non_conforming_stdin() {
+    let record = emit_record(1000).replace("\"Caution\"", "\"Halt\"");
+    let out = run(&["validate"], &record);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stdout(&out).contains("[FAIL] INV-C1"), "{}", stdout(&out));
+}
+
+#[test]
+fn test_validate_empty_and_malformed_stdin() {
+    for input in ["", "{bad"] {
+        let out = run(&["validate"], input);
+        assert_eq!(out.status.code(), Some(2), "{:?}", input);
+        assert!(stderr(&out).starts_with("JSON parse error:"), "{}", stderr(&out));
+    }
+}
+
+#[test]
+fn test_validate_undecodable_input_is_input_error() {
+    let latin1 = std::env::temp_dir().join(format!("pmatrix-latin1-{}.json", std::process::id()));
+    std::fs::write(&latin1, b"{\"mode\": \"\xe9\"}").unwrap();
+    let cases: [(&[&str], &str); 2] = [
+        (&["validate"], "hello"),
+        (&["validate", "--input-format", "json", latin1.to_str().unwrap()], ""),
+    ];
+    for (args, input) in cases {
+        let out = run(args, input);
+        assert_eq!(out.status.code(), Some(2), "{:?}: {}", args, stderr(&out));
+        assert!(stderr(&out).starts_with("Input error: "), "{}", stderr(&out));
+    }
+    std::fs::remove_file(&latin1).unwrap();
+}
+
+#[test]
+fn test_streamed_input_honors_input_format() {
+    for args in [&["sample", "--k", "5"][..], &["stats", "--streaming"]] {
+        let out = run(args, "---\nmode: Caution\n");
+        assert_eq!(out.status.code(), Some(2), "{:?}: {}", args, stderr(&out));
+        assert!(stderr(&out).contains("YAML input is not supported"), "{:?}: {}", args, stderr(&out));
+        let out = run(&[&["--input-format", "json"], args].concat(), &emit_record(1000));
+        assert_eq!(out.status.code(), Some(0), "{:?}: {}", args, stderr(&out));
+    }
+}
+
+#[test]
+fn test_max_records_applies_to_every_input_path() {
+    let too_many = emit_record(1000) + &emit_record(1001);
+    let enveloped = format!("{{\"records\": [{}]}}", too_many.trim_end().replace("}\n{", "},{"));
+    let cases: [(&[&str], &str); 4] = [
+        (&["validate", "--batch"], &too_many),
+        (&["validate", "--batch", "--json-pointer", "/records"], &enveloped),
+        (&["stats", "--streaming"], &too_many),
+        (&["sample", "--k", "5"], &too_many),
+    ];
+    for (args, input) in cases {
+        let out = run(&[&["--max-records", "1"], args].concat(), input);
+        assert_eq!(out.status.code(), Some(2), "{:?}: {}", args, stderr(&out));
+        assert!(stderr(&out).contains("more than 1 records; raise --max-records"), "{:?}: {}", args, stderr(&out));
+    }
+}
+
+#[test]
+fn test_validate_unknown_flag_is_usage_error() {
+    let out = run(&["validate", "--no-such-flag"], "");
+    assert_eq!(out.status.code(), Some(4));
+}
+
+#[test]
+fn test_legacy_exit_code_scheme() {
+    let legacy = |args: &[&str], stdin: &str| run(&[&["--exit-code-scheme", "legacy"], args].concat(), stdin).status.code();
+    assert_eq!(legacy(&["validate"], "{bad"), Some(1));
+    assert_eq!(legacy(&["validate", "/nonexistent/record.json"], ""), Some(1));
+    assert_eq!(legacy(&["validate", "--no-such-flag"], ""), Some(2));
+    assert_eq!(run(&["--exit-code-scheme=legacy", "validate", "--no-such-flag"], "").status.code(), Some(2));
+    assert_eq!(legacy(&["validate", "--batch", "--max-staleness", "60", "--now", "1200"], &emit_record(1000)), Some(3));
+    assert_eq!(legacy(&["validate"], &emit_record(1000)), Some(0));
+    assert_eq!(run(&["validate", "/nonexistent/record.json"], "").status.code(), Some(3));
+}
+
+#[test]
+fn test_validate_batch_json_format() {
+    let stream = emit_record(1000) + &emit_record(900);
+    let out = run(&["validate", "--batch", "--format", "json"], &stream);
+    assert_eq!(out.status.code(), Some(1));
+    let lines: Vec<serde_json::Value> = stdout(&out).lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0]["conforming"], true);
+    assert_eq!(lines[2]["summary"]["records"], 2);
+    assert_eq!(lines[2]["summary"]["t1_violation"], 1);
+}
+
+#[test]
+fn test_validate_invert_flips_only_the_exit_code() {
+    let good = emit_record(1000);
+    let bad = good.replace("\"Caution\"", "\"Halt\"");
+    let out = run(&["validate", "--invert"], &bad);
+    assert_eq!(out.status.code(), Some(0));
+    assert!(stdout(&out).contains("[FAIL] INV-C1"), "{}", stdout(&out));
+
+    let out = run(&["validate", "--invert"], &good);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stdout(&out).contains("ALL INVARIANTS SATISFIED"));
+
+    let out = run(&["validate", "--invert"], "{bad");
+    assert_eq!(out.status.code(), Some(0));
+    let out = run(&["validate", "--invert", "/nonexistent/record.json"], "");
+    assert_eq!(out.status.code(), Some(3));
+}
+
+#[test]
+fn test_validate_max_staleness() {
+    let stream = emit_record(1000) + &emit_record(1100);
+    let out = run(&["validate", "--batch", "--max-staleness", "60", "--now", "1150"], &stream);
+    assert_eq!(out.status.code(), Some(0), "{}", stderr(&out));
+
+    let out = run(&["validate", "--batch", "--max-staleness", "60", "--now", "1200"], &stream);
+    assert_eq!(out.status.code(), Some(5));
+    assert!(stderr(&out).contains("is 100 s old"), "{}", stderr(&out));
+
+    // A record from the future is noted but not stale.
+    let out = run(&["validate", "--max-staleness", "60", "--now", "900"], &emit_record(1000));
+    assert_eq!(out.status.code(), Some(0));
+    assert!(stderr(&out).contains("100 s in the future"), "{}", stderr(&out));
+
+    // Invariant failures take precedence over staleness.
+    let bad = emit_record(1000).replace("\"Caution\"", "\"Halt\"");
+    let out = run(&["validate", "--max-staleness", "60", "--now", "5000"], &bad);
+    assert_eq!(out.status.code(), Some(1));
+}
+
+#[test]
+fn test_validate_only_failures() {
+    let bad = emit_record(1000).replace("\"Caution\"", "\"Halt\"");
+    let out = run(&["validate", "--only-failures"], &bad);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(!stdout(&out).contains("[PASS]"), "{}", stdout(&out));
+    assert!(stdout(&out).contains("[FAIL] INV-C1"));
+
+    let stream = emit_record(1000) + &bad + &emit_record(1002);
+    let out = run(&["validate", "--batch", "--only-failures"], &stream);
+    let text = stdout(&out);
+    assert!(text.starts_with("[FAIL] record #1 — "), "{}", text);
+    assert!(!text.contains("record #0") && !text.contains("record #2"), "{}", text);
+    assert!(text.contains("INV-T1"));
+
+    let out = run(&["validate", "--only-failures", "--format", "markdown"], &bad);
+    assert_eq!(out.status.code(), Some(4));
+    assert!(stderr(&out).contains("--only-failures is not supported"), "{}", stderr(&out));
+}
+
+#[test]
+fn test_validate_recompute_mode() {
+    let corrected = std::env::temp_dir().join(format!("pmatrix-corrected-{}.ndjson", std::process::id()));
+    let stream = emit_record(1000) + &emit_record(1001);
+    let args = ["validate", "--batch", "--recompute-mode", "--threshold-profile", "conservative", "--corrected-out"];
+    let out = run(&[&args[..], &[corrected.to_str().unwrap()]].concat(), &stream);
+    assert_eq!(out.status.code(), Some(1));
+    let text = stdout(&out);
+    assert!(!text.contains("INV-C1"), "{}", text);
+    assert!(text.contains("[FAIL] record #0 — INV-C5\n[FAIL] record #1 — INV-C5\n"), "{}", text);
+
+    let fixed = std::fs::read_to_string(&corrected).unwrap();
+    std::fs::remove_file(&corrected).unwrap();
+    assert_eq!(fixed.lines().count(), 2);
+    assert!(fixed.lines().all(|l| l.contains("\"mode\":\"Alert\"") && l.contains("\"risk_level\":\"L4\"")), "{}", fixed);
+    let out = run(&["validate", "--batch", "--threshold-profile", "conservative"], &fixed);
+    assert_eq!(out.status.code(), Some(0), "{}", stdout(&out));
+
+    let out = run(&["validate", "--recompute-mode"], &emit_record(1000));
+    assert_eq!(out.status.code(), Some(0), "{}", stderr(&out));
+
+    // A single stale record is reported as failing, not as conforming.
+    let args = ["validate", "--recompute-mode", "--threshold-profile", "conservative", "--format", "json"];
+    let out = run(&args, &emit_record(1000));
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stdout(&out).starts_with("{\"index\":0,\"timestamp\":1000,\"conforming\":false,\"failed\":[\"INV-C5\"]"), "{}", stdout(&out));
+    assert!(stdout(&out).contains("Stale classification: risk_score 0.5 maps to Alert/L4"), "{}", stdout(&out));
+}
+
+#[test]
+fn test_validate_strict_monotonic() {
+    let stream = emit_record(1000) + &emit_record(1000) + &emit_record(1001);
+    let out = run(&["validate", "--batch"], &stream);
+    assert_eq!(out.status.code(), Some(0), "{}", stdout(&out));
+    let out = run(&["validate", "--batch", "--strict-monotonic", "--format", "json"], &stream);
+    assert_eq!(out.status.code(), Some(1));
+    let text = stdout(&out);
+    assert!(text.contains("\"failures\":[{\"id\":\"INV-T2\",\"code\":\"TIMESTAMP_NOT_INCREASING\",\"detail\":\"timestamp=1000, previous=1000\"}]"), "{}", text);
+    assert!(text.lines().last().unwrap().contains("\"first_failure\":{\"id\":\"INV-T2\",\"index\":1}"), "{}", text);
+}
+
+#[test]
+fn test_validate_schema_version_range() {
+    let record = emit_record(1000);
+    let out = run(&["validate", "--schema-version-range", ">=1.0.0, <2.0.0"], &record);
+    assert_eq!(out.status.code(), Some(0), "{}", stdout(&out));
+    assert!(stdout(&out).contains("[PASS] INV-S6"));
+
+    let out = run(&["validate", "--structural-only", "--schema-version-range", ">=1.1.0"], &record);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stdout(&out).contains("[FAIL] INV-S6 — schema_version=1.0.0, accepted=>=1.1.0"), "{}", stdout(&out));
+
+    let out = run(&["validate", "--schema-version-range", "1.x"], &record);
+    assert_eq!(out.status.code(), Some(4));
+    assert!(stderr(&out).contains("`1.x` is not MAJOR.MINOR.PATCH"), "{}", stderr(&out));
+}
+
+#[test]
+fn test_validate_functions_array() {
+    let mut record: serde_json::Value = serde_json::from_str(&emit_record(1000)).unwrap();
+    record["functions"] = serde_json::json!([0.5, 0.5, 0.5, 0.5]);
+    let out = run(&["validate"], &record.to_string());
+    assert_eq!(out.status.code(), Some(2));
+    let out = run(&["--functions-array", "validate"], &record.to_string());
+    assert_eq!(out.status.code(), Some(0), "{}", stderr(&out));
+
+    record["functions"] = serde_json::json!([0.5, 0.5]);
+    let out = run(&["--functions-array", "validate"], &record.to_string());
+    assert_eq!(out.status.code(), Some(2));
+    assert!(stderr(&out).contains("functions array has 2 element(s)"), "{}", stderr(&out));
+}
+
+// ============================================================
+// replay
+// ============================================================
+
+#[test]
+fn test_replay_reports_drift_and_match_rate() {
+    let mut drifted: serde_json::Value = serde_json::from_str(&emit_record(1001)).unwrap();
+    drifted["risk_score"] = 0.45.into();
+    let drifted = format!("{}\n", drifted);
+    let out = run(&["replay"], &(emit_record(1000) + &drifted));
+    assert_eq!(out.status.code(), Some(1));
+    let text = stdout(&out);
+    assert!(text.starts_with("[MATCH] #0 (conforming)\n"), "{}", text);
+    assert!(text.contains("[DRIFT] #1 (conforming) — risk_score 0.45 → 0.5\n"), "{}", text);
+    assert!(text.ends_with("1 of 2 record(s) match the demo model (50.0%); 2 conforming.\n"), "{}", text);
+
+    let out = run(&["replay", "--eps", "0.1"], &drifted);
+    assert_eq!(out.status.code(), Some(0), "{}", stdout(&out));
+}
+
+#[test]
+fn test_bisect() {
+    let good = emit_record(1000) + &emit_record(1001);
+    let out = run(&["bisect"], &good);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(stdout(&out), "All 2 record(s) conform.\n");
+
+    let bad = emit_record(1002).replace("\"Caution\"", "\"Halt\"");
+    let out = run(&["bisect"], &(good + &bad + &emit_record(1003)));
+    assert_eq!(out.status.code(), Some(1));
+    // With the chrono feature the timestamp is followed by its RFC 3339 form.
+    let text = stdout(&out);
+    assert!(text.starts_with("First non-conforming record: #2 at timestamp 1002"), "{}", text);
+    assert!(text.contains(" — INV-C1, INV-C2, INV-C3\n"), "{}", text);
+}
+
+#[test]
+fn test_check_schema() {
+    let schema = std::env::temp_dir().join(format!("pmatrix-schema-{}.json", std::process::id()));
+    std::fs::write(&schema, r#"{"type": "object", "properties": {"mode": {"pattern": "^[A-Z]"}}}"#).unwrap();
+    let out = run(&["check-schema", "--schema", schema.to_str().unwrap()], &emit_record(1000));
+    std::fs::remove_file(&schema).unwrap();
+    if cfg!(feature = "json-schema") {
+        assert_eq!(out.status.code(), Some(0), "{}", stderr(&out));
+        assert!(stdout(&out).ends_with("Result: AGREE — both accept the record.\n"), "{}", stdout(&out));
+    } else {
+        assert_eq!(out.status.code(), Some(4));
+        assert!(stderr(&out).contains("requires the `json-schema` feature"), "{}", stderr(&out));
+    }
+}
+
+#[test]
+fn test_doctor() {
+    let out = run(&["doctor"], &emit_record(1000));
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(stdout(&out), "No problems found; the record conforms.\n");
+
+    let out = run(&["doctor"], "{\"mode\": \"Caution\",}");
+    assert_eq!(out.status.code(), Some(1));
+    let text = stdout(&out);
+    assert!(text.starts_with("1. the input is not valid JSON: trailing comma"), "{}", text);
+    assert!(text.contains("   hint: Remove the comma"), "{}", text);
+    assert!(text.ends_with("1 problem(s) found.\n"), "{}", text);
+}
+
+// ============================================================
+// stats
+// ============================================================
+
+#[test]
+fn test_stats_color() {
+    let stream = emit_record(1000) + &emit_record(1001);
+    let out = run(&["stats"], &stream);
+    assert_eq!(out.status.code(), Some(0));
+    assert!(!stdout(&out).contains('\x1b'), "{:?}", stdout(&out));
+    let out = run(&["stats", "--color", "always"], &stream);
+    assert!(stdout(&out).contains("\x1b[33mrisk_score "), "{:?}", stdout(&out));
+}
+
+// ============================================================
+// filter
+// ============================================================
+
+#[test]
+fn test_filter_where() {
+    let stream = emit_record(1000) + &emit_record(1001) + &emit_record(1002);
+    let out = run(&["filter", "--where", "timestamp >= 1001 && risk_level == L3"], &stream);
+    assert_eq!(out.status.code(), Some(0), "{}", stderr(&out));
+    assert_eq!(stdout(&out).lines().count(), 2);
+
+    let out = run(&["filter", "--where", "risk_score >"], &stream);
+    assert_eq!(out.status.code(), Some(4));
+    assert!(stderr(&out).contains("expected a number to compare `risk_score` with at column 13"), "{}", stderr(&out));
+}
+
+// ============================================================
+// convert
+// ============================================================
+
+#[test]
+fn test_convert() {
+    let stream = emit_record(1000) + &emit_record(1001);
+    let out = run(&["convert", "--to", "json"], &stream);
+    assert_eq!(out.status.code(), Some(0), "{}", stderr(&out));
+    let array: serde_json::Value = serde_json::from_str(&stdout(&out)).unwrap();
+    assert_eq!(array.as_array().unwrap().len(), 2);
+
+    let out = run(&["convert", "--to", "ndjson"], &stdout(&out));
+    assert_eq!(out.status.code(), Some(0), "{}", stderr(&out));
+    assert_eq!(stdout(&out).lines().count(), 2);
+
+    let array = stdout(&run(&["convert", "--to", "json"], &stream));
+    let cases: [(&str, &str, &str); 2] = [("json", &stream, "several records"), ("ndjson", &array, "a JSON array")];
+    for (from, input, reason) in cases {
+        let out = run(&["convert", "--from", from, "--to", "json"], input);
+        assert_eq!(out.status.code(), Some(2), "{}: {}", from, stderr(&out));
+        assert!(stderr(&out).contains(reason), "{}: {}", from, stderr(&out));
+    }
+    assert_eq!(run(&["convert", "--from", "json", "--to", "ndjson"], &array).status.code(), Some(0));
+    assert_eq!(run(&["convert", "--from", "ndjson", "--to", "json"], &stream).status.code(), Some(0));
+
+    // Failures are numbered as by validate --batch, and INV-T1 is checked.
+    let bad = emit_record(1000) + &emit_record(1001).replace("\"L3\"", "\"L4\"");
+    let out = run(&["convert", "--to", "json"], &bad);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stderr(&out).contains("record #1 fails INV-C2"), "{}", stderr(&out));
+    let out = run(&["convert", "--to", "json"], &(emit_record(1001) + &emit_record(1000)));
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stderr(&out).contains("timestamp decreases at record #1 (INV-T1)"), "{}", stderr(&out));
+
+    for to in ["yaml", "cbor"] {
+        // Rejected before stdin is read, so none is written.
+        let out = run(&["convert", "--to", to], "");
+        assert_eq!(out.status.code(), Some(4));
+        assert!(stderr(&out).contains("[possible values: json, ndjson]"), "{}", stderr(&out));
+    }
+}
@@ -5,18 +5,33 @@
 // - Boundary values: 0.0, 0.2, 0.4, 0.6, 0.8, 1.0
 // - Edge cases: NaN, out-of-range, empty strings
 // - D1-A §5 Example record verification
+// - Opt-in schema version ranges (INV-S6)
+// - Opt-in recomputed classification (INV-C5)
 // - Stream-level INV-T1 validation
+// - Warning-severity quality checks (INV-Q1)
+// - Machine-readable error codes on failed checks
+// - Demo score aggregators and their registry
 
+#[path = "../benches/common/mod.rs"]
+mod common;
+
+use common::reference_partition_map;
 use pmatrix_encoder::schema::*;
 use pmatrix_encoder::mode::*;
 use pmatrix_encoder::invariants::*;
 use pmatrix_encoder::demo::*;
-use pmatrix_encoder::{emit_demo_record, is_record_valid};
+use pmatrix_encoder::input::{parse_lenient, parse_strict};
+use pmatrix_encoder::clock::FixedClock;
+use pmatrix_encoder::{
+    collect_stream, emit_demo_record, emit_demo_record_aggregated, emit_demo_record_from, emit_demo_record_with_clock, EmitInputs, first_nonconforming, is_record_valid, normalize_record, replay_drift,
+    validate_json, validate_value, PmatrixError,
+};
 
 // ============================================================
 // Helper
 // ============================================================
 
+#[allow(clippy::too_many_arguments)]
 fn make_record(
     baseline: f64, norm: f64, stability: f64, meta_control: f64,
     stability_score: f64, risk_score: f64,
@@ -27,6 +42,7 @@ fn make_record(
         spec_version: SPEC_VERSION.to_string(),
         schema_version: SCHEMA_VERSION.to_string(),
         timestamp,
+        sequence_number: None,
         functions: Functions { baseline, norm, stability, meta_control },
         stability_score,
         risk_score,
@@ -48,6 +64,9 @@ fn test_spec_example_record() {
         1707500000,
     );
     assert!(is_record_valid(&record));
+    // The library's reference copy must match the spec literal exactly.
+    assert_eq!(spec_example_record(), record);
+    assert!(validate_all(&spec_example_record()).iter().all(|r| r.passed));
 }
 
 // ============================================================
@@ -159,6 +178,253 @@ fn test_inv_r3_fail_nan() {
     assert!(!results.iter().find(|r| r.id == "INV-R3").unwrap().passed);
 }
 
+#[test]
+fn test_negative_zero_treated_as_zero() {
+    let record = make_record(-0.0, 1.0, 1.0, 1.0, 0.75, -0.0, "Optimal", "L1", 1000);
+    assert!(is_record_valid(&record));
+    let results = validate_all(&record);
+    let r3 = results.iter().find(|r| r.id == "INV-R3").unwrap();
+    assert!(r3.passed);
+    assert_eq!(r3.detail, "risk_score=0");
+}
+
+#[test]
+fn test_subnormal_flagged_only_with_strict_floats() {
+    let tiny = f64::MIN_POSITIVE / 2.0;
+    assert!(tiny.is_subnormal());
+    let record = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000)
+        .with_raw_functions(Functions { baseline: tiny, norm: 0.5, stability: 0.5, meta_control: 0.5 });
+    assert!(is_record_valid(&record));
+
+    let strict = ValidationOptions { strict_floats: true, ..Default::default() };
+    assert!(!is_valid_with(&record, &strict));
+    let r1 = validate_all_with(&record, &strict);
+    let r1 = r1.iter().find(|r| r.id == "INV-R1").unwrap();
+    assert!(!r1.passed);
+    assert!(r1.detail.contains("subnormal (bits=0x0008000000000000)"), "{}", r1.detail);
+    assert_eq!(r1.code, Some(ErrorCode::FunctionSubnormal));
+}
+
+#[test]
+fn test_error_codes() {
+    let tiny = f64::MIN_POSITIVE / 2.0;
+    let record = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000)
+        .with_raw_functions(Functions { baseline: tiny, norm: 1.5, stability: 0.5, meta_control: 0.5 });
+    let strict = ValidationOptions { strict_floats: true, ..Default::default() };
+    let codes: Vec<_> = validate_all_with(&record, &strict).iter().filter_map(|r| r.code).collect();
+    // The out-of-range norm outranks the earlier subnormal baseline.
+    assert_eq!(codes, [ErrorCode::FunctionOutOfRange]);
+
+    let mut record = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.1, "Caution", "L3", 0);
+    record.spec_version = "pmatrix-3.4".to_string();
+    let codes: Vec<_> = validate_all(&record).iter().filter_map(|r| r.code).map(ErrorCode::as_str).collect();
+    assert_eq!(
+        codes,
+        ["TIMESTAMP_ZERO", "MODE_SCORE_MISMATCH", "INCONSISTENT_CLASSIFICATION", "SPEC_VERSION_MISMATCH"]
+    );
+    assert!(validate_all(&make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000)).iter().all(|r| r.code.is_none()));
+    assert_eq!(serde_json::to_string(&ErrorCode::LevelModeMismatch).unwrap(), "\"LEVEL_MODE_MISMATCH\"");
+}
+
+// ============================================================
+// INV-R6 (opt-in, demo-only): demo aggregation of functions
+// ============================================================
+
+#[test]
+fn test_inv_r6_off_by_default() {
+    let record = make_record(0.25, 0.70, 0.30, 0.20, 0.58, 0.42, "Caution", "L3", 1000);
+    assert!(validate_all(&record).iter().all(|r| r.id != "INV-R6"));
+}
+
+#[test]
+fn test_inv_r6_demo_record_passes() {
+    let opts = ValidationOptions { demo_aggregation_eps: Some(1e-9), ..Default::default() };
+    let record = emit_demo_record(0.25, 0.70, 0.30, 0.20, Some(1000)).unwrap();
+    let results = validate_all_with(&record, &opts);
+    assert!(results.iter().find(|r| r.id == "INV-R6").unwrap().passed);
+}
+
+#[test]
+fn test_inv_r6_spec_example_fails() {
+    // The D1-A §5 example was not produced by the demo mean (0.3625).
+    let opts = ValidationOptions { demo_aggregation_eps: Some(1e-9), ..Default::default() };
+    let record = make_record(0.25, 0.70, 0.30, 0.20, 0.58, 0.42, "Caution", "L3", 1000);
+    let results = validate_all_with(&record, &opts);
+    let r6 = results.iter().find(|r| r.id == "INV-R6").unwrap();
+    assert!(!r6.passed);
+    assert!(r6.detail.contains("demo aggregation=0.3625"), "{}", r6.detail);
+}
+
+// ============================================================
+// Threshold profiles
+// ============================================================
+
+#[test]
+fn test_builtin_profiles() {
+    assert_eq!(builtin_profile("default"), Some(PartitionMap::default()));
+    assert_eq!(builtin_profile("nope"), None);
+    for (name, _, _) in THRESHOLD_PROFILES {
+        assert!(validate_partition_coverage(&builtin_profile(name).unwrap()).is_ok(), "{}", name);
+    }
+    let conservative = builtin_profile("conservative").unwrap();
+    assert_eq!(conservative.classify(0.6), Some("Halt"));
+    assert_eq!(conservative.classify(0.14), Some("Optimal"));
+    assert_eq!(conservative.classify(1.0), Some("Halt"));
+    let opts = ValidationOptions { partition_map: conservative, ..Default::default() };
+    // 0.5 is Caution by default but Alert under the conservative profile.
+    let record = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+    assert!(is_record_valid(&record));
+    assert!(!validate_all_with(&record, &opts).iter().find(|r| r.id == "INV-C1").unwrap().passed);
+}
+
+#[test]
+fn test_reclassify_record_after_profile_change() {
+    let opts = ValidationOptions { partition_map: builtin_profile("conservative").unwrap(), ..Default::default() };
+    let record = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+    let fixed = reclassify_record(&record, &opts).unwrap();
+    assert_eq!((fixed.mode.as_str(), fixed.risk_level.as_str()), ("Alert", "L4"));
+    assert_eq!(fixed.risk_score, 0.5);
+    assert!(validate_all_with(&fixed, &opts).iter().all(|r| r.passed));
+    assert_eq!(reclassify_record(&record, &ValidationOptions::default()), Some(record.clone()));
+
+    let out_of_range = RuntimeStateRecord { risk_score: 1.5, ..record };
+    assert_eq!(reclassify_record(&out_of_range, &opts), None);
+}
+
+#[test]
+fn test_partition_map_from_thresholds_rejects_disorder() {
+    assert!(PartitionMap::from_thresholds([0.2, 0.6, 0.4, 0.8]).is_err());
+}
+
+#[test]
+fn test_partition_map_binary_search_matches_linear_scan() {
+    let default = PartitionMap::default();
+    for k in 0..=100_000 {
+        let x = k as f64 / 100_000.0;
+        assert_eq!(default.map(x), demo_partition_map(x), "risk_score={}", x);
+    }
+    for b in [0.0f64, 0.2, 0.4, 0.6, 0.8, 1.0] {
+        for x in [b, b.next_down(), b.next_up()] {
+            assert_eq!(default.map(x), demo_partition_map(x), "risk_score={:e}", x);
+        }
+    }
+    for x in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.0] {
+        assert_eq!(default.map(x), demo_partition_map(x), "risk_score={:e}", x);
+    }
+
+    // 20 equal intervals, listed high to low; `new` sorts them.
+    let intervals = (0..20)
+        .rev()
+        .map(|i| PartitionInterval {
+            mode: MODES[i / 4],
+            lower: i as f64 / 20.0,
+            upper: (i + 1) as f64 / 20.0,
+            upper_closed: i == 19,
+        })
+        .collect();
+    let fine = PartitionMap::new(intervals).unwrap();
+    assert!(fine.intervals.windows(2).all(|w| w[0].lower < w[1].lower));
+    let linear = |x: f64| {
+        let contains = |i: &&PartitionInterval| i.lower <= x && (x < i.upper || (i.upper_closed && x == i.upper));
+        fine.intervals.iter().find(contains).map(|i| i.mode)
+    };
+    for k in 0..=1000 {
+        let x = k as f64 / 1000.0;
+        assert_eq!(fine.classify(x), linear(x), "risk_score={}", x);
+        assert_eq!(fine.map(x), fine.classify(x), "risk_score={}", x);
+    }
+    assert_eq!(fine.map(0.75), Some("Alert"));
+    assert_eq!(fine.map(1.0 + 1e-9), None);
+}
+
+// ============================================================
+// INV-C4 (opt-in): score → mode → level chain vs direct level
+// ============================================================
+
+#[test]
+fn test_risk_score_to_level_matches_mode_chain() {
+    for i in 0..=1000 {
+        let x = i as f64 / 1000.0;
+        let via_mode = demo_partition_map(x).and_then(mode_to_risk_level);
+        assert_eq!(risk_score_to_level(x), via_mode, "x={}", x);
+    }
+    assert_eq!(risk_score_to_level(-0.1), None);
+    assert_eq!(risk_score_to_level(f64::NAN), None);
+}
+
+#[test]
+fn test_inv_c4_off_by_default_and_passes_spec_example() {
+    let record = make_record(0.25, 0.70, 0.30, 0.20, 0.58, 0.42, "Caution", "L3", 1000);
+    assert!(validate_all(&record).iter().all(|r| r.id != "INV-C4"));
+    let opts = ValidationOptions { check_chain: true, ..Default::default() };
+    assert!(validate_all_with(&record, &opts).iter().find(|r| r.id == "INV-C4").unwrap().passed);
+}
+
+#[test]
+fn test_inv_c4_catches_custom_level_map_disagreement() {
+    // The custom map makes Caution → L4 pass INV-C2, but 0.42 is directly L3.
+    let levels = LevelMap::new([
+        ("Optimal", "L1"), ("Normal", "L2"), ("Caution", "L4"), ("Alert", "L3"), ("Halt", "L5"),
+    ])
+    .unwrap();
+    let opts = ValidationOptions { level_map: levels, check_chain: true, ..Default::default() };
+    let record = make_record(0.25, 0.70, 0.30, 0.20, 0.58, 0.42, "Caution", "L4", 1000);
+    let results = validate_all_with(&record, &opts);
+    assert!(results.iter().find(|r| r.id == "INV-C2").unwrap().passed);
+    let c4 = results.iter().find(|r| r.id == "INV-C4").unwrap();
+    assert!(!c4.passed);
+    assert!(c4.detail.contains("direct risk_level=Some(\"L3\")"), "{}", c4.detail);
+}
+
+// ============================================================
+// Quality checks (warnings)
+// ============================================================
+
+#[test]
+fn test_inv_q1_warns_on_identical_functions() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    assert!(validate_all(&record).iter().all(|r| r.id != "INV-Q1"));
+
+    let opts = ValidationOptions { quality_checks: true, ..Default::default() };
+    let results = validate_all_with(&record, &opts);
+    let q1 = results.iter().find(|r| r.id == "INV-Q1").unwrap();
+    assert!(!q1.passed);
+    assert!(q1.is_warning() && !q1.is_violation());
+    assert_eq!(q1.severity(), Severity::Warning);
+    assert!(is_valid_with(&record, &opts));
+    assert_eq!(violation_bitmask(&results), 0);
+
+    // -0.0 and 0.0 differ bitwise, so they are not degenerate.
+    let mixed = make_record(0.0, -0.0, 0.0, 0.0, 0.0, 1.0, "Halt", "L5", 1000);
+    assert!(validate_all_with(&mixed, &opts).iter().find(|r| r.id == "INV-Q1").unwrap().passed);
+    assert_eq!(invariant_severity("INV-C1"), Severity::Error);
+}
+
+// ============================================================
+// Consistency explanations
+// ============================================================
+
+#[test]
+fn test_explain_consistency_failure() {
+    let ok = make_record(0.5, 0.5, 0.5, 0.5, 0.65, 0.35, "Normal", "L2", 1000);
+    assert_eq!(explain_consistency_failure(&ok), None);
+
+    let severe = make_record(0.5, 0.5, 0.5, 0.5, 0.65, 0.35, "Caution", "L3", 1000);
+    assert_eq!(
+        explain_consistency_failure(&severe).unwrap(),
+        "risk_score 0.35 maps to Normal/L2, but the record says Caution/L3; the mode is one bucket too severe."
+    );
+    let lenient = make_record(0.5, 0.5, 0.5, 0.5, 0.1, 0.9, "Normal", "L4", 1000);
+    let text = explain_consistency_failure(&lenient).unwrap();
+    assert!(text.ends_with("the mode is three buckets too lenient, and risk_level L4 does not match it either."), "{}", text);
+    let level = make_record(0.5, 0.5, 0.5, 0.5, 0.65, 0.35, "Normal", "L3", 1000);
+    assert!(explain_consistency_failure(&level).unwrap().ends_with("the mode is right, but Normal belongs with L2."));
+    let unknown = make_record(0.5, 0.5, 0.5, 0.5, 0.65, 0.35, "Calm", "L2", 1000);
+    assert!(explain_consistency_failure(&unknown).unwrap().ends_with("`Calm` is not one of the five modes."));
+    let out = make_record(0.5, 0.5, 0.5, 0.5, 0.65, 1.5, "Halt", "L5", 1000);
+    assert!(explain_consistency_failure(&out).unwrap().contains("outside [0.0, 1.0]"));
+}
+
 // ============================================================
 // INV-R4: Timestamp positive
 // ============================================================
@@ -193,6 +459,119 @@ fn test_inv_c2_fail_wrong_level() {
     assert!(!results.iter().find(|r| r.id == "INV-C2").unwrap().passed);
 }
 
+const ALERT_AS_L3: &str =
+    r#"{"Optimal": "L1", "Normal": "L2", "Caution": "L3", "Alert": "L3", "Halt": "L5"}"#;
+
+#[test]
+fn test_inv_c2_custom_level_map() {
+    let opts = ValidationOptions {
+        level_map: LevelMap::from_json(ALERT_AS_L3).unwrap(),
+        ..Default::default()
+    };
+    let remapped = make_record(0.3, 0.3, 0.3, 0.3, 0.3, 0.7, "Alert", "L3", 1000);
+    let results = validate_all_with(&remapped, &opts);
+    assert!(results.iter().find(|r| r.id == "INV-C2").unwrap().passed);
+    assert!(results.iter().find(|r| r.id == "INV-C3").unwrap().passed);
+
+    let canonical = make_record(0.3, 0.3, 0.3, 0.3, 0.3, 0.7, "Alert", "L4", 1000);
+    assert!(!validate_all_with(&canonical, &opts).iter().find(|r| r.id == "INV-C2").unwrap().passed);
+    assert!(validate_all(&canonical).iter().find(|r| r.id == "INV-C2").unwrap().passed);
+}
+
+#[test]
+fn test_level_map_default_is_canonical() {
+    for mode in MODES {
+        assert_eq!(LevelMap::default().level_for(mode), mode_to_risk_level(mode));
+    }
+    assert_eq!(LevelMap::default().level_for("Unknown"), None);
+}
+
+#[test]
+fn test_level_map_rejects_incomplete_or_unknown() {
+    let missing = r#"{"Optimal": "L1", "Normal": "L2", "Caution": "L3", "Alert": "L4"}"#;
+    assert!(matches!(LevelMap::from_json(missing), Err(PmatrixError::Config(_))));
+    let bad_level = r#"{"Optimal": "L0", "Normal": "L2", "Caution": "L3", "Alert": "L4", "Halt": "L5"}"#;
+    assert!(matches!(LevelMap::from_json(bad_level), Err(PmatrixError::Config(_))));
+    assert!(matches!(LevelMap::new([("Panic", "L5")]), Err(PmatrixError::Config(_))));
+    assert!(matches!(LevelMap::new([("Halt", "L5"), ("Halt", "L5")]), Err(PmatrixError::Config(_))));
+    let twice = r#"{"Optimal": "L1", "Normal": "L2", "Caution": "L3", "Alert": "L4", "Halt": "L5", "Halt": "L4"}"#;
+    let err = LevelMap::from_json(twice).unwrap_err();
+    assert!(err.to_string().contains("mode Halt listed twice"), "{}", err);
+    assert!(matches!(LevelMap::from_json(r#"["Optimal"]"#), Err(PmatrixError::Parse(_))));
+}
+
+// ============================================================
+// Allocation-free validation into caller buffers
+// ============================================================
+
+#[test]
+fn test_validate_into_matches_validate_all() {
+    let opts = ValidationOptions { strict_floats: true, ..Default::default() };
+    let records = [
+        make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000),
+        make_record(1.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Alert", "L3", 0),
+        make_record(0.5, 0.5, 0.5, 0.5, f64::MIN_POSITIVE / 2.0, 0.5, "Caution", "L4", 1),
+        make_record(0.2, 0.2, 0.2, 0.2, 0.2, 0.8, "Halt", "L5", 1).with_sequence_number(1),
+    ];
+    let mut full: [InvariantResult; 12] = Default::default();
+    let mut summaries = [InvariantSummary::default(); 12];
+    for record in &records {
+        let expected = validate_all_with(record, &opts);
+        validate_into_with(record, &opts, &mut full);
+        validate_summary_into(record, &opts, &mut summaries);
+        for ((e, f), s) in expected.iter().zip(&full).zip(&summaries) {
+            assert_eq!((e.id, e.passed, &e.detail), (f.id, f.passed, &f.detail));
+            assert_eq!((e.id, e.passed), (s.id, s.passed));
+            assert_eq!(Some(s.summary), invariant_description(s.id));
+        }
+    }
+
+    let mut schema = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+    for version in ["1.0", "1.0.0.0", "", "1.0.x"] {
+        schema.schema_version = version.to_string();
+        validate_into(&schema, &mut full);
+        validate_summary_into(&schema, &ValidationOptions::default(), &mut summaries);
+        assert!(!full[10].passed && !summaries[10].passed, "{:?}", version);
+    }
+}
+
+// ============================================================
+// validate_consistency / validate_structural: C- and S-series subsets
+// ============================================================
+
+#[test]
+fn test_validate_consistency_matches_full_results() {
+    let record = make_record(0.5, 0.5, 0.5, 0.5, 0.65, 0.35, "Caution", "L3", 0);
+    let subset = validate_consistency(&record);
+    let ids: Vec<&str> = subset.iter().map(|r| r.id).collect();
+    assert_eq!(ids, vec!["INV-C1", "INV-C2", "INV-C3"]);
+
+    let full = validate_all(&record);
+    for r in &subset {
+        let f = full.iter().find(|f| f.id == r.id).unwrap();
+        assert_eq!((r.passed, &r.detail), (f.passed, &f.detail));
+    }
+    // INV-R4 fails (timestamp 0) but is not part of the subset.
+    assert!(!subset[0].passed && subset[1].passed && !subset[2].passed);
+}
+
+#[test]
+fn test_validate_structural_matches_full_results() {
+    let mut record = make_record(2.0, 0.5, 0.5, 0.5, 0.65, 0.35, "Halt", "L1", 0);
+    record.schema_version = "1.0".to_string();
+    let subset = validate_structural(&record);
+    let ids: Vec<&str> = subset.iter().map(|r| r.id).collect();
+    assert_eq!(ids, vec!["INV-S1", "INV-S2", "INV-S3", "INV-S4"]);
+
+    let full = validate_all(&record);
+    for r in &subset {
+        let f = full.iter().find(|f| f.id == r.id).unwrap();
+        assert_eq!((r.passed, &r.detail), (f.passed, &f.detail));
+    }
+    // Range and consistency failures are not part of the subset; INV-S4 is.
+    assert!(subset[0].passed && subset[1].passed && subset[2].passed && !subset[3].passed);
+}
+
 // ============================================================
 // INV-S3: spec_version must be "pmatrix-3.5"
 // ============================================================
@@ -217,8 +596,89 @@ fn test_inv_s4_fail_invalid_semver() {
     assert!(!results.iter().find(|r| r.id == "INV-S4").unwrap().passed);
 }
 
+#[test]
+fn test_inv_s4_component_rules() {
+    let cases = [
+        ("1.0.0", None),
+        ("0.10.200", None),
+        ("01.0.0", Some("component `01` has a leading zero")),
+        ("1.0", Some("has 2 component(s), expected 3")),
+        ("1.0.0.0", Some("has 4 component(s), expected 3")),
+        ("1..0", Some("has an empty component")),
+        ("1.0.x", Some("component `x` is not numeric")),
+        ("1.+0.0", Some("component `+0` is not numeric")),
+        ("99999999999.0.0", Some("component `99999999999` exceeds 4294967295")),
+    ];
+    for (version, reason) in cases {
+        let mut record = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+        record.schema_version = version.to_string();
+        let s4 = validate_all(&record).into_iter().find(|r| r.id == "INV-S4").unwrap();
+        assert_eq!(s4.passed, reason.is_none(), "{}", version);
+        if let Some(reason) = reason {
+            assert!(s4.detail.ends_with(reason), "{}: {}", version, s4.detail);
+        }
+    }
+}
+
 // ============================================================
-// INV-T1: Stream-level temporal monotonicity
+// INV-S6: schema_version within an accepted range (opt-in)
+// ============================================================
+
+#[test]
+fn test_schema_version_range() {
+    let range = SchemaVersionRange::parse(">=1.0.0, <2.0.0").unwrap();
+    for (version, inside) in [("1.0.0", true), ("1.9.12", true), ("0.9.9", false), ("2.0.0", false), ("1.0", false)] {
+        assert_eq!(range.contains(version), inside, "{}", version);
+    }
+    assert_eq!(range.to_string(), ">=1.0.0, <2.0.0");
+    assert_eq!(SchemaVersionRange::parse(" 1.1.0 ").unwrap().to_string(), "=1.1.0");
+    assert!(SchemaVersionRange::parse("> 1.0.0,<=1.1.0").unwrap().contains("1.1.0"));
+    for bad in ["", ">=1.0.0,", "^1.0.0", ">=1.0", "=01.0.0"] {
+        assert!(matches!(SchemaVersionRange::parse(bad), Err(PmatrixError::Config(_))), "{}", bad);
+    }
+}
+
+#[test]
+fn test_inv_s6_opt_in() {
+    let mut record = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+    assert!(validate_all(&record).iter().all(|r| r.id != "INV-S6"));
+
+    let opts = ValidationOptions {
+        schema_version_range: Some(SchemaVersionRange::parse(">1.0.0").unwrap()),
+        ..Default::default()
+    };
+    let s6 = validate_all_with(&record, &opts).into_iter().find(|r| r.id == "INV-S6").unwrap();
+    assert!(!s6.passed);
+    assert_eq!(s6.code, Some(ErrorCode::SchemaVersionOutOfRange));
+    assert_eq!(s6.detail, "schema_version=1.0.0, accepted=>1.0.0");
+    assert_eq!(violation_bitmask(&[s6]), 1 << 14);
+    assert_eq!(validate_structural_with(&record, &opts).last().unwrap().id, "INV-S6");
+
+    record.schema_version = SEQUENCE_SCHEMA_VERSION.to_string();
+    assert!(is_valid_with(&record, &opts));
+}
+
+#[test]
+fn test_inv_c5_opt_in() {
+    let record = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+    assert!(validate_all(&record).iter().all(|r| r.id != "INV-C5"));
+
+    let mut opts = ValidationOptions { recompute_classification: true, ..Default::default() };
+    let c5 = validate_all_with(&record, &opts).into_iter().find(|r| r.id == "INV-C5").unwrap();
+    assert!(c5.passed && c5.code.is_none());
+
+    opts.partition_map = builtin_profile("conservative").unwrap();
+    let c5 = validate_all_with(&record, &opts).into_iter().find(|r| r.id == "INV-C5").unwrap();
+    assert!(c5.is_violation());
+    assert_eq!(c5.code, Some(ErrorCode::StaleClassification));
+    assert!(c5.detail.starts_with("Stale classification: risk_score 0.5 maps to Alert/L4"), "{}", c5.detail);
+    assert_eq!(violation_bitmask(&[c5]), 1 << 15);
+
+    // A score that maps to no mode is INV-R3's failure, not INV-C5's.
+    let out_of_range = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 1.5, "Caution", "L3", 1000);
+    assert!(validate_all_with(&out_of_range, &opts).iter().any(|r| r.id == "INV-C5" && r.passed));
+}
+
 // ============================================================
 
 #[test]
@@ -240,6 +700,65 @@ fn test_inv_t1_fail_decreasing() {
     assert_eq!(validate_stream_t1(&records), Some(1));
 }
 
+#[test]
+fn test_strict_monotonic_rejects_duplicates() {
+    let a = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+    let b = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+    let c = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1001);
+    let stream = vec![a, b, c];
+    assert_eq!(validate_stream_t1(&stream), None);
+    assert_eq!(validate_strict_monotonic(&stream), Some(1));
+    assert_eq!(validate_strict_monotonic(&stream[1..]), None);
+
+    assert!(check_inv_t2(None, &stream[0]).passed);
+    let t2 = check_inv_t2(Some(&stream[0]), &stream[1]);
+    assert!(t2.is_violation());
+    assert_eq!(t2.code, Some(ErrorCode::TimestampNotIncreasing));
+    assert_eq!(t2.detail, "timestamp=1000, previous=1000");
+    assert_eq!(violation_bitmask(&[t2]), 1 << 16);
+    assert!(check_inv_t2(Some(&stream[1]), &stream[2]).passed);
+}
+
+// ============================================================
+// sequence_number (schema 1.1.0)
+// ============================================================
+
+fn numbered(seq: Option<u64>) -> RuntimeStateRecord {
+    let mut r = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+    r.schema_version = SEQUENCE_SCHEMA_VERSION.to_string();
+    r.sequence_number = seq;
+    r
+}
+
+#[test]
+fn test_validate_sequence_flags_gaps_and_repeats() {
+    let stream: Vec<_> = [Some(1), Some(2), None, Some(3), Some(5), Some(5), Some(6)]
+        .into_iter()
+        .map(numbered)
+        .collect();
+    assert_eq!(validate_sequence(&stream), vec![4, 5]);
+    assert!(validate_sequence(&[numbered(None), numbered(None)]).is_empty());
+}
+
+#[test]
+fn test_sequence_number_gated_by_schema_version() {
+    assert!(is_valid(&numbered(Some(7))));
+    let old = make_record(0.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000).with_sequence_number(7);
+    let results = validate_all(&old);
+    assert!(!results.iter().find(|r| r.id == "INV-S2").unwrap().passed);
+    assert!(supports_sequence_number("2.0.0"));
+    assert!(!supports_sequence_number("1.0.9"));
+    assert!(!supports_sequence_number("1.x.0"));
+}
+
+#[test]
+fn test_sequence_number_omitted_when_absent() {
+    let json = serde_json::to_string(&emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1)).unwrap()).unwrap();
+    assert!(!json.contains("sequence_number"));
+    let parsed = parse_strict(&serde_json::to_string(&numbered(Some(3))).unwrap()).unwrap();
+    assert_eq!(parsed.sequence_number, Some(3));
+}
+
 // ============================================================
 // emit_demo_record integration
 // ============================================================
@@ -285,6 +804,25 @@ fn test_emit_demo_record_reject_infinite() {
     assert!(emit_demo_record(f64::NEG_INFINITY, 0.5, 0.5, 0.5, Some(1000)).is_err());
 }
 
+#[test]
+fn test_emit_from_named_inputs() {
+    let functions = Functions { baseline: 0.25, norm: 0.70, stability: 0.30, meta_control: 0.20 };
+    let record = emit_demo_record_from(EmitInputs { functions: functions.clone(), timestamp: Some(1000) }).unwrap();
+    assert_eq!(record, emit_demo_record(0.25, 0.70, 0.30, 0.20, Some(1000)).unwrap());
+    assert_eq!(record.functions, functions);
+    let bad = Functions { norm: 1.5, ..functions };
+    assert!(emit_demo_record_from(EmitInputs { functions: bad, timestamp: Some(1000) }).is_err());
+}
+
+#[test]
+fn test_emit_default_timestamp_from_clock() {
+    let clock = FixedClock(1_707_500_000);
+    let record = emit_demo_record_with_clock(0.5, 0.5, 0.5, 0.5, None, &clock).unwrap();
+    assert_eq!(record.timestamp, 1_707_500_000);
+    let explicit = emit_demo_record_with_clock(0.5, 0.5, 0.5, 0.5, Some(42), &clock).unwrap();
+    assert_eq!(explicit.timestamp, 42);
+}
+
 // ============================================================
 // Demo score logic (simple arithmetic — NOT kernel logic)
 // ============================================================
@@ -301,11 +839,232 @@ fn test_demo_stability_score() {
     assert!((s - 0.3625).abs() < 1e-10);
 }
 
+#[test]
+fn test_functions_summary_accessors() {
+    let f = Functions { baseline: 0.25, norm: 0.70, stability: 0.30, meta_control: 0.20 };
+    assert_eq!(f.mean(), demo_stability_score(&f));
+    assert_eq!((f.min(), f.max()), (0.20, 0.70));
+    assert!((f.range() - 0.5).abs() < 1e-12);
+    let flat = Functions { baseline: 0.5, norm: 0.5, stability: 0.5, meta_control: 0.5 };
+    assert_eq!(flat.range(), 0.0);
+    let partial = Functions { norm: f64::NAN, ..f };
+    assert_eq!((partial.min(), partial.max()), (0.20, 0.30));
+    assert!(partial.mean().is_nan());
+}
+
+#[test]
+fn test_record_hash_and_eq_use_canonical_bits() {
+    use std::collections::HashSet;
+
+    let record = emit_demo_record(0.0, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    let mut negative_zero = record.clone();
+    negative_zero.functions.baseline = -0.0;
+    let mut nan = record.clone();
+    nan.risk_score = f64::NAN;
+    let mut other_nan = record.clone();
+    other_nan.risk_score = f64::from_bits(f64::NAN.to_bits() | 1);
+    assert_eq!(negative_zero, record);
+    assert_eq!(nan, other_nan);
+
+    let later = record.clone().with_timestamp(1001);
+    let set: HashSet<_> = [record.clone(), negative_zero, nan, other_nan, later].into_iter().collect();
+    assert_eq!(set.len(), 3);
+    assert!(set.contains(&record));
+    assert_eq!(canonical_bits(-0.0), canonical_bits(0.0));
+    assert_ne!(canonical_bits(1.0), canonical_bits(-1.0));
+}
+
+#[test]
+fn test_functions_array_conversions() {
+    let f = Functions::from([0.25, 0.70, 0.30, 0.20]);
+    assert_eq!(f, Functions { baseline: 0.25, norm: 0.70, stability: 0.30, meta_control: 0.20 });
+    let values: [f64; 4] = f.into();
+    assert_eq!(values, [0.25, 0.70, 0.30, 0.20]);
+}
+
 #[test]
 fn test_demo_risk_score() {
     assert!((demo_risk_score(0.3625) - 0.6375).abs() < 1e-10);
 }
 
+#[test]
+fn test_demo_scores_clamped_against_drift() {
+    // Values a hair above 1.0 (as produced by upstream float noise) must not
+    // push the derived scores outside [0.0, 1.0].
+    let hair = 1.0 + f64::EPSILON;
+    let f = Functions { baseline: hair, norm: hair, stability: hair, meta_control: hair };
+    assert_eq!(demo_stability_score(&f), 1.0);
+    assert_eq!(demo_risk_score(hair), 0.0);
+    assert_eq!(demo_risk_score(-f64::EPSILON), 1.0);
+}
+
+#[test]
+fn test_emit_boundary_inputs_satisfy_r2_r3() {
+    let below_one = 1.0 - f64::EPSILON;
+    for v in [0.0, f64::MIN_POSITIVE, 0.1, 0.2, 0.7, below_one, 1.0] {
+        let record = emit_demo_record(v, v, 1.0, below_one, Some(1000)).unwrap();
+        let results = validate_all(&record);
+        assert!(results.iter().find(|r| r.id == "INV-R2").unwrap().passed);
+        assert!(results.iter().find(|r| r.id == "INV-R3").unwrap().passed);
+    }
+}
+
+// Pins classification at the representable neighbors of each boundary.
+#[test]
+fn test_partition_map_exact_at_ulp_neighbors() {
+    let below = |x: f64| f64::from_bits(x.to_bits() - 1);
+    let above = |x: f64| f64::from_bits(x.to_bits() + 1);
+    for (i, &b) in DEFAULT_BOUNDARIES.iter().enumerate() {
+        assert_eq!(partition_map_exact(below(b)), Some(MODES[i]), "{:?}", below(b));
+        assert_eq!(partition_map_exact(b), Some(MODES[i + 1]), "{:?}", b);
+        assert_eq!(partition_map_exact(above(b)), Some(MODES[i + 1]), "{:?}", above(b));
+    }
+    assert_eq!(below(0.2), 0.19999999999999998);
+    assert_eq!(partition_map_exact(0.0), Some("Optimal"));
+    assert_eq!(partition_map_exact(1.0), Some("Halt"));
+    assert_eq!(partition_map_exact(above(1.0)), None);
+    assert_eq!(partition_map_exact(-0.0), Some("Optimal"));
+
+    // Demo arithmetic falls just short of the boundary.
+    let risk = demo_risk_score(0.8);
+    assert_eq!(risk, 0.19999999999999996);
+    assert_eq!(partition_map_exact(risk), Some("Optimal"));
+    assert_eq!(partition_map_tolerant(risk, BOUNDARY_EPS), Some("Normal"));
+}
+
+#[test]
+fn test_partition_map_tolerant() {
+    for x in [0.0, 0.1, 0.2, 0.39, 0.4, 0.6, 0.8, 1.0, -0.1, 1.1] {
+        assert_eq!(partition_map_tolerant(x, 0.0), partition_map_exact(x), "{}", x);
+    }
+    assert_eq!(partition_map_tolerant(0.7999999999999999, BOUNDARY_EPS), Some("Halt"));
+    assert_eq!(partition_map_tolerant(0.79, BOUNDARY_EPS), Some("Alert"));
+    assert_eq!(partition_map_tolerant(1.0 + 1e-13, BOUNDARY_EPS), None);
+}
+
+#[test]
+fn test_classify_rounded_and_classify_precision() {
+    assert_eq!(round_to_decimals(0.5999999999, 4), 0.6);
+    assert_eq!(round_to_decimals(0.123456, 20), 0.123456);
+    assert_eq!(classify_rounded(0.5999999999, 4), Some("Alert"));
+    assert_eq!(classify_rounded(0.5999999999, 10), Some("Caution"));
+    assert_eq!(classify_rounded(1.00004, 4), Some("Halt"));
+
+    let record = make_record(0.5, 0.5, 0.5, 0.5, 0.6, 0.5999999999, "Alert", "L4", 1000);
+    assert!(!is_valid(&record));
+    let opts = ValidationOptions { classify_precision: Some(4), check_chain: true, ..Default::default() };
+    let results = validate_all_with(&record, &opts);
+    assert!(results.iter().all(|r| r.passed), "{:?}", results);
+    let c1 = results.iter().find(|r| r.id == "INV-C1").unwrap();
+    assert!(c1.detail.contains("rounded to 4 decimals: 0.6"), "{}", c1.detail);
+}
+
+#[test]
+fn test_partition_fast_path_matches_reference() {
+    let n = 1_000_000;
+    for k in 0..=n {
+        let x = k as f64 / n as f64;
+        assert_eq!(demo_partition_map(x), reference_partition_map(x), "risk_score={}", x);
+    }
+    for t in [0.0, 0.2, 0.4, 0.6, 0.8, 1.0] {
+        let mut x = t;
+        let mut y = t;
+        for _ in 0..64 {
+            assert_eq!(demo_partition_map(x), reference_partition_map(x), "risk_score={:e}", x);
+            assert_eq!(demo_partition_map(y), reference_partition_map(y), "risk_score={:e}", y);
+            x = x.next_down();
+            y = y.next_up();
+        }
+    }
+    for x in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY, -0.0, f64::MIN_POSITIVE] {
+        assert_eq!(demo_partition_map(x), reference_partition_map(x), "risk_score={:e}", x);
+    }
+}
+
+// ============================================================
+// remediation_hint and dominant_risk_factor (demo-only)
+// ============================================================
+
+#[test]
+fn test_remediation_hint_exit_halt() {
+    // mean 0.1 → risk 0.9 (Halt); Alert needs risk < 0.8, i.e. mean > 0.2.
+    let record = emit_demo_record(0.1, 0.1, 0.1, 0.1, Some(1)).unwrap();
+    let hint = remediation_hint(&record, "Alert").unwrap();
+    assert_eq!(hint.target, "Alert");
+    assert!((hint.mean_increase - 0.1).abs() < 1e-9);
+    assert!((hint.per_function_increase - 0.1).abs() < 1e-9);
+
+    let d = hint.per_function_increase + 1e-6;
+    let improved = emit_demo_record(0.1 + d, 0.1 + d, 0.1 + d, 0.1 + d, Some(2)).unwrap();
+    assert_eq!(improved.mode, "Alert");
+}
+
+#[test]
+fn test_remediation_hint_accounts_for_saturation() {
+    // mean 0.35; Optimal needs mean > 0.8. With one function already at 1.0
+    // the other three must each rise by (3.2 - 1.0 - 0.4) / 3 = 0.6.
+    let record = emit_demo_record(1.0, 0.0, 0.2, 0.2, Some(1)).unwrap();
+    let hint = remediation_hint(&record, "Optimal").unwrap();
+    assert!((hint.mean_increase - 0.45).abs() < 1e-9);
+    assert!((hint.per_function_increase - 0.6).abs() < 1e-9);
+}
+
+#[test]
+fn test_remediation_hint_none_when_already_there() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1)).unwrap();
+    assert_eq!(remediation_hint(&record, "Alert"), None);
+    assert_eq!(remediation_hint(&record, "Caution"), None);
+    assert_eq!(remediation_hint(&record, "Halt"), None);
+    assert_eq!(remediation_hint(&record, "Unknown"), None);
+    assert!(remediation_hint(&record, "Normal").is_some());
+}
+
+#[test]
+fn test_dominant_risk_factor() {
+    let f = Functions { baseline: 0.9, norm: 0.8, stability: 0.3, meta_control: 0.7 };
+    assert_eq!(dominant_risk_factor(&f), ("stability", 0.3));
+    // Ties go to the first field; NaN is skipped.
+    let f = Functions { baseline: f64::NAN, norm: 0.4, stability: 0.9, meta_control: 0.4 };
+    assert_eq!(dominant_risk_factor(&f), ("norm", 0.4));
+}
+
+// ============================================================
+// Score aggregators (demo-only)
+// ============================================================
+
+#[test]
+fn test_aggregators() {
+    let f = Functions { baseline: 0.2, norm: 0.8, stability: 0.8, meta_control: 0.8 };
+    let score = |name: &str, weights: Option<&[f64]>| aggregator(name, weights).unwrap().stability_score(&f);
+    assert_eq!(score("mean", None), demo_stability_score(&f));
+    assert_eq!(score("min", None), 0.2);
+    assert!((score("geometric", None) - (0.2f64 * 0.512).powf(0.25)).abs() < 1e-12);
+    assert!((score("weighted", Some(&[2.0, 1.0, 1.0, 1.0])) - 2.8 / 5.0).abs() < 1e-12);
+    for (name, _) in AGGREGATORS {
+        let weights = (name == "weighted").then_some(&[1.0, 0.0, 3.0, 1.0][..]);
+        assert_eq!(aggregator(name, weights).unwrap().name(), name);
+        // Output stays in [0.0, 1.0] across the input grid.
+        for i in 0..=10 {
+            let v = i as f64 / 10.0;
+            let f = Functions { baseline: v, norm: 1.0 - v, stability: v, meta_control: 1.0 };
+            assert!((0.0..=1.0).contains(&aggregator(name, weights).unwrap().stability_score(&f)), "{} at {}", name, v);
+        }
+    }
+
+    let err = |name: &str, weights: Option<&[f64]>| aggregator(name, weights).err().unwrap().to_string();
+    assert!(err("median", None).contains("unknown aggregator `median`"));
+    assert!(err("weighted", None).contains("requires weights"));
+    assert!(err("weighted", Some(&[1.0, 2.0])).contains("takes 4 weights"));
+    assert!(err("weighted", Some(&[0.0; 4])).contains("at least one weight must be positive"));
+    assert!(err("weighted", Some(&[1.0, -1.0, 1.0, 1.0])).contains("non-negative"));
+    assert!(err("min", Some(&[1.0; 4])).contains("takes no weights"));
+
+    let inputs = EmitInputs { functions: f, timestamp: Some(1000) };
+    let record = emit_demo_record_aggregated(inputs, &MinAggregator).unwrap();
+    assert_eq!((record.stability_score, record.mode.as_str()), (0.2, "Halt"));
+    assert!(is_record_valid(&record));
+}
+
 // ============================================================
 // demo_partition_map edge cases
 // ============================================================
@@ -325,6 +1084,96 @@ fn test_partition_map_rejects_nan() {
     assert_eq!(demo_partition_map(f64::NAN), None);
 }
 
+// ============================================================
+// PartitionMap coverage
+// ============================================================
+
+fn interval(mode: &'static str, lower: f64, upper: f64) -> PartitionInterval {
+    PartitionInterval { mode, lower, upper, upper_closed: mode == "Halt" }
+}
+
+#[test]
+fn test_partition_map_default_matches_demo_partition() {
+    let map = PartitionMap::default();
+    assert_eq!(validate_partition_coverage(&map), Ok(()));
+    for x in [0.0, 0.19, 0.2, 0.4, 0.6, 0.79, 0.8, 1.0, -0.1, 1.1] {
+        assert_eq!(map.classify(x), demo_partition_map(x), "risk_score={}", x);
+    }
+}
+
+#[test]
+fn test_default_boundaries_constants() {
+    // Usable in const context.
+    const HALT_FROM: f64 = DEFAULT_BOUNDARIES[3];
+    assert_eq!(HALT_FROM, 0.8);
+    assert_eq!(MODE_FOR_BUCKET, MODES);
+    for (i, &b) in DEFAULT_BOUNDARIES.iter().enumerate() {
+        assert_eq!(demo_partition_map(b), Some(MODE_FOR_BUCKET[i + 1]));
+        assert_eq!(mode_interval(MODE_FOR_BUCKET[i]).unwrap().1, b);
+    }
+    assert_eq!(PartitionMap::from_thresholds(DEFAULT_BOUNDARIES).unwrap(), PartitionMap::default());
+}
+
+#[test]
+fn test_partition_map_accepts_custom_boundaries() {
+    let map = PartitionMap::new(vec![
+        interval("Halt", 0.9, 1.0),
+        interval("Optimal", 0.0, 0.1),
+        interval("Normal", 0.1, 0.5),
+        interval("Caution", 0.5, 0.7),
+        interval("Alert", 0.7, 0.9),
+    ])
+    .unwrap();
+    assert_eq!(map.classify(0.85), Some("Alert"));
+    assert_eq!(map.classify(1.0), Some("Halt"));
+}
+
+#[test]
+fn test_partition_map_rejects_gaps_and_overlaps() {
+    let mut map = PartitionMap::default();
+    map.intervals[1].upper = 0.35;
+    assert_eq!(validate_partition_coverage(&map), Err(CoverageError::Gap { from: 0.35, to: 0.4 }));
+    map.intervals[1].upper = 0.45;
+    assert_eq!(validate_partition_coverage(&map), Err(CoverageError::Overlap { at: 0.4 }));
+}
+
+#[test]
+fn test_partition_map_rejects_unsorted_intervals() {
+    // `map` binary-searches the stored order, so a reordered map must not pass.
+    let mut map = PartitionMap::default();
+    map.intervals.swap(1, 3);
+    let err = validate_partition_coverage(&map).unwrap_err();
+    assert_eq!(err, CoverageError::Unsorted { lower: 0.4, after: 0.6 });
+    assert_eq!(err.to_string(), "interval starting at 0.4 is listed after one starting at 0.6");
+    // The constructor sorts the same intervals first.
+    assert_eq!(PartitionMap::new(map.intervals).unwrap(), PartitionMap::default());
+}
+
+#[test]
+fn test_partition_map_rejects_bad_endpoints() {
+    let mut map = PartitionMap::default();
+    map.intervals[4].upper_closed = false;
+    assert_eq!(validate_partition_coverage(&map), Err(CoverageError::OpenAtOne));
+    map.intervals[4].upper_closed = true;
+    map.intervals[2].upper_closed = true;
+    assert_eq!(validate_partition_coverage(&map), Err(CoverageError::ClosedInterior { at: 0.6 }));
+    map.intervals[2].upper_closed = false;
+    map.intervals[0].lower = 0.05;
+    assert_eq!(validate_partition_coverage(&map), Err(CoverageError::StartsAbove { lower: 0.05 }));
+    assert!(PartitionMap::new(Vec::new()).is_err());
+}
+
+#[test]
+fn test_inv_c1_custom_partition_map() {
+    let mut partition_map = PartitionMap::default();
+    partition_map.intervals[2].upper = 0.7;
+    partition_map.intervals[3].lower = 0.7;
+    let opts = ValidationOptions { partition_map, ..Default::default() };
+    let record = make_record(0.35, 0.35, 0.35, 0.35, 0.35, 0.65, "Caution", "L3", 1000);
+    assert!(validate_all_with(&record, &opts).iter().all(|r| r.passed));
+    assert!(!is_valid(&record));
+}
+
 // ============================================================
 // mode_to_risk_level
 // ============================================================
@@ -339,6 +1188,91 @@ fn test_mode_to_risk_level_all() {
     assert_eq!(mode_to_risk_level("Unknown"), None);
 }
 
+// ============================================================
+// Fluent mutators
+// ============================================================
+
+#[test]
+fn test_with_timestamp_is_raw() {
+    let record = emit_demo_record(0.25, 0.70, 0.30, 0.20, Some(1000)).unwrap();
+    let bumped = record.clone().with_timestamp(2000);
+    assert_eq!(bumped.timestamp, 2000);
+    assert_eq!(bumped.clone().with_timestamp(1000), record);
+}
+
+#[test]
+fn test_with_functions_rederives() {
+    let record = emit_demo_record(1.0, 1.0, 1.0, 1.0, Some(1000)).unwrap();
+    let zeros = Functions { baseline: 0.0, norm: 0.0, stability: 0.0, meta_control: 0.0 };
+    let updated = record.with_functions(zeros).unwrap();
+    assert_eq!(updated.timestamp, 1000);
+    assert_eq!(updated.mode, "Halt");
+    assert_eq!(updated.risk_level, "L5");
+    assert!(is_record_valid(&updated));
+}
+
+#[test]
+fn test_normalize_record_rederives_only_derived_fields() {
+    let mut record = make_record(0.5, 0.5, 0.5, 0.5, 0.9, 0.1, "Optimal", "L1", 1000);
+    record.schema_version = SEQUENCE_SCHEMA_VERSION.to_string();
+    record.sequence_number = Some(4);
+    let normalized = normalize_record(&record).unwrap();
+    assert_eq!(normalized.mode, "Caution");
+    assert_eq!(normalized.risk_level, "L3");
+    assert_eq!(normalized.risk_score, 0.5);
+    assert_eq!(normalized.sequence_number, Some(4));
+    assert_eq!(normalized.schema_version, SEQUENCE_SCHEMA_VERSION);
+    assert_eq!(normalize_record(&normalized).unwrap(), normalized);
+}
+
+#[test]
+fn test_replay_drift_reports_only_derived_fields() {
+    // Internally consistent (risk 0.45 → Caution, L3) but not the demo mean.
+    let record = make_record(0.5, 0.5, 0.5, 0.5, 0.55, 0.45, "Caution", "L3", 1000);
+    assert!(is_record_valid(&record));
+    let drift = replay_drift(&record, 1e-9).unwrap();
+    let fields: Vec<&str> = drift.iter().map(|d| d.field).collect();
+    assert_eq!(fields, vec!["stability_score", "risk_score"]);
+    assert_eq!((drift[1].left.as_str(), drift[1].right.as_str()), ("0.45", "0.5"));
+    assert!(replay_drift(&record, 0.1).unwrap().is_empty());
+
+    let emitted = emit_demo_record(0.2, 0.4, 0.6, 0.8, Some(1000)).unwrap();
+    assert!(replay_drift(&emitted, 0.0).unwrap().is_empty());
+    let out_of_range = make_record(1.5, 0.5, 0.5, 0.5, 0.5, 0.5, "Caution", "L3", 1000);
+    assert!(replay_drift(&out_of_range, 1e-9).is_err());
+}
+
+#[test]
+fn test_first_nonconforming() {
+    let mut records: Vec<_> = (0..6).map(|i| emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000 + i)).unwrap()).collect();
+    assert_eq!(first_nonconforming(&records), None);
+    assert_eq!(first_nonconforming(&[]), None);
+    // Not monotonic: #4 fails, #5 conforms again, then #2 fails first.
+    records[4].mode = "Halt".to_string();
+    assert_eq!(first_nonconforming(&records), Some(4));
+    records[2].risk_level = "L1".to_string();
+    assert_eq!(first_nonconforming(&records), Some(2));
+    // INV-T1 is not a per-record invariant.
+    records[1].timestamp = 1;
+    assert_eq!(first_nonconforming(&records), Some(2));
+}
+
+#[test]
+fn test_with_functions_rejects_invalid() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    let bad = Functions { baseline: f64::NAN, norm: 0.5, stability: 0.5, meta_control: 0.5 };
+    assert!(record.with_functions(bad).is_err());
+}
+
+#[test]
+fn test_with_raw_functions_does_not_rederive() {
+    let record = emit_demo_record(1.0, 1.0, 1.0, 1.0, Some(1000)).unwrap();
+    let zeros = Functions { baseline: 0.0, norm: 0.0, stability: 0.0, meta_control: 0.0 };
+    let raw = record.with_raw_functions(zeros);
+    assert_eq!(raw.mode, "Optimal");
+    assert!(is_record_valid(&raw)); // demo aggregation is not an invariant
+}
+
 // ============================================================
 // JSON round-trip
 // ============================================================
@@ -352,6 +1286,32 @@ fn test_json_roundtrip() {
     assert!(is_record_valid(&parsed));
 }
 
+#[test]
+fn test_validate_json() {
+    let record = emit_demo_record(0.50, 0.60, 0.40, 0.30, Some(1707500000)).unwrap();
+    let results = validate_json(&serde_json::to_string(&record).unwrap()).unwrap();
+    assert_eq!(results.len(), 12);
+    assert!(results.iter().all(|r| r.passed));
+
+    assert!(matches!(validate_json("[]"), Err(PmatrixError::Parse(_))));
+    let zero_ts = serde_json::to_string(&record.with_timestamp(0)).unwrap();
+    let results = validate_json(&zero_ts).unwrap();
+    assert!(!results.iter().find(|r| r.id == "INV-R4").unwrap().passed);
+}
+
+#[test]
+fn test_validate_value_matches_validate_json() {
+    let record = emit_demo_record(0.50, 0.60, 0.40, 0.30, Some(1707500000)).unwrap();
+    let mut value = serde_json::to_value(&record).unwrap();
+    let json = serde_json::to_string(&record).unwrap();
+    let key = |rs: Vec<InvariantResult>| rs.into_iter().map(|r| (r.id, r.passed, r.detail)).collect::<Vec<_>>();
+    assert_eq!(key(validate_value(&value).unwrap()), key(validate_json(&json).unwrap()));
+
+    value["functions"]["extra"] = serde_json::json!(1);
+    assert!(matches!(validate_value(&value), Err(PmatrixError::Parse(_))));
+    assert!(matches!(validate_value(&serde_json::json!([])), Err(PmatrixError::Parse(_))));
+}
+
 #[test]
 fn test_json_reject_extra_fields() {
     let json = r#"{
@@ -374,3 +1334,143 @@ fn test_json_reject_extra_fields() {
     let result: Result<RuntimeStateRecord, _> = serde_json::from_str(json);
     assert!(result.is_err());
 }
+
+// ============================================================
+// PmatrixError
+// ============================================================
+
+#[test]
+fn test_emit_error_is_encode_variant() {
+    let err = emit_demo_record(0.5, 1.5, 0.5, 0.5, Some(1000)).unwrap_err();
+    assert!(matches!(err, PmatrixError::Encode(_)));
+    assert_eq!(err.to_string(), "norm = 1.5 is outside [0.0, 1.0]");
+}
+
+#[test]
+fn test_parse_error_converts_with_question_mark() {
+    fn parse(input: &str) -> Result<RuntimeStateRecord, PmatrixError> {
+        Ok(serde_json::from_str(input)?)
+    }
+    let err = parse("{ not json").unwrap_err();
+    assert!(matches!(err, PmatrixError::Parse(_)));
+    assert!(std::error::Error::source(&err).is_some());
+}
+
+// ============================================================
+// Strict vs lenient parsing
+// ============================================================
+
+const EXTRA_FIELDS_JSON: &str = r#"{
+    "spec_version": "pmatrix-3.5",
+    "schema_version": "1.0.0",
+    "timestamp": 1707500000,
+    "functions": {
+        "baseline": 0.25, "norm": 0.70, "stability": 0.30, "meta_control": 0.20,
+        "latency": 0.1
+    },
+    "stability_score": 0.58,
+    "risk_score": 0.42,
+    "mode": "Caution",
+    "risk_level": "L3",
+    "emitter_id": "agent-7"
+}"#;
+
+#[test]
+fn test_parse_strict_rejects_extra_fields() {
+    assert!(matches!(parse_strict(EXTRA_FIELDS_JSON), Err(PmatrixError::Parse(_))));
+}
+
+#[test]
+fn test_parse_lenient_reports_ignored_fields() {
+    let (record, ignored) = parse_lenient(EXTRA_FIELDS_JSON).unwrap();
+    assert_eq!(ignored, vec!["emitter_id".to_string(), "functions.latency".to_string()]);
+    assert_eq!(record.mode, "Caution");
+    assert!(is_record_valid(&record));
+}
+
+#[test]
+fn test_parse_lenient_still_requires_fields() {
+    assert!(parse_lenient(r#"{"spec_version": "pmatrix-3.5"}"#).is_err());
+}
+
+// ============================================================
+// collect_stream
+// ============================================================
+
+#[test]
+fn test_collect_stream_emits_in_order() {
+    let records = collect_stream((1..=3).map(|t| (0.5, 0.5, 0.5, 0.5, t * 10))).unwrap();
+    assert_eq!(records.iter().map(|r| r.timestamp).collect::<Vec<_>>(), vec![10, 20, 30]);
+    assert!(records.iter().all(is_record_valid));
+}
+
+#[test]
+fn test_collect_stream_reports_encode_index() {
+    let input = vec![(0.5, 0.5, 0.5, 0.5, 1), (0.5, 1.5, 0.5, 0.5, 2)];
+    let (index, err) = collect_stream(input).unwrap_err();
+    assert_eq!(index, 1);
+    assert!(matches!(err, PmatrixError::Encode(_)));
+}
+
+#[test]
+fn test_collect_stream_reports_t1_index() {
+    let input = vec![(0.5, 0.5, 0.5, 0.5, 5), (0.5, 0.5, 0.5, 0.5, 5), (0.5, 0.5, 0.5, 0.5, 4)];
+    let (index, err) = collect_stream(input).unwrap_err();
+    assert_eq!(index, 2);
+    assert!(matches!(err, PmatrixError::Validation(ids) if ids == vec!["INV-T1"]));
+}
+
+// ============================================================
+// Function key aliases
+// ============================================================
+
+fn with_meta_key(key: &str) -> String {
+    EXTRA_FIELDS_JSON
+        .replace(",\n        \"latency\": 0.1", "")
+        .replace(",\n    \"emitter_id\": \"agent-7\"", "")
+        .replace("\"meta_control\"", &format!("\"{}\"", key))
+}
+
+#[test]
+fn test_meta_control_aliases_accepted() {
+    for key in std::iter::once("meta_control").chain(META_CONTROL_ALIASES) {
+        let record = parse_strict(&with_meta_key(key)).unwrap();
+        assert_eq!(record.functions.meta_control, 0.20, "{}", key);
+        let (lenient, ignored) = parse_lenient(&with_meta_key(key)).unwrap();
+        assert_eq!(lenient, record);
+        assert!(ignored.is_empty(), "{}: {:?}", key, ignored);
+        // Output always uses the canonical name.
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains("\"meta_control\":0.2"), "{}", json);
+    }
+}
+
+#[test]
+fn test_unlisted_alias_still_rejected() {
+    assert!(parse_strict(&with_meta_key("metacontrol")).is_err());
+}
+
+// ============================================================
+// arbitrary (feature-gated)
+// ============================================================
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn test_arbitrary_records_cover_valid_and_invalid() {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    let (mut valid, mut invalid) = (0, 0);
+    for seed in 0u32..2000 {
+        let bytes: Vec<u8> = (0..256u32)
+            .map(|i| (seed.wrapping_mul(2_654_435_761).wrapping_add(i.wrapping_mul(40_503)) >> 13) as u8)
+            .collect();
+        let mut u = Unstructured::new(&bytes);
+        let record = RuntimeStateRecord::arbitrary(&mut u).unwrap();
+        if is_valid(&record) {
+            valid += 1;
+        } else {
+            invalid += 1;
+        }
+    }
+    assert!(valid > 0 && invalid > 0, "valid={} invalid={}", valid, invalid);
+}
@@ -0,0 +1,164 @@
+// diff_tests.rs — Record and Stream Comparison Tests
+//
+// Tests cover:
+// - Field-level record diffs with float tolerance
+// - Approximate record equality
+// - Index and timestamp stream alignment
+// - Stream parsing from JSON arrays and NDJSON
+// - Structural comparison with the spec example record
+
+use pmatrix_encoder::diff::*;
+use pmatrix_encoder::input::parse_stream;
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::schema::RuntimeStateRecord;
+
+fn record(baseline: f64, timestamp: u64) -> RuntimeStateRecord {
+    emit_demo_record(baseline, 0.5, 0.5, 0.5, Some(timestamp)).unwrap()
+}
+
+// ============================================================
+// diff_records
+// ============================================================
+
+#[test]
+fn test_diff_records_identical() {
+    let a = record(0.5, 1000);
+    assert!(diff_records(&a, &a.clone(), 0.0).is_empty());
+}
+
+#[test]
+fn test_diff_records_within_eps() {
+    let a = record(0.5, 1000);
+    let mut b = a.clone();
+    b.risk_score += 1e-12;
+    assert!(diff_records(&a, &b, 1e-9).is_empty());
+    assert_eq!(diff_records(&a, &b, 0.0)[0].field, "risk_score");
+}
+
+#[test]
+fn test_diff_records_reports_fields_in_schema_order() {
+    let a = record(0.5, 1000);
+    let b = record(0.0, 1001);
+    let fields: Vec<&str> = diff_records(&a, &b, 1e-9).iter().map(|d| d.field).collect();
+    assert_eq!(
+        fields,
+        vec![
+            "timestamp",
+            "functions.baseline",
+            "stability_score",
+            "risk_score",
+            "mode",
+            "risk_level",
+        ]
+    );
+}
+
+#[test]
+fn test_records_approx_eq() {
+    let a = record(0.5, 1000);
+    let mut b = a.clone();
+    b.functions.norm += 1e-12;
+    assert_ne!(a, b);
+    assert!(records_approx_eq(&a, &b, 1e-9));
+    assert!(!records_approx_eq(&a, &b, 0.0));
+    b.risk_score = f64::NAN;
+    let mut c = b.clone();
+    assert!(records_approx_eq(&b, &c, 1e-9));
+    c.mode = "Halt".into();
+    assert!(!records_approx_eq(&b, &c, 1.0));
+    assert!(!records_approx_eq(&a, &a.clone().with_timestamp(1001), 1.0));
+    let mut inf = a.clone();
+    inf.stability_score = f64::INFINITY;
+    assert!(records_approx_eq(&inf, &inf, 1e-9));
+    assert!(diff_records(&inf, &inf, 1e-9).is_empty());
+    let mut neg = inf.clone();
+    neg.stability_score = f64::NEG_INFINITY;
+    assert!(!records_approx_eq(&inf, &neg, 1e-9));
+}
+
+// ============================================================
+// diff_streams
+// ============================================================
+
+#[test]
+fn test_diff_streams_by_index() {
+    let a = vec![record(0.5, 1000), record(0.5, 1001), record(0.5, 1002)];
+    let mut b = a.clone();
+    b[1].mode = "Alert".to_string();
+    b.pop();
+
+    let diffs = diff_streams(&a, &b, 1e-9);
+    assert_eq!(diffs.len(), 2);
+    match &diffs[0] {
+        StreamDiff::Field { left_index, diff, .. } => {
+            assert_eq!(*left_index, 1);
+            assert_eq!(diff.field, "mode");
+        }
+        other => panic!("unexpected diff {:?}", other),
+    }
+    assert_eq!(diffs[1], StreamDiff::LengthMismatch { left_len: 3, right_len: 2 });
+}
+
+#[test]
+fn test_diff_streams_by_timestamp() {
+    let a = vec![record(0.5, 1000), record(0.5, 1001), record(0.5, 1002)];
+    let b = vec![record(0.5, 1000), record(0.5, 1002)];
+
+    let diffs = diff_streams_aligned(&a, &b, 1e-9, Alignment::Timestamp);
+    assert_eq!(
+        diffs,
+        vec![
+            StreamDiff::Unmatched { side: Side::Left, index: 1, timestamp: 1001 },
+            StreamDiff::LengthMismatch { left_len: 3, right_len: 2 },
+        ]
+    );
+}
+
+// ============================================================
+// parse_stream
+// ============================================================
+
+#[test]
+fn test_parse_stream_array_and_ndjson() {
+    let records = vec![record(0.5, 1000), record(0.2, 1001)];
+    let array = serde_json::to_string(&records).unwrap();
+    let ndjson: String = records
+        .iter()
+        .map(|r| serde_json::to_string(r).unwrap() + "\n")
+        .collect();
+
+    assert_eq!(parse_stream(&array).unwrap(), records);
+    assert_eq!(parse_stream(&ndjson).unwrap(), records);
+    assert!(parse_stream("{\"spec_version\": 1}").is_err());
+}
+
+// ============================================================
+// Shape comparison with the spec example
+// ============================================================
+
+#[test]
+fn test_diff_shape_against_example() {
+    let mut value = serde_json::to_value(record(0.5, 1000)).unwrap();
+    assert!(diff_shape_against_example(&value).is_empty());
+    value["sequence_number"] = serde_json::json!(7);
+    value["risk_score"] = serde_json::json!(1);
+    assert!(diff_shape_against_example(&value).is_empty());
+
+    value["timestamp"] = serde_json::json!("now");
+    value["functions"].as_object_mut().unwrap().remove("norm");
+    value["note"] = serde_json::json!(null);
+    let diffs = diff_shape_against_example(&value);
+    assert_eq!(
+        diffs,
+        vec![
+            ShapeDiff::Missing { path: "/functions/norm".into(), expected: "number" },
+            ShapeDiff::WrongType { path: "/timestamp".into(), expected: "integer", found: "string" },
+            ShapeDiff::Unexpected { path: "/note".into(), found: "null" },
+        ]
+    );
+    assert_eq!(diffs[1].to_string(), "/timestamp is string, expected integer");
+    assert_eq!(
+        diff_shape(&serde_json::json!([]), &serde_json::json!({})),
+        vec![ShapeDiff::WrongType { path: "/".into(), expected: "object", found: "array" }]
+    );
+}
@@ -0,0 +1,130 @@
+// doctor_tests.rs — Record Diagnosis Tests
+//
+// Tests cover:
+// - Syntax errors with a targeted hint
+// - Missing, misnamed, mistyped and unknown fields
+// - meta_control aliases accepted by the codec
+// - Partial diagnosis of records that do not deserialize
+// - Invariant findings in priority order, with one consistency finding
+// - A conforming record yields no findings
+
+use pmatrix_encoder::doctor::diagnose;
+use pmatrix_encoder::emit_demo_record;
+use serde_json::Value;
+
+fn record() -> Value {
+    serde_json::to_value(emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap()).unwrap()
+}
+
+fn problems(input: &str) -> Vec<String> {
+    diagnose(input).into_iter().map(|f| f.problem).collect()
+}
+
+#[test]
+fn test_conforming_record_has_no_findings() {
+    assert!(diagnose(&record().to_string()).is_empty());
+}
+
+#[test]
+fn test_syntax_errors() {
+    let findings = diagnose("{'mode': 'Caution'}");
+    assert_eq!(findings.len(), 1);
+    assert!(findings[0].problem.starts_with("the input is not valid JSON: key must be a string"));
+    assert!(findings[0].hint.contains("double quotes"));
+
+    let stream = format!("{}\n{}", record(), record());
+    assert!(diagnose(&stream)[0].hint.contains("validate --batch"));
+    assert!(diagnose("{\"mode\": ").remove(0).hint.contains("cut short"));
+    assert_eq!(problems(" \u{feff}\n"), ["the input is empty"]);
+    assert_eq!(problems("42"), ["the input is the number 42, not a record object"]);
+}
+
+#[test]
+fn test_field_findings() {
+    let mut value = record();
+    let object = value.as_object_mut().unwrap();
+    let risk = object.remove("risk_score").unwrap();
+    object.insert("riskScore".into(), risk);
+    object.remove("mode");
+    object.insert("timestamp".into(), "2024-02-10T00:00:00Z".into());
+    object.insert("source".into(), "edge-7".into());
+    object["functions"].as_object_mut().unwrap().insert("drift".into(), 0.1.into());
+    assert_eq!(
+        problems(&value.to_string()),
+        [
+            "`riskScore` should be named `risk_score`",
+            "missing required field `mode`",
+            "`timestamp` is the string \"2024-02-10T00:00:00Z\"",
+            "unknown field `source`",
+            "unknown field `functions.drift`",
+        ]
+    );
+}
+
+#[test]
+fn test_meta_control_aliases_are_known() {
+    for alias in ["meta_ctrl", "metaControl", "meta-control"] {
+        let input = record().to_string().replace("meta_control", alias);
+        assert!(diagnose(&input).is_empty(), "{}: {:?}", alias, diagnose(&input));
+    }
+    let mut value = record();
+    value["functions"]["meta_ctrl"] = 0.5.into();
+    assert_eq!(problems(&value.to_string()), ["unknown field `functions.meta_ctrl`"]);
+}
+
+#[test]
+fn test_partial_diagnosis() {
+    // Without mode the record cannot deserialize, but the usable fields are
+    // still checked.
+    let mut value = record();
+    let object = value.as_object_mut().unwrap();
+    object.remove("mode");
+    object.insert("spec_version".into(), "pmatrix-3.4".into());
+    object["functions"]["norm"] = 1.5.into();
+    object.insert("risk_level".into(), "L9".into());
+    assert_eq!(
+        problems(&value.to_string()),
+        [
+            "missing required field `mode`",
+            "spec_version is \"pmatrix-3.4\"",
+            "functions.norm=1.5 outside [0.0, 1.0]",
+            "risk_level \"L9\" is not one of L1, L2, L3, L4, L5",
+        ]
+    );
+
+    let mut value = record();
+    value["functions"] = serde_json::json!([0.5, 0.5, 0.5, 0.5]);
+    let findings = diagnose(&value.to_string());
+    assert_eq!(findings.len(), 1);
+    assert!(findings[0].hint.contains("--functions-array"));
+}
+
+#[test]
+fn test_invariant_findings_in_priority_order() {
+    let mut value = record();
+    value["mode"] = "Alert".into();
+    value["risk_score"] = 1.5.into();
+    value["spec_version"] = "pmatrix-3.4".into();
+    let findings = diagnose(&value.to_string());
+    let problems: Vec<&str> = findings.iter().map(|f| f.problem.as_str()).collect();
+    // Version first, then range, then a single finding for INV-C1/C2/C3.
+    assert_eq!(problems.len(), 3, "{:?}", problems);
+    assert!(problems[0].starts_with("INV-S3: "));
+    assert!(problems[1].starts_with("INV-R3: "));
+    assert!(problems[2].starts_with("risk_score 1.5 is outside [0.0, 1.0]"));
+    assert!(findings[2].hint.contains("--recompute-mode"));
+
+    let mut value = record();
+    value["mode"] = "Alert".into();
+    assert_eq!(diagnose(&value.to_string()).len(), 1);
+}
+
+#[test]
+fn test_array_input_diagnoses_first_element() {
+    let mut bad = record();
+    bad["mode"] = "Alert".into();
+    let input = serde_json::json!([bad, record()]).to_string();
+    let problems = problems(&input);
+    assert_eq!(problems.len(), 2);
+    assert_eq!(problems[0], "the input is an array of 2 value(s), not a single record");
+}
@@ -0,0 +1,40 @@
+// filter_tests.rs — Stream Filtering Tests
+//
+// Tests cover:
+// - filter_records preserves order
+// - RecordFilter risk band bounds (inclusive) and mode selection
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::filter::*;
+use pmatrix_encoder::schema::RuntimeStateRecord;
+
+fn stream() -> Vec<RuntimeStateRecord> {
+    // Baseline 0.0, 0.25, ... 1.0 gives one record per mode, increasing risk.
+    (0..5).map(|i| emit_demo_record(0.25 * i as f64, 0.5, 0.5, 0.5, Some(1000 + i)).unwrap()).collect()
+}
+
+#[test]
+fn test_filter_records_preserves_order() {
+    let kept: Vec<u64> = filter_records(stream(), |r| r.timestamp % 2 == 0).map(|r| r.timestamp).collect();
+    assert_eq!(kept, vec![1000, 1002, 1004]);
+}
+
+#[test]
+fn test_record_filter_band_and_modes() {
+    let records = stream();
+    let all = RecordFilter::default();
+    assert!(records.iter().all(|r| all.matches(r)));
+
+    let risk = records[2].risk_score;
+    let band = RecordFilter { min_risk: Some(risk), max_risk: Some(risk), ..Default::default() };
+    let kept: Vec<_> = filter_records(records.clone(), |r| band.matches(r)).collect();
+    assert_eq!(kept, vec![records[2].clone()]);
+
+    let modes = RecordFilter { modes: vec![records[0].mode.clone(), records[4].mode.clone()], ..Default::default() };
+    let kept: Vec<u64> = filter_records(records.clone(), |r| modes.matches(r)).map(|r| r.timestamp).collect();
+    assert_eq!(kept, vec![1000, 1004]);
+
+    let mut nan = records[0].clone();
+    nan.risk_score = f64::NAN;
+    assert!(!RecordFilter { min_risk: Some(0.0), ..Default::default() }.matches(&nan));
+}
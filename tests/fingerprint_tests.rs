@@ -0,0 +1,143 @@
+// fingerprint_tests.rs — Record Fingerprint Tests
+//
+// Tests cover:
+// - Canonical serialization and a pinned golden fingerprint
+// - Sensitivity to any field change
+// - Case-insensitive fingerprint matching
+// - Pinned field order and byte-identical reserialization
+// - Batch manifests and their verification
+// - Sealed records
+#![cfg(feature = "canonical")]
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::fingerprint::*;
+use pmatrix_encoder::input::parse_strict;
+use pmatrix_encoder::manifest::*;
+
+#[test]
+fn test_canonical_bytes_compact_schema_order() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(10)).unwrap();
+    assert_eq!(
+        String::from_utf8(canonical_bytes(&record)).unwrap(),
+        r#"{"spec_version":"pmatrix-3.5","schema_version":"1.0.0","timestamp":10,"functions":{"baseline":0.5,"norm":0.5,"stability":0.5,"meta_control":0.5},"stability_score":0.5,"risk_score":0.5,"mode":"Caution","risk_level":"L3"}"#
+    );
+}
+
+#[test]
+fn test_fingerprint_golden_value() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(10)).unwrap();
+    assert_eq!(
+        record_fingerprint(&record),
+        "95d9ffae1f5d21f9c209e0bf15f3e022c3bd5a08c4dbea23065aa38d8ed0a6ab"
+    );
+}
+
+#[test]
+fn test_fingerprint_changes_with_any_field() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(10)).unwrap();
+    let fp = record_fingerprint(&record);
+    assert_ne!(record_fingerprint(&record.clone().with_timestamp(11)), fp);
+    let mut tweaked = record.clone();
+    tweaked.functions.norm = 0.5000000000000001;
+    assert_ne!(record_fingerprint(&tweaked), fp);
+}
+
+#[test]
+fn test_fingerprint_matches_case_insensitive() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(10)).unwrap();
+    let upper = record_fingerprint(&record).to_uppercase();
+    assert!(fingerprint_matches(&record, &upper));
+    assert!(!fingerprint_matches(&record, "00"));
+}
+
+#[test]
+fn test_canonical_field_order_matrix() {
+    const ORDER: [&str; 12] = [
+        "\"spec_version\"", "\"schema_version\"", "\"timestamp\"", "\"functions\"",
+        "\"baseline\"", "\"norm\"", "\"stability\"", "\"meta_control\"",
+        "\"stability_score\"", "\"risk_score\"", "\"mode\"", "\"risk_level\"",
+    ];
+    for (b, ts) in [(0.0, 1), (0.25, 1_000), (0.5, 1_707_500_000), (1.0, u64::MAX)] {
+        let record = emit_demo_record(b, 1.0 - b, b, 0.5, Some(ts)).unwrap();
+        let json = String::from_utf8(canonical_bytes(&record)).unwrap();
+        let positions: Vec<usize> = ORDER.iter().map(|k| json.find(k).expect(k)).collect();
+        assert!(positions.windows(2).all(|w| w[0] < w[1]), "{}", json);
+    }
+}
+
+#[test]
+fn test_canonical_reserialization_is_byte_identical() {
+    // Values exactly representable in binary round-trip without drift.
+    for v in [0.0, 0.25, 0.5, 0.75, 1.0] {
+        let record = emit_demo_record(v, v, v, v, Some(42)).unwrap();
+        let bytes = canonical_bytes(&record);
+        let reparsed = parse_strict(std::str::from_utf8(&bytes).unwrap()).unwrap();
+        assert_eq!(canonical_bytes(&reparsed), bytes);
+        assert_eq!(record_fingerprint(&reparsed), record_fingerprint(&record));
+    }
+}
+
+// ============================================================
+// Batch manifests
+// ============================================================
+
+fn batch() -> Vec<pmatrix_encoder::schema::RuntimeStateRecord> {
+    [0.0, 0.5, 0.5, 1.0]
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| emit_demo_record(b, b, b, b, Some(100 + i as u64)).unwrap())
+        .collect()
+}
+
+#[test]
+fn test_build_manifest() {
+    let records = batch();
+    let m = build_manifest(&records);
+    assert_eq!(m.record_count, 4);
+    assert_eq!((m.first_timestamp, m.last_timestamp), (Some(100), Some(103)));
+    assert_eq!(m.mode_counts.values().sum::<usize>(), 4);
+    let ndjson: Vec<u8> = records.iter().flat_map(|r| [canonical_bytes(r), b"\n".to_vec()].concat()).collect();
+    use sha2::{Digest, Sha256};
+    let expected: String = Sha256::digest(&ndjson).iter().map(|b| format!("{:02x}", b)).collect();
+    assert_eq!(m.fingerprint, expected);
+
+    let empty = build_manifest(&[]);
+    assert_eq!((empty.record_count, empty.first_timestamp), (0, None));
+    assert_eq!(empty.fingerprint, "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+}
+
+#[test]
+fn test_verify_manifest_detects_changes() {
+    let records = batch();
+    let stored: Manifest = serde_json::from_str(&serde_json::to_string(&build_manifest(&records)).unwrap()).unwrap();
+    assert!(verify_manifest(&records, &stored).is_empty());
+
+    let mut reordered = records.clone();
+    reordered.swap(1, 2);
+    reordered[1].functions.norm = 0.25;
+    assert_eq!(verify_manifest(&reordered, &stored), vec!["fingerprint"]);
+    assert_eq!(
+        verify_manifest(&records[..3], &stored),
+        vec!["record_count", "last_timestamp", "fingerprint", "mode_counts"]
+    );
+}
+
+// ============================================================
+// Sealed records
+// ============================================================
+
+#[test]
+fn test_seal_round_trip_and_tamper() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(10)).unwrap();
+    let sealed = seal(record.clone());
+    assert_eq!(sealed, seal(record.clone()));
+    assert_eq!(sealed.fingerprint, record_fingerprint(&record));
+    assert!(sealed.verify());
+
+    let json = serde_json::to_string(&sealed).unwrap();
+    let mut reparsed: SealedRecord = serde_json::from_str(&json).unwrap();
+    assert!(reparsed.verify());
+    reparsed.record.timestamp = 11;
+    assert!(!reparsed.verify());
+    assert!(serde_json::from_str::<SealedRecord>(r#"{"record": null}"#).is_err());
+}
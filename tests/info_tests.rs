@@ -0,0 +1,33 @@
+// info_tests.rs — Build Information Tests
+//
+// Tests cover:
+// - Versions and invariant IDs reported by crate_info
+// - Feature flags of the test build
+
+use pmatrix_encoder::info::crate_info;
+use pmatrix_encoder::invariants::INVARIANTS;
+use pmatrix_encoder::schema::{SCHEMA_VERSION, SPEC_VERSION};
+
+// ============================================================
+// crate_info
+// ============================================================
+
+#[test]
+fn test_crate_info_versions_and_invariants() {
+    let info = crate_info();
+    assert_eq!(info.version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(info.spec_versions, vec![SPEC_VERSION]);
+    assert_eq!(info.schema_versions, vec![SCHEMA_VERSION, "1.1.0"]);
+    assert_eq!(info.invariants.len(), INVARIANTS.len());
+    assert_eq!(info.invariants[0], "INV-R1");
+    assert_eq!(info.optional_invariants, vec!["INV-R6", "INV-C4", "INV-S6", "INV-C5", "INV-T2", "INV-Q1"]);
+}
+
+#[test]
+fn test_crate_info_features_match_build() {
+    let features = crate_info().features;
+    assert_eq!(features.contains(&"canonical"), cfg!(feature = "canonical"));
+    assert_eq!(features.contains(&"gzip"), cfg!(feature = "gzip"));
+    let json = serde_json::to_value(crate_info()).unwrap();
+    assert!(json["features"].is_array());
+}
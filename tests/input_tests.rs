@@ -0,0 +1,271 @@
+// input_tests.rs — Input Reading Tests
+//
+// Tests cover:
+// - Reading plain record files
+// - gzip detection by extension and magic bytes (with and without the feature)
+// - Input format sniffing, for whole and lazily read inputs
+// - Records embedded at a JSON Pointer
+// - Parsing a bounded stream prefix, from a string or a reader
+// - The --max-records cap for arrays, NDJSON, lazy streams and JSON Pointers
+// - BOM-prefixed, CRLF and whitespace-padded input
+// - The compact functions array form
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::input::*;
+
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("pmatrix-input-{}-{}", std::process::id(), name))
+}
+
+fn ndjson() -> String {
+    (0..3)
+        .map(|i| {
+            let r = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000 + i)).unwrap();
+            serde_json::to_string(&r).unwrap() + "\n"
+        })
+        .collect()
+}
+
+#[test]
+fn test_read_plain_file() {
+    let path = temp_path("plain.ndjson");
+    fs::write(&path, ndjson()).unwrap();
+    let records = parse_stream(&read_input(Some(&path), false).unwrap()).unwrap();
+    assert_eq!(records.len(), 3);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(not(feature = "gzip"))]
+#[test]
+fn test_gzip_rejected_without_feature() {
+    let path = temp_path("magic.ndjson");
+    fs::write(&path, [GZIP_MAGIC[0], GZIP_MAGIC[1], 0x08, 0x00]).unwrap();
+    let err = read_input(Some(&path), false).unwrap_err();
+    assert!(err.to_string().contains("`gzip` feature"), "{}", err);
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_gzip_detected_by_extension_and_magic() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    let mut enc = GzEncoder::new(Vec::new(), Compression::default());
+    enc.write_all(ndjson().as_bytes()).unwrap();
+    let compressed = enc.finish().unwrap();
+
+    for name in ["stream.ndjson.gz", "stream-no-extension"] {
+        let path = temp_path(name);
+        fs::write(&path, &compressed).unwrap();
+        assert_eq!(read_input(Some(&path), false).unwrap(), ndjson().trim());
+        fs::remove_file(&path).unwrap();
+    }
+}
+
+#[test]
+fn test_sniff_format() {
+    assert_eq!(sniff_format(ndjson().as_bytes()).unwrap(), InputFormat::Json);
+    assert_eq!(sniff_format(b"  \n[]").unwrap(), InputFormat::Json);
+    assert_eq!(sniff_format(b"").unwrap(), InputFormat::Json);
+    assert_eq!(sniff_format(&[0xd9, 0xd9, 0xf7, 0xa8]).unwrap(), InputFormat::Cbor);
+    assert_eq!(sniff_format(&[0xa8, 0x6c]).unwrap(), InputFormat::Cbor);
+    assert_eq!(sniff_format(b"---\nmode: Caution\n").unwrap(), InputFormat::Yaml);
+    assert_eq!(sniff_format(b"spec_version: pmatrix-3.5\n").unwrap(), InputFormat::Yaml);
+    let err = sniff_format(b"hello world").unwrap_err();
+    assert!(err.to_string().contains("--input-format json"), "{}", err);
+}
+
+#[test]
+fn test_read_input_as_rejects_non_json() {
+    let path = temp_path("record.yaml");
+    fs::write(&path, "spec_version: pmatrix-3.5\n").unwrap();
+    let err = read_input_as(Some(&path), false, None).unwrap_err();
+    assert!(err.to_string().contains("YAML input is not supported"), "{}", err);
+    // An explicit format skips sniffing.
+    assert!(read_input_as(Some(&path), false, Some(InputFormat::Json)).is_ok());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_input_as_rejects_non_json() {
+    let path = temp_path("stream.yaml");
+    fs::write(&path, "---\nmode: Caution\n").unwrap();
+    let err = open_input_as(Some(&path), false, None).err().unwrap();
+    assert!(err.to_string().contains("YAML input is not supported"), "{}", err);
+    assert!(open_input_as(Some(&path), false, Some(InputFormat::Json)).is_ok());
+    fs::write(&path, ndjson()).unwrap();
+    let records: Vec<_> = read_records(open_input_as(Some(&path), false, None).unwrap()).collect();
+    assert_eq!(records.len(), ndjson().lines().count());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_parse_at_pointer_objects_and_arrays() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    let json = serde_json::to_string(&record).unwrap();
+    let enveloped = format!("{{\"meta\":{{}},\"state\":{}}}\n{{\"meta\":{{}},\"state\":{}}}\n", json, json);
+    assert_eq!(parse_at_pointer(&enveloped, "/state").unwrap(), vec![record.clone(); 2]);
+
+    let nested = format!("{{\"batch\":{{\"records\":[{},{}]}}}}", json, json);
+    assert_eq!(parse_at_pointer(&nested, "/batch/records").unwrap().len(), 2);
+    assert_eq!(parse_at_pointer(&json, "").unwrap(), vec![record]);
+}
+
+#[test]
+fn test_parse_at_pointer_errors() {
+    let doc = r#"{"state": {"extra": 1}, "n": 3}"#;
+    let err = parse_at_pointer(doc, "/missing").unwrap_err();
+    assert!(err.to_string().contains("`/missing` does not resolve in document 1"), "{}", err);
+    let err = parse_at_pointer(doc, "/n").unwrap_err();
+    assert!(err.to_string().contains("selects neither an object nor an array"), "{}", err);
+    assert!(parse_at_pointer(doc, "state").unwrap_err().to_string().contains("start with '/'"));
+    // The selected object is still parsed strictly.
+    assert!(parse_at_pointer(doc, "/state").is_err());
+}
+
+#[test]
+fn test_parse_stream_limit() {
+    let mut input = ndjson();
+    input.push_str("{ not json\n");
+    assert!(parse_stream(&input).is_err());
+    assert_eq!(parse_stream_limit(&input, 2).unwrap().len(), 2);
+    assert_eq!(parse_stream_limit(&input, 3).unwrap().len(), 3);
+    assert!(parse_stream_limit(&input, 4).is_err());
+    let array = format!("[{}]", ndjson().trim_end().replace('\n', ","));
+    assert_eq!(parse_stream_limit(&array, 1).unwrap().len(), 1);
+    assert_eq!(parse_stream_limit(&array, 10).unwrap().len(), 3);
+}
+
+#[test]
+fn test_parse_stream_bounded_caps_records() {
+    let input = ndjson();
+    let array = format!("[{}]", input.trim_end().replace('\n', ","));
+    for doc in [&input, &array] {
+        assert_eq!(parse_stream_bounded(doc, usize::MAX, 3).unwrap().len(), 3);
+        let err = parse_stream_bounded(doc, usize::MAX, 2).unwrap_err();
+        assert!(err.to_string().contains("more than 2 records; raise --max-records"), "{}", err);
+        assert_eq!(parse_stream_bounded(doc, 1, 3).unwrap().len(), 1);
+    }
+    // Only the NDJSON records within the limit count toward the cap.
+    assert_eq!(parse_stream_bounded(&input, 2, 2).unwrap().len(), 2);
+    assert!(parse_stream_bounded(&array, 2, 2).is_err());
+    // The array is aborted before its malformed tail is reached.
+    let truncated = format!("{}, {{ ", array.trim_end_matches(']'));
+    assert!(parse_stream_bounded(&truncated, usize::MAX, 2).unwrap_err().to_string().contains("more than 2"));
+    assert!(parse_stream_bounded(&truncated, usize::MAX, 5).unwrap_err().to_string().contains("JSON"));
+    assert!(matches!(parse_stream_bounded(&input, usize::MAX, 2), Err(pmatrix_encoder::PmatrixError::Parse(_))));
+}
+
+#[test]
+fn test_read_stream_bounded_matches_parse_stream_bounded() {
+    let input = ndjson();
+    let array = format!("  \n[{}]", input.trim_end().replace('\n', ","));
+    for doc in [&input, &array] {
+        let read = |limit, max| read_stream_bounded(doc.as_bytes(), limit, max);
+        assert_eq!(read(usize::MAX, 3).unwrap(), parse_stream_bounded(doc, usize::MAX, 3).unwrap());
+        assert!(read(usize::MAX, 2).unwrap_err().to_string().contains("more than 2 records"));
+        assert_eq!(read(1, 3).unwrap().len(), 1);
+    }
+    assert_eq!(read_stream_bounded(&b" \n\n"[..], usize::MAX, 1).unwrap(), vec![]);
+    assert!(read_stream_bounded(&b"[{"[..], usize::MAX, 1).is_err());
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn test_read_stream_bounded_stops_compressed_input_at_the_cap() {
+    use flate2::write::GzEncoder;
+    use flate2::Compression;
+    use std::io::Write;
+
+    // Expands to about 80 MB; only the first records are ever decompressed.
+    let mut enc = GzEncoder::new(Vec::new(), Compression::best());
+    for _ in 0..100_000 {
+        enc.write_all(ndjson().as_bytes()).unwrap();
+    }
+    let path = temp_path("bomb.ndjson.gz");
+    fs::write(&path, enc.finish().unwrap()).unwrap();
+    let err = read_stream_bounded(open_input(Some(&path), false).unwrap(), usize::MAX, 10).unwrap_err();
+    assert!(err.to_string().contains("more than 10 records"), "{}", err);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_cap_records_and_pointer_bounded() {
+    let input = ndjson();
+    let capped: Vec<_> = cap_records(read_records(input.as_bytes()), 2).collect();
+    assert_eq!(capped.len(), 3);
+    assert!(capped[..2].iter().all(Result::is_ok));
+    assert!(capped[2].as_ref().unwrap_err().to_string().contains("more than 2 records"));
+    assert!(cap_records(read_records(input.as_bytes()), 3).all(|r| r.is_ok()));
+    // Taking fewer records than the cap never reaches it.
+    assert!(cap_records(read_records(input.as_bytes()), 1).take(1).all(|r| r.is_ok()));
+
+    let enveloped = format!("{{\"records\": [{}]}}", input.trim_end().replace('\n', ","));
+    assert_eq!(parse_at_pointer_bounded(&enveloped, "/records", 3).unwrap().len(), 3);
+    let err = parse_at_pointer_bounded(&enveloped, "/records", 2).unwrap_err();
+    assert!(err.to_string().contains("more than 2 records"), "{}", err);
+    assert!(parse_at_pointer_bounded(&input, "", 2).is_err());
+}
+
+// ============================================================
+// BOM and whitespace cleanup
+// ============================================================
+
+#[test]
+fn test_clean_input_strips_bom_per_line() {
+    assert_eq!(clean_input("  {}\r\n\n"), "{}");
+    assert!(matches!(clean_input("{}"), std::borrow::Cow::Borrowed("{}")));
+    // Concatenated exports: a BOM at the start of every line.
+    let crlf = ndjson().replace('\n', "\r\n");
+    let bommed: String = crlf.lines().map(|l| format!("\u{feff}{}\r\n", l)).collect();
+    assert_eq!(parse_stream(&clean_input(&bommed)).unwrap().len(), 3);
+    // A BOM inside a string value is left alone.
+    assert_eq!(clean_input("{\"a\": \"x\u{feff}\"}\u{feff}\n"), "{\"a\": \"x\u{feff}\"}\u{feff}");
+}
+
+#[test]
+fn test_read_input_handles_bom_and_crlf() {
+    let record = serde_json::to_string_pretty(&emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap()).unwrap();
+    let path = temp_path("bom.json");
+    fs::write(&path, format!("\u{feff}{}\r\n\r\n", record.replace('\n', "\r\n"))).unwrap();
+    let input = read_input(Some(&path), false).unwrap();
+    assert!(parse_strict(&input).is_ok());
+    let input = read_input_as(Some(&path), false, None).unwrap();
+    assert!(parse_strict(&input).is_ok());
+
+    let bommed: String = ndjson().lines().map(|l| format!("\u{feff}{}\r\n", l)).collect();
+    fs::write(&path, bommed).unwrap();
+    let streamed: Vec<_> = read_records(open_input(Some(&path), false).unwrap()).collect();
+    assert_eq!(streamed.len(), 3);
+    assert!(streamed.iter().all(|r| r.is_ok()));
+    fs::remove_file(&path).unwrap();
+}
+
+// ============================================================
+// functions as an array
+// ============================================================
+
+#[test]
+fn test_expand_functions_array() {
+    let record = emit_demo_record(0.25, 0.7, 0.3, 0.2, Some(1000)).unwrap();
+    let mut value = serde_json::to_value(&record).unwrap();
+    value["functions"] = serde_json::json!([0.25, 0.7, 0.3, 0.2]);
+    // The strict default still rejects the array form.
+    assert!(parse_strict(&value.to_string()).is_err());
+
+    let ndjson = format!("{}\n{}\n", value, value);
+    assert_eq!(parse_stream(&expand_functions_arrays(&ndjson).unwrap()).unwrap(), vec![record.clone(), record.clone()]);
+    let array = format!("[{}, {}]", value, serde_json::to_string(&record).unwrap());
+    let expanded = expand_functions_arrays(&array).unwrap();
+    assert!(expanded.starts_with('['));
+    assert_eq!(parse_stream(&expanded).unwrap().len(), 2);
+
+    value["functions"] = serde_json::json!([0.25, 0.7, 0.3]);
+    let err = expand_functions_array(&mut value).unwrap_err();
+    assert!(err.to_string().contains("functions array has 3 element(s); expected 4"), "{}", err);
+}
@@ -0,0 +1,84 @@
+// lint_tests.rs — Fixture Directory Linting Tests
+//
+// Tests cover:
+// - Glob matching with `*`, `?` and `**`
+// - Recursive discovery with ignore patterns, not following directory links
+// - Per-file verdicts for records, arrays and unreadable or oversized files
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::lint::*;
+
+use std::fs;
+use std::path::PathBuf;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pmatrix-lint-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("nested/deeper")).unwrap();
+    fs::create_dir_all(dir.join("drafts")).unwrap();
+    dir
+}
+
+fn record_json(timestamp: u64) -> String {
+    serde_json::to_string(&emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(timestamp)).unwrap()).unwrap()
+}
+
+// ============================================================
+// glob_match
+// ============================================================
+
+#[test]
+fn test_glob_match() {
+    assert!(glob_match("**/*.json", "a.json"));
+    assert!(glob_match("**/*.json", "nested/deeper/a.json"));
+    assert!(!glob_match("*.json", "nested/a.json"));
+    assert!(glob_match("nested/*.json", "nested/a.json"));
+    assert!(glob_match("record-?.json", "record-1.json"));
+    assert!(!glob_match("record-?.json", "record-10.json"));
+    assert!(glob_match("drafts/**", "drafts/x/y.json"));
+    assert!(!glob_match("**/*.json", "a.ndjson"));
+}
+
+// ============================================================
+// Discovery and verdicts
+// ============================================================
+
+#[test]
+fn test_lint_directory() {
+    let dir = temp_dir("verdicts");
+    fs::write(dir.join("good.json"), record_json(1000)).unwrap();
+    fs::write(dir.join("nested/array.json"), format!("[{}, {}]", record_json(2), record_json(1))).unwrap();
+    fs::write(dir.join("nested/deeper/broken.json"), "{ not json").unwrap();
+    fs::write(dir.join("drafts/skip.json"), "{}").unwrap();
+    fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+    let files = find_record_files(&dir, DEFAULT_GLOB, &["drafts/**".to_string()]).unwrap();
+    let names: Vec<String> = files.iter().map(|p| relative_path(&dir, p)).collect();
+    assert_eq!(names, vec!["good.json", "nested/array.json", "nested/deeper/broken.json"]);
+
+    assert_eq!(lint_file(&files[0]), FileVerdict::Conforming { records: 1 });
+    assert_eq!(lint_file(&files[1]), FileVerdict::NonConforming { records: 2, failed: vec!["INV-T1"] });
+    assert!(matches!(lint_file(&files[2]), FileVerdict::Error(_)));
+    assert_eq!(lint_file_bounded(&files[1], 2), lint_file(&files[1]));
+    let FileVerdict::Error(msg) = lint_file_bounded(&files[1], 1) else { panic!("cap not applied") };
+    assert!(msg.contains("more than 1 records"), "{}", msg);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn test_lint_skips_symlinked_directories() {
+    use std::os::unix::fs::symlink;
+
+    let dir = temp_dir("symlinks");
+    fs::write(dir.join("nested/good.json"), record_json(1000)).unwrap();
+    // A link back to the root would loop forever if followed.
+    symlink(&dir, dir.join("nested/loop")).unwrap();
+    symlink(dir.join("nested/good.json"), dir.join("alias.json")).unwrap();
+
+    let files = find_record_files(&dir, DEFAULT_GLOB, &[]).unwrap();
+    let names: Vec<String> = files.iter().map(|p| relative_path(&dir, p)).collect();
+    assert_eq!(names, vec!["alias.json", "nested/good.json"]);
+    fs::remove_dir_all(&dir).unwrap();
+}
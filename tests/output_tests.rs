@@ -0,0 +1,35 @@
+// output_tests.rs — Record Output Tests
+//
+// Tests cover:
+// - Streaming JSON-array and NDJSON writers round-trip through parse_stream
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::input::parse_stream;
+use pmatrix_encoder::output::*;
+use pmatrix_encoder::schema::RuntimeStateRecord;
+
+fn records(n: u64) -> Vec<RuntimeStateRecord> {
+    (0..n).map(|i| emit_demo_record(0.25 * (i % 5) as f64, 0.5, 0.5, 0.5, Some(1000 + i)).unwrap()).collect()
+}
+
+#[test]
+fn test_write_records_array_round_trip() {
+    for n in [0, 1, 25] {
+        let expected = records(n);
+        let mut buf = Vec::new();
+        assert_eq!(write_records_array(expected.clone().into_iter(), &mut buf).unwrap(), n as usize);
+        assert!(buf.starts_with(b"[") && buf.ends_with(b"]"));
+        let parsed: Vec<RuntimeStateRecord> = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(parsed, expected);
+    }
+}
+
+#[test]
+fn test_write_records_ndjson_round_trip() {
+    let expected = records(5);
+    let mut buf = Vec::new();
+    assert_eq!(write_records_ndjson(expected.clone().into_iter(), &mut buf).unwrap(), 5);
+    let text = String::from_utf8(buf).unwrap();
+    assert_eq!(text.lines().count(), 5);
+    assert_eq!(parse_stream(&text).unwrap(), expected);
+}
@@ -0,0 +1,96 @@
+// packed_tests.rs — Fixed-Width Binary Layout Tests
+//
+// Tests cover:
+// - Round trips, including special floats and sequence numbers
+// - The documented byte offsets and little-endian order
+// - Records that cannot be packed
+// - Rejection of malformed packed bytes
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::packed::*;
+use pmatrix_encoder::schema::{spec_example_record, RuntimeStateRecord};
+use pmatrix_encoder::PmatrixError;
+
+type Mutation = fn(&mut RuntimeStateRecord);
+
+fn parse_error(bytes: &[u8]) -> String {
+    match from_packed(bytes) {
+        Err(PmatrixError::Parse(e)) => e.to_string(),
+        other => panic!("expected a parse error, got {:?}", other),
+    }
+}
+
+// ============================================================
+// Round trips and layout
+// ============================================================
+
+#[test]
+fn test_packed_round_trip() {
+    let example = spec_example_record();
+    assert_eq!(from_packed(&to_packed(&example).unwrap()).unwrap(), example);
+
+    let mut record = emit_demo_record(0.0, 1.0, 0.3, 0.7, Some(u64::MAX)).unwrap();
+    record.schema_version = "1.1.0".to_string();
+    record.sequence_number = Some(0);
+    record.functions.baseline = -0.0;
+    record.risk_score = f64::NAN;
+    let decoded = from_packed(&to_packed(&record).unwrap()).unwrap();
+    assert_eq!(decoded.sequence_number, Some(0));
+    assert_eq!(decoded.functions.baseline.to_bits(), (-0.0f64).to_bits());
+    assert_eq!(decoded.risk_score.to_bits(), f64::NAN.to_bits());
+    assert_eq!(decoded.schema_version, "1.1.0");
+}
+
+#[test]
+fn test_packed_layout_offsets() {
+    let mut record = spec_example_record();
+    record.sequence_number = Some(7);
+    let bytes = to_packed(&record).unwrap();
+    assert_eq!(bytes.len(), PACKED_LEN);
+    // flags, pmatrix-3.5, 1.0.0, Caution, L3.
+    assert_eq!(bytes[..8], [1, 3, 5, 1, 0, 0, 2, 2]);
+    assert_eq!(bytes[8..16], 1707500000u64.to_le_bytes());
+    assert_eq!(bytes[16..24], 0.25f64.to_le_bytes());
+    assert_eq!(bytes[56..64], 0.42f64.to_le_bytes());
+    assert_eq!(bytes[64..], 7u64.to_le_bytes());
+}
+
+// ============================================================
+// Errors
+// ============================================================
+
+#[test]
+fn test_unpackable_records() {
+    let cases: [(Mutation, &str); 4] = [
+        (|r| r.mode = "Panic".to_string(), "unknown mode"),
+        (|r| r.risk_level = "L0".to_string(), "unknown risk_level"),
+        (|r| r.spec_version = "pmatrix-3.05".to_string(), "spec_version"),
+        (|r| r.schema_version = "1.0.256".to_string(), "schema_version"),
+    ];
+    for (mutate, expected) in cases {
+        let mut record = spec_example_record();
+        mutate(&mut record);
+        match to_packed(&record) {
+            Err(PmatrixError::Encode(msg)) => assert!(msg.contains(expected), "{}", msg),
+            other => panic!("expected an encode error, got {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_malformed_packed_bytes() {
+    let good = to_packed(&spec_example_record()).unwrap();
+    assert!(parse_error(&good[..71]).contains("expected 72 bytes, got 71"));
+
+    let mut bytes = good;
+    bytes[0] = 0x80;
+    assert!(parse_error(&bytes).contains("reserved flag bits"));
+
+    let mut bytes = good;
+    bytes[6] = 5;
+    assert!(parse_error(&bytes).contains("mode byte 5"));
+
+    let mut bytes = good;
+    bytes[64] = 1;
+    assert!(parse_error(&bytes).contains("flagged absent"));
+}
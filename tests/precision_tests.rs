@@ -0,0 +1,35 @@
+// precision_tests.rs — Lossy Float Detection Tests
+//
+// Tests cover:
+// - Significant-digit counting of shortest round-trip forms
+// - Flagged fields and their cleaned values
+
+use pmatrix_encoder::precision::*;
+use pmatrix_encoder::schema::spec_example_record;
+
+#[test]
+fn test_significant_digits() {
+    assert_eq!(significant_digits(0.42), 2);
+    assert_eq!(significant_digits(0.42000000000000004), 17);
+    assert_eq!(significant_digits(0.1 + 0.2), 17);
+    assert_eq!(significant_digits(1.0), 1);
+    assert_eq!(significant_digits(-0.125), 3);
+    assert_eq!(significant_digits(1e-20), 1);
+    assert_eq!(significant_digits(0.0), 0);
+    assert_eq!(significant_digits(f64::NAN), 0);
+}
+
+#[test]
+fn test_lossy_values() {
+    let mut record = spec_example_record();
+    assert!(lossy_values(&record, 12).is_empty());
+    record.risk_score = 0.42000000000000004;
+    record.functions.norm = 0.1 + 0.2;
+    let lossy = lossy_values(&record, 12);
+    let fields: Vec<&str> = lossy.iter().map(|v| v.field).collect();
+    assert_eq!(fields, vec!["functions.norm", "risk_score"]);
+    assert_eq!(lossy[0].cleaned, 0.3);
+    assert_eq!(lossy[1].cleaned, 0.42);
+    assert_eq!(lossy[1].digits, 17);
+    assert!(lossy_values(&record, 17).is_empty());
+}
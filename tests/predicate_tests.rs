@@ -0,0 +1,92 @@
+// predicate_tests.rs — Record Predicate Tests
+//
+// Tests cover:
+// - Numeric comparisons, including NaN and an absent sequence_number
+// - mode and risk_level equality and severity ordering
+// - Operator precedence, negation and parentheses
+// - Parse errors with their column
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::predicate::Predicate;
+use pmatrix_encoder::schema::RuntimeStateRecord;
+
+fn stream() -> Vec<RuntimeStateRecord> {
+    // Functions 0.0, 0.25, ... 1.0 give one record per mode, Halt first.
+    (0..5)
+        .map(|i| {
+            let f = 0.25 * i as f64;
+            emit_demo_record(f, f, f, f, Some(1000 + i)).unwrap()
+        })
+        .collect()
+}
+
+fn kept(expr: &str) -> Vec<u64> {
+    let p = Predicate::parse(expr).unwrap();
+    stream().into_iter().filter(|r| p.matches(r)).map(|r| r.timestamp).collect()
+}
+
+fn parse_error(expr: &str) -> String {
+    Predicate::parse(expr).unwrap_err().to_string()
+}
+
+// ============================================================
+// Evaluation
+// ============================================================
+
+#[test]
+fn test_numeric_comparisons() {
+    assert_eq!(kept("risk_score > 0.5"), vec![1000, 1001]);
+    assert_eq!(kept("risk_score >= 0.5"), vec![1000, 1001, 1002]);
+    assert_eq!(kept("functions.baseline == 0.25"), vec![1001]);
+    assert_eq!(kept("timestamp != 1000 && timestamp <= 1.002e3"), vec![1001, 1002]);
+
+    let mut record = stream()[0].clone();
+    record.risk_score = f64::NAN;
+    assert!(!Predicate::parse("risk_score <= 1").unwrap().matches(&record));
+    assert!(Predicate::parse("risk_score != 0.5").unwrap().matches(&record));
+    assert!(!Predicate::parse("sequence_number != 3").unwrap().matches(&record));
+    record.sequence_number = Some(4);
+    assert!(Predicate::parse("sequence_number > 3").unwrap().matches(&record));
+}
+
+#[test]
+fn test_mode_and_level_comparisons() {
+    assert_eq!(kept("mode != Optimal && risk_score > 0.3"), vec![1000, 1001, 1002]);
+    assert_eq!(kept("risk_level >= L4"), vec![1000, 1001]);
+    assert_eq!(kept("mode < Caution"), vec![1003, 1004]);
+    assert_eq!(kept("mode == \"Halt\""), vec![1000]);
+
+    let mut record = stream()[0].clone();
+    record.mode = "Panic".to_string();
+    assert!(Predicate::parse("mode != Halt").unwrap().matches(&record));
+    assert!(!Predicate::parse("mode >= Optimal").unwrap().matches(&record));
+}
+
+#[test]
+fn test_precedence_and_grouping() {
+    // && binds tighter than ||.
+    assert_eq!(kept("mode == Halt || mode == Optimal && risk_score > 0.5"), vec![1000]);
+    assert_eq!(kept("(mode == Halt || mode == Optimal) && risk_score < 0.5"), vec![1004]);
+    assert_eq!(kept("!(risk_level >= L2)"), vec![1004]);
+    assert_eq!(kept("!!(timestamp == 1003)"), vec![1003]);
+}
+
+// ============================================================
+// Parse errors
+// ============================================================
+
+#[test]
+fn test_parse_errors_name_the_column() {
+    assert!(parse_error("risk > 0.5").contains("unknown field `risk`; expected one of timestamp,"));
+    assert!(parse_error("risk > 0.5").ends_with("at column 1"));
+    assert!(parse_error("risk_score 0.5").contains("expected a comparison operator after `risk_score` at column 12"));
+    assert!(parse_error("risk_score = 0.5").contains("use `==` at column 12"));
+    assert!(parse_error("risk_score > Halt").contains("expected a number to compare `risk_score` with at column 14"));
+    assert!(parse_error("mode == Panic").contains("expected a mode (Optimal, Normal, Caution, Alert, Halt), found `Panic`"));
+    assert!(parse_error("risk_level >= 4").contains("expected a risk_level (L1, L2, L3, L4, L5), found `4`"));
+    assert!(parse_error("(mode == Halt").contains("unmatched `(` at column 1"));
+    assert!(parse_error("mode == Halt)").contains("unexpected `)` at column 13"));
+    assert!(parse_error("mode == Halt &&").contains("expected a comparison at column 16"));
+    assert!(parse_error("").contains("expected a comparison at column 1"));
+    assert!(parse_error("risk_score > 0.5.5").contains("`0.5.5` is not a number"));
+}
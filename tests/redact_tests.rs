@@ -0,0 +1,36 @@
+// redact_tests.rs — Record Redaction Tests
+//
+// Tests cover:
+// - Zeroing and bucketing function values
+// - Preserved fields and the effect of blank_scores on the invariants
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::invariants::{validate_all, validate_all_with, ValidationOptions};
+use pmatrix_encoder::redact::*;
+
+#[test]
+fn test_redact_zeroes_functions_and_keeps_the_rest() {
+    let record = emit_demo_record(0.95, 0.7, 0.3, 0.2, Some(1000)).unwrap();
+    let redacted = redact(&record);
+    let f = &redacted.functions;
+    assert_eq!([f.baseline, f.norm, f.stability, f.meta_control], [0.0; 4]);
+    assert_eq!(redacted.mode, record.mode);
+    assert_eq!(redacted.risk_level, record.risk_level);
+    assert_eq!(redacted.timestamp, record.timestamp);
+    assert_eq!(redacted.risk_score, record.risk_score);
+    // The normative invariants still pass; INV-R6 does not.
+    assert!(validate_all(&redacted).iter().all(|r| r.passed));
+    let opts = ValidationOptions { demo_aggregation_eps: Some(1e-9), ..Default::default() };
+    assert!(!validate_all_with(&redacted, &opts).iter().find(|r| r.id == "INV-R6").unwrap().passed);
+}
+
+#[test]
+fn test_redact_bucket_and_blank_scores() {
+    let record = emit_demo_record(0.0, 0.39, 0.6, 1.0, Some(1000)).unwrap();
+    let redacted = redact_with(&record, RedactOptions { bucket: true, blank_scores: true });
+    let f = &redacted.functions;
+    assert_eq!([f.baseline, f.norm, f.stability, f.meta_control], [0.1, 0.3, 0.7, 0.9]);
+    assert_eq!((redacted.stability_score, redacted.risk_score), (0.0, 0.0));
+    let c1 = validate_all(&redacted).into_iter().find(|r| r.id == "INV-C1").unwrap();
+    assert_eq!(c1.passed, record.mode == "Optimal");
+}
@@ -0,0 +1,265 @@
+// report_tests.rs — Human-Readable Report Tests
+//
+// Tests cover:
+// - Classification notes and interval formatting
+// - The partition legend for profiles and level maps
+// - The inspect report layout and verdict
+// - Text and markdown reports, including per-invariant failure counts
+// - Consistency explanations that follow the validation options
+// - Failures-only text output
+// - Stats rendering with and without ANSI colors
+// - Timestamp rendering (RFC 3339 with the `chrono` feature)
+
+use pmatrix_encoder::{emit_demo_record, validate_records};
+use pmatrix_encoder::invariants::{
+    invariant_failure_counts, validate_all, validate_all_with, validate_stream_t1, ValidationOptions,
+};
+use pmatrix_encoder::mode::*;
+use pmatrix_encoder::report::*;
+use pmatrix_encoder::schema::MODES;
+use pmatrix_encoder::stats::stream_stats;
+
+// ============================================================
+// Mode intervals
+// ============================================================
+
+#[test]
+fn test_mode_interval_convention() {
+    assert_eq!(mode_interval("Optimal"), Some((0.0, 0.2)));
+    assert_eq!(format_interval("Caution").as_deref(), Some("[0.4, 0.6)"));
+    assert_eq!(format_interval("Halt").as_deref(), Some("[0.8, 1]"));
+    assert_eq!(mode_interval("Unknown"), None);
+}
+
+#[test]
+fn test_partition_table_follows_profile_and_level_map() {
+    let table = partition_table(&PartitionMap::default(), &LevelMap::default());
+    assert_eq!(table.len(), 5);
+    for (entry, mode) in table.iter().zip(MODES) {
+        assert_eq!(entry.mode, mode);
+        assert_eq!((entry.lower, entry.upper), mode_interval(mode).unwrap());
+        assert_eq!(entry.upper_inclusive, mode == "Halt");
+    }
+    let legend = render_legend(&table);
+    assert_eq!(legend.lines().nth(2), Some("Caution  L3  [0.4, 0.6)"));
+    assert_eq!(legend.lines().nth(4), Some("Halt     L5  [0.8, 1]"));
+
+    let levels = LevelMap::from_json(r#"{"Optimal":"L1","Normal":"L2","Caution":"L3","Alert":"L3","Halt":"L5"}"#).unwrap();
+    let table = partition_table(&builtin_profile("conservative").unwrap(), &levels);
+    assert_eq!((table[3].mode, table[3].level, table[3].lower, table[3].upper), ("Alert", "L3", 0.45, 0.6));
+    assert_eq!(
+        serde_json::to_value(&table[0]).unwrap(),
+        serde_json::json!({"mode": "Optimal", "level": "L1", "lower": 0.0, "upper": 0.15, "upper_inclusive": false})
+    );
+}
+
+// ============================================================
+// inspect
+// ============================================================
+
+#[test]
+fn test_classification_note() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    assert_eq!(classification_note(&record), "risk_score=0.5 ∈ [0.4, 0.6) → Caution / L3");
+}
+
+#[test]
+fn test_render_inspect_conforming() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    let report = render_inspect(&record, &validate_all(&record));
+    assert!(report.starts_with('{'));
+    assert!(report.contains("// invariants:     12/12 passed"));
+    assert!(report.ends_with("// result:         CONFORMING\n"));
+}
+
+#[test]
+fn test_render_inspect_lists_failures() {
+    let mut record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    record.risk_level = "L4".to_string();
+    let report = render_inspect(&record, &validate_all(&record));
+    assert!(report.contains("// [FAIL] INV-C2"));
+    assert!(report.contains("// demo hint:      risk driven primarily by low 'baseline' = 0.50 (demo model only)\n"));
+    assert!(report.contains("// result:         MALFORMED"));
+}
+
+// ============================================================
+// Text and markdown reports
+// ============================================================
+
+#[test]
+fn test_render_text_verdict() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    let text = render_text(&validate_all(&record));
+    assert!(text.starts_with("[PASS] INV-R1 — "));
+    assert!(text.ends_with("Result: ALL INVARIANTS SATISFIED — record is conforming.\n"));
+}
+
+#[test]
+fn test_render_warnings_keep_conformance() {
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    let opts = ValidationOptions { quality_checks: true, ..Default::default() };
+    let results = validate_all_with(&record, &opts);
+    let text = render_text(&results);
+    assert!(text.contains("[WARN] INV-Q1 — all four functions = 0.5"), "{}", text);
+    assert!(text.ends_with("record is conforming.\n"));
+    let batch = render_batch_text(&[results], None);
+    assert!(batch.starts_with("[WARN] record #0 — INV-Q1\n"), "{}", batch);
+    assert!(batch.ends_with("Result: ALL 1 RECORD(S) CONFORMING.\n"));
+}
+
+#[test]
+fn test_render_text_failures_omits_passes() {
+    let mut record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    let opts = ValidationOptions { quality_checks: true, ..Default::default() };
+    let text = render_text_failures(&validate_all_with(&record, &opts));
+    assert!(text.starts_with("[WARN] INV-Q1 — "), "{}", text);
+    assert!(!text.contains("[PASS]") && text.ends_with("record is conforming.\n"));
+
+    record.mode = "Halt".to_string();
+    let text = render_text_failures(&validate_all(&record));
+    assert_eq!(text.lines().filter(|l| l.starts_with("[FAIL]")).count(), 3, "{}", text);
+    assert!(!text.contains("[PASS]") && text.ends_with("record is malformed.\n"));
+}
+
+#[test]
+fn test_render_markdown_single() {
+    let mut record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    record.mode = "Alert".to_string();
+    let md = render_markdown(&record, &validate_all(&record));
+    assert!(md.starts_with("# P-MATRIX Conformance Report\n"));
+    assert!(md.contains("| INV-C1 | mode = threshold_map(risk_score) | FAIL |"));
+    assert!(md.contains("**MALFORMED** — violated: INV-C1, INV-C2, INV-C3."));
+    // Every table row has the same number of columns.
+    for row in md.lines().filter(|l| l.starts_with("| INV-")) {
+        assert_eq!(row.matches(" | ").count(), 3, "{}", row);
+    }
+}
+
+#[test]
+fn test_render_batch_markdown() {
+    let records = vec![
+        emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1001)).unwrap(),
+        emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(0)).unwrap(),
+    ];
+    let results = validate_records(&records);
+    let t1 = validate_stream_t1(&records);
+    let md = render_batch_markdown(&records, &results, t1);
+    assert!(md.contains("| Conforming records | 1 |"));
+    assert!(md.contains("| INV-T1 (stream) | FAIL at record #1 |"));
+    assert!(md.contains("| 1 | 0 | INV-R4 |"));
+
+    let text = render_batch_text(&results, t1);
+    assert!(text.contains("[FAIL] record #1 — INV-R4"));
+    assert!(text.contains("\nConformance: 1/2 record(s) (50.0%); first failure at record #1 (INV-R4).\n"), "{}", text);
+}
+
+#[test]
+fn test_invariant_failure_counts_ranked() {
+    let ok = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    let mut wrong_mode = ok.clone();
+    wrong_mode.mode = "Alert".to_string();
+    let mut wrong_level = ok.clone();
+    wrong_level.risk_level = "L4".to_string();
+    let records = vec![ok, wrong_mode.clone(), wrong_level, wrong_mode];
+    let results = validate_records(&records);
+
+    let counts = invariant_failure_counts(&results);
+    assert_eq!(counts.get("INV-C1"), Some(&2));
+    assert_eq!(counts.get("INV-C2"), Some(&3));
+    assert_eq!(counts.get("INV-C3"), Some(&3));
+    assert_eq!(counts.get("INV-R1"), None);
+
+    let text = render_batch_text(&results, None);
+    let c2 = text.find("  INV-C2 — 3 record(s)").unwrap();
+    let c3 = text.find("  INV-C3 — 3 record(s)").unwrap();
+    let c1 = text.find("  INV-C1 — 2 record(s)").unwrap();
+    assert!(c2 < c3 && c3 < c1);
+
+    let md = render_batch_markdown(&records, &results, None);
+    assert!(md.contains("## Failures by Invariant"));
+    assert!(md.contains("| INV-C2 | risk_level = level_map(mode) | 3 |"));
+}
+
+// ============================================================
+// Stats rendering
+// ============================================================
+
+#[test]
+fn test_render_stats_colored() {
+    let mut records: Vec<_> = (0..3).map(|i| emit_demo_record(0.1, 0.1, 0.1, 0.1, Some(1000 + i)).unwrap()).collect();
+    let stats = stream_stats(&records);
+    let plain = render_stats(&stats, &[], &[]);
+    assert!(!plain.contains('\x1b') && !plain.contains("INV-T1"), "{}", plain);
+    assert_eq!(render_stats_colored(&stats, &[], &[], TimestampStyle::Epoch, false), plain);
+    let colored = render_stats_colored(&stats, &[], &[], TimestampStyle::Epoch, true);
+    assert!(colored.contains("\x1b[31mrisk_score "), "{:?}", colored);
+
+    records[2].timestamp = 900;
+    let stats = stream_stats(&records);
+    assert!(render_stats(&stats, &[], &[]).ends_with("INV-T1           1 record(s) earlier than their predecessor\n"));
+    let colored = render_stats_colored(&stats, &[], &[], TimestampStyle::Epoch, true);
+    assert!(colored.ends_with("\x1b[1;31mINV-T1           1 record(s) earlier than their predecessor\x1b[0m\n"));
+
+    let healthy = stream_stats(&[emit_demo_record(0.9, 0.9, 0.9, 0.9, Some(1)).unwrap()]);
+    let colored = render_stats_colored(&healthy, &[], &[], TimestampStyle::Epoch, true);
+    assert!(colored.contains("\x1b[32mrisk_score "), "{:?}", colored);
+}
+
+// ============================================================
+// Timestamp rendering
+// ============================================================
+
+#[test]
+fn test_parse_utc_offset() {
+    assert_eq!(parse_utc_offset("UTC").unwrap(), 0);
+    assert_eq!(parse_utc_offset("Z").unwrap(), 0);
+    assert_eq!(parse_utc_offset("+02:00").unwrap(), 7200);
+    assert_eq!(parse_utc_offset("-0530").unwrap(), -19800);
+    for bad in ["02:00", "+2:00", "+24:00", "+02:60", "+02:0a", "Europe/Paris", "+020:0"] {
+        assert!(parse_utc_offset(bad).is_err(), "{}", bad);
+    }
+}
+
+#[test]
+fn test_reports_explain_with_validation_options() {
+    // Alert is right for 0.5 under the conservative profile.
+    let mut record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    record.mode = "Alert".to_string();
+    record.risk_level = "L4".to_string();
+    let opts = ValidationOptions { partition_map: builtin_profile("conservative").unwrap(), ..Default::default() };
+    let results = validate_all_with(&record, &opts);
+    assert!(results.iter().all(|r| r.passed));
+    let md = render_markdown_with(&record, &results, &opts, TimestampStyle::Epoch);
+    assert!(!md.contains("**Consistency:**"), "{}", md);
+    assert!(!render_inspect_with(&record, &results, &opts).contains("// consistency:"));
+    assert!(render_inspect(&record, &validate_all(&record)).contains("// consistency:"));
+}
+
+#[test]
+fn test_epoch_style_is_raw() {
+    assert_eq!(format_timestamp(1_707_500_000, TimestampStyle::Epoch), "1707500000");
+    let record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    let md = render_markdown(&record, &validate_all(&record));
+    assert!(md.contains("- **Timestamp:** 1000\n"));
+}
+
+#[cfg(feature = "chrono")]
+#[test]
+fn test_rfc3339_style() {
+    let utc = TimestampStyle::Rfc3339 { utc_offset_secs: 0 };
+    assert_eq!(format_timestamp(1_707_500_000, utc), "1707500000 (2024-02-09T17:33:20Z)");
+    let tokyo = TimestampStyle::Rfc3339 { utc_offset_secs: 9 * 3600 };
+    assert_eq!(format_timestamp(1_707_500_000, tokyo), "1707500000 (2024-02-10T02:33:20+09:00)");
+    assert_eq!(format_timestamp(u64::MAX, utc), format!("{} (out of range)", u64::MAX));
+
+    let records = vec![emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1_707_500_000)).unwrap()];
+    let md = render_batch_markdown_with(&records, &validate_records(&records), None, utc);
+    assert!(md.contains("| First – last timestamp | 1707500000 (2024-02-09T17:33:20Z) – "));
+}
+
+#[cfg(not(feature = "chrono"))]
+#[test]
+fn test_rfc3339_style_without_feature_is_raw() {
+    let utc = TimestampStyle::Rfc3339 { utc_offset_secs: 0 };
+    assert_eq!(format_timestamp(1_707_500_000, utc), "1707500000");
+}
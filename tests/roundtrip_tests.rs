@@ -0,0 +1,135 @@
+// roundtrip_tests.rs — Cross-Format Round-Trip Tests
+//
+// Every valid record must survive a round trip through each encoding the
+// crate can both write and read. New encodings are added to `codecs()` and
+// are then covered by every generator below. YAML and CBOR are detected on
+// input but not decoded, and there is no CSV encoding, so none of them has a
+// codec. No codec may lose precision: JSON relies on serde_json's
+// `float_roundtrip` parser, since the default one can be off by one ULP.
+//
+// Tests cover:
+// - Random valid records from a seeded generator, including boundary values
+// - Valid records drawn from the `arbitrary` impls (feature-gated)
+// - The D1-A §5 example record
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::input::{expand_functions_arrays, parse_stream, parse_strict};
+use pmatrix_encoder::invariants::is_valid;
+use pmatrix_encoder::output::{write_records_array, write_records_ndjson};
+use pmatrix_encoder::packed::{from_packed, to_packed};
+use pmatrix_encoder::sample::{Rng, SplitMix64};
+use pmatrix_encoder::schema::{spec_example_record, RuntimeStateRecord, SEQUENCE_SCHEMA_VERSION};
+use pmatrix_encoder::PmatrixError;
+
+type Codec = fn(&RuntimeStateRecord) -> Result<RuntimeStateRecord, PmatrixError>;
+
+fn single(mut records: Vec<RuntimeStateRecord>) -> RuntimeStateRecord {
+    assert_eq!(records.len(), 1);
+    records.remove(0)
+}
+
+/// Each encoding's name with an encode-then-decode function.
+fn codecs() -> Vec<(&'static str, Codec)> {
+    vec![
+        ("json", |r| parse_strict(&serde_json::to_string(r)?)),
+        ("json-pretty", |r| parse_strict(&serde_json::to_string_pretty(r)?)),
+        ("json-value", |r| Ok(serde_json::from_value(serde_json::to_value(r)?)?)),
+        ("ndjson", |r| {
+            let mut out = Vec::new();
+            write_records_ndjson(std::iter::once(r.clone()), &mut out)?;
+            Ok(single(parse_stream(&String::from_utf8(out).unwrap())?))
+        }),
+        ("json-array", |r| {
+            let mut out = Vec::new();
+            write_records_array(std::iter::once(r.clone()), &mut out)?;
+            Ok(single(parse_stream(&String::from_utf8(out).unwrap())?))
+        }),
+        ("functions-array", |r| {
+            let mut value = serde_json::to_value(r)?;
+            let f = &r.functions;
+            value["functions"] = serde_json::json!([f.baseline, f.norm, f.stability, f.meta_control]);
+            parse_strict(&expand_functions_arrays(&value.to_string())?)
+        }),
+        ("packed", |r| from_packed(&to_packed(r)?)),
+        #[cfg(feature = "canonical")]
+        ("canonical", |r| Ok(serde_json::from_slice(&pmatrix_encoder::fingerprint::canonical_bytes(r))?)),
+    ]
+}
+
+fn assert_round_trips(record: &RuntimeStateRecord) {
+    for (name, codec) in codecs() {
+        let decoded = codec(record).unwrap_or_else(|e| panic!("{} failed on {:?}: {}", name, record, e));
+        assert_eq!(&decoded, record, "{} changed the record", name);
+        // Equality treats -0.0 as 0.0; the encoded bits must survive too.
+        assert_eq!(decoded.risk_score.to_bits(), record.risk_score.to_bits(), "{}", name);
+        assert_eq!(decoded.functions.baseline.to_bits(), record.functions.baseline.to_bits(), "{}", name);
+    }
+}
+
+// A function value: uniform most of the time, otherwise a boundary.
+fn function_value(rng: &mut SplitMix64) -> f64 {
+    match rng.below(8) {
+        0 => [0.0, -0.0, 0.2, 0.4, 0.6, 0.8, 1.0][rng.below(7) as usize],
+        1 => 0.6f64.next_down(),
+        _ => (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64,
+    }
+}
+
+fn random_record(rng: &mut SplitMix64) -> RuntimeStateRecord {
+    let timestamp = match rng.below(4) {
+        0 => u64::MAX,
+        _ => 1 + rng.below(u64::MAX - 1),
+    };
+    let f: Vec<f64> = (0..4).map(|_| function_value(rng)).collect();
+    let mut record = emit_demo_record(f[0], f[1], f[2], f[3], Some(timestamp)).unwrap();
+    if rng.below(2) == 0 {
+        record.schema_version = SEQUENCE_SCHEMA_VERSION.to_string();
+        record.sequence_number = Some(rng.next_u64());
+    }
+    record
+}
+
+#[test]
+fn test_random_valid_records_round_trip() {
+    let mut rng = SplitMix64::new(0x5eed);
+    for _ in 0..2000 {
+        let record = random_record(&mut rng);
+        assert!(is_valid(&record), "{:?}", record);
+        assert_round_trips(&record);
+    }
+}
+
+#[test]
+fn test_spec_example_round_trips() {
+    assert_round_trips(&spec_example_record());
+}
+
+#[cfg(feature = "arbitrary")]
+#[test]
+fn test_arbitrary_valid_records_round_trip() {
+    use arbitrary::{Arbitrary, Unstructured};
+
+    // Arbitrary records are mostly invalid, so the generated functions,
+    // timestamp and sequence fields are kept and the scores re-derived.
+    let mut rng = SplitMix64::new(7);
+    let mut checked = 0;
+    for _ in 0..5000 {
+        let bytes: Vec<u8> = (0..64).flat_map(|_| rng.next_u64().to_le_bytes()).collect();
+        let generated = RuntimeStateRecord::arbitrary(&mut Unstructured::new(&bytes)).unwrap();
+        let f = &generated.functions;
+        let Ok(mut record) =
+            emit_demo_record(f.baseline, f.norm, f.stability, f.meta_control, Some(generated.timestamp))
+        else {
+            continue;
+        };
+        if generated.schema_version == SEQUENCE_SCHEMA_VERSION {
+            record.schema_version = generated.schema_version;
+            record.sequence_number = generated.sequence_number;
+        }
+        if is_valid(&record) {
+            assert_round_trips(&record);
+            checked += 1;
+        }
+    }
+    assert!(checked > 1000, "only {} valid records generated", checked);
+}
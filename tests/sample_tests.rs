@@ -0,0 +1,54 @@
+// sample_tests.rs — Reservoir Sampling Tests
+//
+// Tests cover:
+// - Sample size and short streams
+// - Determinism for a fixed seed
+// - Rough uniformity of selection
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::input::read_records;
+use pmatrix_encoder::sample::*;
+use pmatrix_encoder::schema::RuntimeStateRecord;
+
+fn stream(n: u64) -> impl Iterator<Item = RuntimeStateRecord> {
+    (0..n).map(|i| emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1 + i)).unwrap())
+}
+
+#[test]
+fn test_reservoir_sample_sizes() {
+    let mut rng = SplitMix64::new(42);
+    assert_eq!(reservoir_sample(stream(100), 10, &mut rng).len(), 10);
+    assert!(reservoir_sample(stream(100), 0, &mut rng).is_empty());
+    let short: Vec<u64> = reservoir_sample(stream(3), 10, &mut rng).iter().map(|r| r.timestamp).collect();
+    assert_eq!(short, vec![1, 2, 3]);
+}
+
+#[test]
+fn test_reservoir_sample_deterministic_for_seed() {
+    let draw = |seed| -> Vec<u64> {
+        reservoir_sample(stream(1000), 5, &mut SplitMix64::new(seed)).iter().map(|r| r.timestamp).collect()
+    };
+    assert_eq!(draw(7), draw(7));
+    assert_ne!(draw(7), draw(8));
+}
+
+#[test]
+fn test_reservoir_sample_roughly_uniform() {
+    // Each of 10 records should be picked close to 20% of the time for k = 2.
+    let mut hits = [0usize; 10];
+    let mut rng = SplitMix64::new(1);
+    for _ in 0..5000 {
+        for r in reservoir_sample(stream(10), 2, &mut rng) {
+            hits[(r.timestamp - 1) as usize] += 1;
+        }
+    }
+    assert!(hits.iter().all(|&h| (800..1200).contains(&h)), "{:?}", hits);
+}
+
+#[test]
+fn test_read_records_streams_ndjson() {
+    let ndjson: String = stream(3).map(|r| serde_json::to_string(&r).unwrap() + "\n").collect();
+    let records: Vec<_> = read_records(ndjson.as_bytes()).collect::<Result<_, _>>().unwrap();
+    assert_eq!(records.len(), 3);
+    assert!(read_records(&b"{\"oops\": 1}"[..]).next().unwrap().is_err());
+}
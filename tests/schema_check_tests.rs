@@ -0,0 +1,101 @@
+// schema_check_tests.rs — JSON Schema Cross-Check Tests
+//
+// Needs the `json-schema` feature.
+//
+// Tests cover:
+// - A record schema accepting a conforming record
+// - Range, enum, required and additionalProperties violations with pointers
+// - `pattern` and local `$ref`s into `$defs`
+// - Rejection of malformed schemas and remote references
+
+#![cfg(feature = "json-schema")]
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::schema_check::schema_errors;
+
+use serde_json::{json, Value};
+
+fn record_schema() -> Value {
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": "P-MATRIX runtime state record",
+        "type": "object",
+        "required": ["spec_version", "schema_version", "timestamp", "functions",
+                     "stability_score", "risk_score", "mode", "risk_level"],
+        "additionalProperties": false,
+        "$defs": {
+            "unit": {"type": "number", "minimum": 0, "maximum": 1}
+        },
+        "properties": {
+            "spec_version": {"const": "pmatrix-3.5"},
+            "schema_version": {"type": "string", "pattern": "^[0-9]+\\.[0-9]+\\.[0-9]+$"},
+            "timestamp": {"type": "integer", "exclusiveMinimum": 0},
+            "functions": {
+                "type": "object",
+                "additionalProperties": false,
+                "properties": {
+                    "baseline": {"$ref": "#/$defs/unit"},
+                    "norm": {"$ref": "#/$defs/unit"},
+                    "stability": {"$ref": "#/$defs/unit"},
+                    "meta_control": {"$ref": "#/$defs/unit"}
+                }
+            },
+            "stability_score": {"$ref": "#/$defs/unit"},
+            "risk_score": {"$ref": "#/$defs/unit"},
+            "mode": {"enum": ["Optimal", "Normal", "Caution", "Alert", "Halt"]},
+            "risk_level": {"enum": ["L1", "L2", "L3", "L4", "L5"]}
+        }
+    })
+}
+
+fn record() -> Value {
+    serde_json::to_value(emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap()).unwrap()
+}
+
+// ============================================================
+// Verdicts
+// ============================================================
+
+#[test]
+fn test_conforming_record_passes() {
+    assert_eq!(schema_errors(&record_schema(), &record()).unwrap(), Vec::<String>::new());
+}
+
+#[test]
+fn test_violations_name_the_value() {
+    let mut r = record();
+    r["functions"]["norm"] = json!(1.5);
+    r["mode"] = json!("Calm");
+    r["extra"] = json!(true);
+    r.as_object_mut().unwrap().remove("risk_level");
+    let errors = schema_errors(&record_schema(), &r).unwrap();
+    assert_eq!(errors.len(), 4, "{:?}", errors);
+    assert!(errors.contains(&"/: \"risk_level\" is a required property".to_string()), "{:?}", errors);
+    assert!(errors.contains(&"/functions/norm: 1.5 is greater than the maximum of 1".to_string()), "{:?}", errors);
+    assert!(errors.iter().any(|e| e.starts_with("/mode: \"Calm\" is not one of")), "{:?}", errors);
+    assert!(errors.iter().any(|e| e.starts_with("/: Additional properties are not allowed ('extra'")), "{:?}", errors);
+}
+
+#[test]
+fn test_pattern_and_refs() {
+    let mut r = record();
+    r["schema_version"] = json!("1.0");
+    r["risk_score"] = json!(-0.5);
+    let errors = schema_errors(&record_schema(), &r).unwrap();
+    assert_eq!(errors.len(), 2, "{:?}", errors);
+    assert!(errors.iter().any(|e| e.starts_with("/schema_version: \"1.0\" does not match")), "{:?}", errors);
+    assert!(errors.contains(&"/risk_score: -0.5 is less than the minimum of 0".to_string()), "{:?}", errors);
+}
+
+// ============================================================
+// Schema errors
+// ============================================================
+
+#[test]
+fn test_malformed_schemas_are_rejected() {
+    assert!(schema_errors(&json!(3), &record()).is_err());
+    assert!(schema_errors(&json!({"minimum": "0"}), &json!(1)).is_err());
+    let remote = json!({"$ref": "https://example.com/record.json"});
+    let err = schema_errors(&remote, &record()).unwrap_err();
+    assert!(err.to_string().starts_with("invalid configuration: JSON Schema: "), "{}", err);
+}
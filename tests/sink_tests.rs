@@ -0,0 +1,82 @@
+// sink_tests.rs — Result Sink Tests
+//
+// Tests cover:
+// - Batch summaries and failure ranking
+// - Driving sinks from a batch, including INV-T1
+// - NDJSON output of the JSON sink
+// - Filtering out passing records with FailuresOnly
+// - Agreement of the summary rendering with render_batch_text
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::invariants::{validate_all, ValidationOptions};
+use pmatrix_encoder::report::{render_batch_record_line, render_batch_summary, render_batch_text};
+use pmatrix_encoder::schema::RuntimeStateRecord;
+use pmatrix_encoder::sink::*;
+
+fn batch() -> Vec<RuntimeStateRecord> {
+    let mut records: Vec<_> = (0..3).map(|i| emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000 + i)).unwrap()).collect();
+    records[1].mode = "Halt".to_string();
+    records[2].timestamp = 900;
+    records
+}
+
+// ============================================================
+// Summaries and sinks
+// ============================================================
+
+#[test]
+fn test_counting_sink_and_summary() {
+    let mut sink = CountingSink::default();
+    let summary = validate_into_sink(&batch(), &ValidationOptions::default(), &mut sink);
+    assert_eq!((sink.records, sink.conforming), (3, 2));
+    assert_eq!(sink.summary.as_ref(), Some(&summary));
+    assert_eq!(summary.t1_violation, Some(2));
+    assert!(!summary.is_conforming());
+    assert_eq!(summary.ranked_failures(), vec![("INV-C1", 1), ("INV-C2", 1), ("INV-C3", 1)]);
+    assert_eq!(summary.first_failure, Some(FirstFailure { index: 1, id: "INV-C1" }));
+    assert!((summary.conformance_rate() - 200.0 / 3.0).abs() < 1e-9);
+    assert!(!summary.t1_held());
+    assert_eq!(BatchSummary::default().conformance_rate(), 100.0);
+}
+
+#[test]
+fn test_json_sink_lines() {
+    let mut sink = JsonSink::new(Vec::new());
+    validate_into_sink(&batch(), &ValidationOptions::default(), &mut sink);
+    let out = String::from_utf8(sink.into_inner().unwrap()).unwrap();
+    let lines: Vec<serde_json::Value> = out.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0]["conforming"], true);
+    assert_eq!(lines[1]["failed"], serde_json::json!(["INV-C1", "INV-C2", "INV-C3"]));
+    let codes: Vec<_> = lines[1]["failures"].as_array().unwrap().iter().map(|f| f["code"].clone()).collect();
+    assert_eq!(codes, ["MODE_SCORE_MISMATCH", "LEVEL_MODE_MISMATCH", "INCONSISTENT_CLASSIFICATION"]);
+    assert!(lines[1]["failures"][0]["detail"].as_str().unwrap().starts_with("risk_score="));
+    assert_eq!(lines[0]["failures"], serde_json::json!([]));
+    assert_eq!(lines[3]["summary"]["records"], 3);
+    assert_eq!(lines[3]["summary"]["t1_violation"], 2);
+    assert_eq!(lines[3]["summary"]["t1_held"], false);
+    assert_eq!(lines[3]["summary"]["first_failure"], serde_json::json!({ "index": 1, "id": "INV-C1" }));
+    assert!(lines[3]["summary"]["conformance_rate"].as_f64().unwrap() > 66.6);
+}
+
+#[test]
+fn test_failures_only_forwards_failing_records() {
+    let mut sink = JsonSink::new(Vec::new());
+    let summary = validate_into_sink(&batch(), &ValidationOptions::default(), &mut FailuresOnly(&mut sink));
+    let out = String::from_utf8(sink.into_inner().unwrap()).unwrap();
+    let lines: Vec<serde_json::Value> = out.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+    // Record #2 only breaks the stream-level INV-T1, so just #1 is listed.
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["index"], 1);
+    assert_eq!(lines[1]["summary"]["records"], 3);
+    assert_eq!(summary.records, 3);
+}
+
+#[test]
+fn test_text_pieces_match_render_batch_text() {
+    let records = batch();
+    let results: Vec<_> = records.iter().map(validate_all).collect();
+    let mut pieces: String = results.iter().enumerate().map(|(i, rs)| render_batch_record_line(i, rs)).collect();
+    pieces.push_str(&render_batch_summary(&BatchSummary::from_results(&results, Some(2))));
+    assert_eq!(pieces, render_batch_text(&results, Some(2)));
+}
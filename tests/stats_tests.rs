@@ -0,0 +1,393 @@
+// stats_tests.rs — Stream Statistics Tests
+//
+// Tests cover:
+// - Aggregate min/mean/max, time span and INV-T1 violation count
+// - Constant-memory accumulation over lazily read NDJSON
+// - Percentile interpolation, empty and single-element streams
+// - Flatline (frozen emitter) detection
+// - Sustained high-risk periods
+// - Mode transitions and flapping detection
+// - Rolling risk averages and smoothed envelopes
+// - Rolling dominant mode
+// - Per-mode counts, breakdown, dominant mode and the tally rendering
+// - Per-mode statistics with time spent in each mode
+// - Record age and staleness relative to the current time
+// - Timestamp differences, durations and spans at u64 extremes
+// - NaN policies for sorting, summaries, percentiles and per-record checks
+// - Deterministic tie-breaks when sorting by risk
+
+use pmatrix_encoder::emit_demo_record;
+use pmatrix_encoder::report::{render_mode_stats, render_tally};
+use pmatrix_encoder::schema::RuntimeStateRecord;
+use pmatrix_encoder::stats::*;
+use pmatrix_encoder::PmatrixError;
+
+fn stream(baselines: &[f64]) -> Vec<RuntimeStateRecord> {
+    baselines
+        .iter()
+        .enumerate()
+        .map(|(i, &b)| emit_demo_record(b, b, b, b, Some(1000 + i as u64)).unwrap())
+        .collect()
+}
+
+// ============================================================
+// stream_stats
+// ============================================================
+
+#[test]
+fn test_stream_stats_summary() {
+    let stats = stream_stats(&stream(&[1.0, 0.5, 0.0]));
+    assert_eq!(stats.count, 3);
+    assert_eq!(stats.first_timestamp, Some(1000));
+    assert_eq!(stats.last_timestamp, Some(1002));
+    assert_eq!(stats.risk, Some(ScoreSummary { min: 0.0, mean: 0.5, max: 1.0 }));
+    assert_eq!(stats.t1_violations, 0);
+}
+
+#[test]
+fn test_stream_stats_counts_t1_violations() {
+    let mut records = stream(&[0.5, 0.5, 0.5, 0.5]);
+    records[1].timestamp = 900;
+    records[3].timestamp = 1001;
+    // 900 < 1000, and 1001 < 1002; 1002 after 900 is fine.
+    assert_eq!(stream_stats(&records).t1_violations, 2);
+}
+
+#[test]
+fn test_stream_stats_empty() {
+    let stats = stream_stats(&[]);
+    assert_eq!(stats.count, 0);
+    assert_eq!(stats.risk, None);
+    assert_eq!(stats.first_timestamp, None);
+}
+
+#[test]
+fn test_stats_accumulator_matches_stream_stats() {
+    let mut records = stream(&[1.0, 0.5, 0.0, 0.25]);
+    records[2].timestamp = 900;
+    let expected = stream_stats(&records);
+    assert_eq!(expected.min_timestamp, Some(900));
+    assert_eq!(expected.max_timestamp, Some(1003));
+    assert_eq!(expected.last_timestamp, Some(1003));
+
+    let ndjson: String = records.iter().map(|r| serde_json::to_string(r).unwrap() + "\n").collect();
+    let streamed = stream_stats_iter(pmatrix_encoder::input::read_records(ndjson.as_bytes())).unwrap();
+    assert_eq!(streamed, expected);
+
+    let broken = ndjson + "{ not json\n";
+    assert!(stream_stats_iter(pmatrix_encoder::input::read_records(broken.as_bytes())).is_err());
+}
+
+// ============================================================
+// Percentiles
+// ============================================================
+
+#[test]
+fn test_percentiles_linear_interpolation() {
+    let values = [0.4, 0.1, 0.3, 0.2, 0.5];
+    let p = percentiles(&values, &[0.0, 50.0, 90.0, 100.0]);
+    assert_eq!(p[0], (0.0, 0.1));
+    assert_eq!(p[1], (50.0, 0.3));
+    assert!((p[2].1 - 0.46).abs() < 1e-12);
+    assert_eq!(p[3], (100.0, 0.5));
+}
+
+#[test]
+fn test_percentiles_empty_and_single() {
+    assert!(percentiles(&[], &DEFAULT_PERCENTILES).is_empty());
+    let single = risk_percentiles(&stream(&[0.75]), &DEFAULT_PERCENTILES);
+    assert_eq!(single, vec![(50.0, 0.25), (90.0, 0.25), (99.0, 0.25)]);
+}
+
+#[test]
+fn test_percentiles_clamped() {
+    let p = percentiles(&[0.1, 0.9], &[-10.0, 150.0]);
+    assert_eq!(p, vec![(-10.0, 0.1), (150.0, 0.9)]);
+}
+
+// ============================================================
+// Flatline detection
+// ============================================================
+
+#[test]
+fn test_detect_flatline_runs() {
+    let records = stream(&[0.1, 0.5, 0.5, 0.5, 0.2, 0.7, 0.7, 0.3, 0.3, 0.3, 0.3]);
+    assert_eq!(detect_flatline(&records, 3), vec![(1, 3), (7, 10)]);
+    assert_eq!(detect_flatline(&records, 2), vec![(1, 3), (5, 6), (7, 10)]);
+    assert_eq!(detect_flatline(&records, 5), Vec::<(usize, usize)>::new());
+    assert!(detect_flatline(&[], 2).is_empty());
+}
+
+#[test]
+fn test_detect_flatline_within_eps() {
+    let records = stream(&[0.5, 0.5 + 1e-12, 0.5 - 1e-12, 0.5 + 2e-6]);
+    assert!(detect_flatline(&records, 2).is_empty());
+    assert_eq!(detect_flatline_within(&records, 3, 1e-9), vec![(0, 2)]);
+    // Drift is measured against the first record of the run.
+    let drifting = stream(&[0.5, 0.5005, 0.501, 0.5015]);
+    assert_eq!(detect_flatline_within(&drifting, 3, 0.0012), vec![(0, 2)]);
+}
+
+#[test]
+fn test_sustained_high_risk_periods() {
+    // baseline 0.1 → L5, 0.3 → L4, 0.5 → L3; records are one second apart.
+    let mut records = stream(&[0.3, 0.1, 0.3, 0.5, 0.1, 0.5, 0.1, 0.1]);
+    records[7].timestamp = 1010;
+    assert_eq!(sustained_high_risk(&records, "L4", 0), vec![(0, 2, 2), (4, 4, 0), (6, 7, 4)]);
+    assert_eq!(sustained_high_risk(&records, "L4", 3), vec![(6, 7, 4)]);
+    assert_eq!(sustained_high_risk(&records, "L5", 0), vec![(1, 1, 0), (4, 4, 0), (6, 7, 4)]);
+    assert!(sustained_high_risk(&records, "L9", 0).is_empty());
+    assert!(sustained_high_risk(&[], "L4", 0).is_empty());
+}
+
+#[test]
+fn test_mode_transitions_and_flapping() {
+    // baseline 0.5 → Caution, 0.3 → Alert.
+    let records = stream(&[0.5, 0.5, 0.3, 0.5, 0.3, 0.5, 0.5, 0.5, 0.5, 0.3, 0.5]);
+    let transitions = mode_transitions(&records);
+    assert_eq!(transitions[0], (2, "Caution", "Alert"));
+    assert_eq!(transitions.iter().map(|t| t.0).collect::<Vec<_>>(), vec![2, 3, 4, 5, 9, 10]);
+
+    assert_eq!(detect_flapping(&records, 4, 3), vec![(1, 5, 4)]);
+    assert_eq!(detect_flapping(&records, 4, 2), vec![(0, 6, 4), (7, 10, 2)]);
+    assert!(detect_flapping(&records, 4, 5).is_empty());
+    // A stream shorter than the window is a single window.
+    assert_eq!(detect_flapping(&records[..4], 100, 2), vec![(0, 3, 2)]);
+    assert!(detect_flapping(&[], 4, 1).is_empty());
+}
+
+#[test]
+fn test_rolling_risk_mean_and_modes() {
+    // Risk scores 0.75, 0.25, 0.5, 1.0.
+    let records = stream(&[0.25, 0.75, 0.5, 0.0]);
+    assert_eq!(rolling_risk_mean(&records, 2), vec![0.75, 0.5, 0.375, 0.75]);
+    assert_eq!(rolling_risk_mean(&records, 1), vec![0.75, 0.25, 0.5, 1.0]);
+    assert_eq!(rolling_risk_mean(&records, 0), rolling_risk_mean(&records, 1));
+    // A window longer than the stream averages everything seen so far.
+    assert_eq!(rolling_risk_mean(&records, 100), vec![0.75, 0.5, 0.5, 0.625]);
+    assert_eq!(rolling_risk_modes(&records, 2), vec![Some("Alert"), Some("Caution"), Some("Normal"), Some("Alert")]);
+    assert!(rolling_risk_mean(&[], 3).is_empty());
+    // A NaN or infinite score affects only the windows that hold it.
+    let mut spiky = stream(&[0.25, 0.75, 0.5, 0.0]);
+    spiky[1].risk_score = f64::NAN;
+    spiky[2].risk_score = f64::INFINITY;
+    let means = rolling_risk_mean(&spiky, 2);
+    assert!(means[1].is_nan() && means[2].is_nan());
+    assert_eq!((means[0], means[3]), (0.75, f64::INFINITY));
+    assert_eq!(rolling_risk_mean(&spiky[2..], 1), vec![f64::INFINITY, 1.0]);
+
+    let smoothed = smooth(&records, 2);
+    assert_eq!(*smoothed[2].record, records[2]);
+    assert_eq!((smoothed[2].smoothed_risk_score, smoothed[2].smoothed_mode.as_deref()), (0.375, Some("Normal")));
+    let json = serde_json::to_string(&smoothed[2]).unwrap();
+    assert_eq!(serde_json::from_str::<SmoothedRecord>(&json).unwrap(), smoothed[2]);
+}
+
+#[test]
+fn test_rolling_dominant_mode() {
+    // Modes Optimal, Halt, Optimal, Halt, Halt, Optimal.
+    let mut records = stream(&[1.0, 0.0, 1.0, 0.0, 0.0, 1.0]);
+    let (o, h) = (Some("Optimal"), Some("Halt"));
+    // Startup windows hold fewer records; the 1–1 tie at #1 goes to Halt.
+    assert_eq!(rolling_dominant_mode(&records, 3), vec![o, h, o, h, h, h]);
+    assert_eq!(rolling_dominant_mode(&records, 0), vec![o, h, o, h, h, o]);
+    assert_eq!(rolling_dominant_mode(&records, 100), vec![o, h, o, h, h, h]);
+    assert!(rolling_dominant_mode(&[], 3).is_empty());
+
+    // Unknown modes are not counted.
+    records[1].mode = "Bogus".to_string();
+    assert_eq!(rolling_dominant_mode(&records[..2], 1), vec![o, None]);
+    assert_eq!(rolling_dominant_mode(&records[..2], 2), vec![o, o]);
+
+    let annotated = annotate_dominant_mode(&records, 3);
+    assert_eq!(*annotated[2].record, records[2]);
+    assert_eq!(annotated[2].dominant_mode.as_deref(), Some("Optimal"));
+    let json = serde_json::to_string(&annotated[2]).unwrap();
+    assert_eq!(serde_json::from_str::<DominantModeRecord>(&json).unwrap(), annotated[2]);
+}
+
+// ============================================================
+// Mode tally
+// ============================================================
+
+#[test]
+fn test_mode_counts_and_tally() {
+    let mut records = stream(&[0.0, 0.0, 1.0, 0.5]);
+    records[3].mode = "Bogus".into();
+    let counts = mode_counts(&records);
+    assert_eq!(counts.values().sum::<usize>(), 4);
+    assert_eq!(counts["Bogus"], 1);
+    assert!(!counts.contains_key("Normal"));
+    let tally = render_tally(&counts);
+    let lines: Vec<&str> = tally.lines().collect();
+    assert_eq!(lines.len(), 7);
+    assert!(lines[0].starts_with("Optimal") && lines[4].starts_with("Halt"), "{}", tally);
+    assert_eq!(lines[1], "Normal   0");
+    assert_eq!(lines[5], "Bogus    1");
+    assert_eq!(lines[6], "total    4");
+}
+
+#[test]
+fn test_record_age_secs() {
+    let mut record = emit_demo_record(0.5, 0.5, 0.5, 0.5, Some(1000)).unwrap();
+    assert_eq!(record_age_secs(&record, 1060), 60);
+    assert_eq!(record_age_secs(&record, 1000), 0);
+    assert_eq!(record_age_secs(&record, 940), -60);
+    record.timestamp = u64::MAX;
+    assert_eq!(record_age_secs(&record, 0), i64::MIN);
+    assert_eq!(record_age_secs(&record.with_timestamp(0), u64::MAX), i64::MAX);
+}
+
+#[test]
+fn test_timestamp_arithmetic_at_extremes() {
+    assert_eq!(timestamp_delta_secs(0, u64::MAX), i64::MAX);
+    assert_eq!(timestamp_delta_secs(u64::MAX, 0), i64::MIN);
+    assert_eq!(timestamp_delta_secs(u64::MAX - 1, u64::MAX), 1);
+    assert_eq!(timestamp_delta_secs(u64::MAX, u64::MAX - 1), -1);
+
+    let mut records = stream(&[0.9, 0.9, 0.9, 0.9]);
+    for (r, ts) in records.iter_mut().zip([0, u64::MAX, 0, u64::MAX]) {
+        r.timestamp = ts;
+    }
+    // Two forward jumps of u64::MAX saturate rather than overflow.
+    assert_eq!(stats_by_mode(&records)["Optimal"].duration_secs, u64::MAX);
+    assert_eq!(sustained_high_risk(&records, "L1", 0), vec![(0, 3, u64::MAX)]);
+    let stats = stream_stats(&records);
+    assert_eq!((stats.min_timestamp, stats.max_timestamp), (Some(0), Some(u64::MAX)));
+
+    let latest = &records[1];
+    assert!(!is_stale(latest, u64::MAX, 0));
+    assert!(is_stale(&records[0], u64::MAX, u64::MAX - 1));
+    assert!(!is_stale(&records[0], u64::MAX, u64::MAX));
+    assert!(!is_stale(latest, 0, 0));
+}
+
+#[test]
+fn test_stats_by_mode_durations() {
+    // baseline 0.9 → Optimal, 0.1 → Halt; the last record holds no time.
+    let mut records = stream(&[0.9, 0.9, 0.1, 0.9]);
+    records[3].timestamp = 1010;
+    let by_mode = stats_by_mode(&records);
+    assert_eq!(by_mode.len(), 2);
+    let (optimal, halt) = (by_mode["Optimal"], by_mode["Halt"]);
+    assert_eq!((optimal.count, optimal.duration_secs), (3, 2));
+    assert_eq!((halt.count, halt.duration_secs), (1, 8));
+    assert!((optimal.mean_risk - records[0].risk_score).abs() < 1e-12);
+
+    // A backwards step contributes no time.
+    records[1].timestamp = 2000;
+    assert_eq!(stats_by_mode(&records)["Optimal"].duration_secs, 1000);
+    assert!(stats_by_mode(&[]).is_empty());
+
+    let text = render_mode_stats(&by_mode);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[1], "Normal   count=0 mean_risk=- time=0 s");
+    assert!(lines[4].starts_with("Halt     count=1 mean_risk=0.9") && lines[4].ends_with("time=8 s"), "{}", text);
+}
+
+#[test]
+fn test_mode_breakdown_and_dominant_mode() {
+    let empty = stream_stats(&[]);
+    assert_eq!(empty.dominant_mode(), None);
+    assert!(empty.mode_breakdown().all(|(_, n)| n == 0));
+
+    // Baseline 1.0 → Optimal, 0.0 → Halt: one each is a tie, won by Halt.
+    let mut records = stream(&[1.0, 0.0]);
+    assert_eq!(stream_stats(&records).dominant_mode(), Some("Halt"));
+    records.extend(stream(&[1.0]));
+    let stats = stream_stats(&records);
+    assert_eq!(stats.dominant_mode(), Some("Optimal"));
+    let breakdown: Vec<_> = stats.mode_breakdown().collect();
+    assert_eq!(breakdown, vec![("Optimal", 2), ("Normal", 0), ("Caution", 0), ("Alert", 0), ("Halt", 1)]);
+
+    records[1].mode = "Bogus".into();
+    records[2].mode = "Bogus".into();
+    let stats = stream_stats(&records);
+    assert_eq!(stats.mode_breakdown().last(), Some(("Bogus", 2)));
+    assert_eq!(stats.dominant_mode(), Some("Bogus"));
+}
+
+// ============================================================
+// NaN policies
+// ============================================================
+
+fn with_nan_risk() -> Vec<RuntimeStateRecord> {
+    let mut records = stream(&[0.0, 0.5, 1.0]);
+    records[1].risk_score = f64::NAN;
+    records
+}
+
+#[test]
+fn test_nan_policy_error_is_default() {
+    assert_eq!(NanPolicy::default(), NanPolicy::Error);
+    let records = with_nan_risk();
+    let err = stream_stats_with(&records, NanPolicy::Error).unwrap_err();
+    assert!(matches!(err, PmatrixError::Parse(_)), "{:?}", err);
+    assert!(err.to_string().contains("risk_score #1 is NaN"), "{}", err);
+    assert!(matches!(sort_by_risk(records.clone(), NanPolicy::Error), Err(PmatrixError::Parse(_))));
+    assert!(risk_percentiles_with(&records, &[50.0], NanPolicy::Error).is_err());
+    // Without NaN every policy agrees with the plain functions.
+    let clean = stream(&[0.0, 0.5, 1.0]);
+    assert_eq!(stream_stats_with(&clean, NanPolicy::Error).unwrap(), stream_stats(&clean));
+}
+
+#[test]
+fn test_nan_policy_check_per_record() {
+    let records = with_nan_risk();
+    let err = NanPolicy::Error.check(7, &records[1]).unwrap_err();
+    assert!(err.to_string().contains("risk_score #7 is NaN"), "{}", err);
+    assert!(NanPolicy::Error.check(0, &records[0]).is_ok());
+    let mut unstable = records[0].clone();
+    unstable.stability_score = f64::NAN;
+    assert!(NanPolicy::Error.check(0, &unstable).unwrap_err().to_string().contains("stability_score #0"));
+    for policy in [NanPolicy::Lowest, NanPolicy::Highest, NanPolicy::Skip] {
+        assert!(policy.check(1, &records[1]).is_ok());
+    }
+}
+
+#[test]
+fn test_nan_policy_sort_by_risk() {
+    let order = |policy| -> Vec<u64> {
+        sort_by_risk(with_nan_risk(), policy).unwrap().iter().map(|r| r.timestamp).collect()
+    };
+    // Baseline 0.0 has the highest risk, so clean order is 1002, 1000.
+    assert_eq!(order(NanPolicy::Lowest), vec![1001, 1002, 1000]);
+    assert_eq!(order(NanPolicy::Highest), vec![1002, 1000, 1001]);
+    assert_eq!(order(NanPolicy::Skip), vec![1002, 1000]);
+}
+
+#[test]
+fn test_sort_by_risk_tie_breaks() {
+    // Equal scores: level first, then timestamp, then input order.
+    let mut records = stream(&[0.5, 0.5, 0.5, 0.5, 0.0]);
+    records[0].risk_level = "L4".to_string();
+    records[1].timestamp = 2000;
+    records[3].timestamp = 1002;
+    records[3].sequence_number = Some(7);
+    let sorted = sort_by_risk(records.clone(), NanPolicy::Error).unwrap();
+    let keys: Vec<(u64, &str)> = sorted.iter().map(|r| (r.timestamp, r.risk_level.as_str())).collect();
+    assert_eq!(keys, vec![(1002, "L3"), (1002, "L3"), (2000, "L3"), (1000, "L4"), (1004, "L5")]);
+    assert_eq!(sorted[1].sequence_number, Some(7));
+    assert_eq!(sort_by_risk(records, NanPolicy::Error).unwrap(), sorted);
+}
+
+#[test]
+fn test_nan_policy_summaries_and_percentiles() {
+    let records = with_nan_risk();
+    let (lo, hi) = (records[2].risk_score, records[0].risk_score);
+
+    let skip = stream_stats_with(&records, NanPolicy::Skip).unwrap().risk.unwrap();
+    assert_eq!((skip.min, skip.mean, skip.max), (lo, (lo + hi) / 2.0, hi));
+    let lowest = stream_stats_with(&records, NanPolicy::Lowest).unwrap().risk.unwrap();
+    assert!(lowest.min.is_nan() && lowest.max == hi && lowest.mean == skip.mean);
+    let highest = stream_stats_with(&records, NanPolicy::Highest).unwrap().risk.unwrap();
+    assert!(highest.max.is_nan() && highest.min == lo);
+
+    let values = [0.1, f64::NAN, 0.9];
+    assert_eq!(percentiles_with(&values, &[0.0, 100.0], NanPolicy::Skip).unwrap(), vec![(0.0, 0.1), (100.0, 0.9)]);
+    let p = percentiles_with(&values, &[0.0, 100.0], NanPolicy::Lowest).unwrap();
+    assert!(p[0].1.is_nan() && p[1].1 == 0.9);
+    let p = percentiles_with(&values, &[0.0, 100.0], NanPolicy::Highest).unwrap();
+    assert!(p[0].1 == 0.1 && p[1].1.is_nan());
+}
@@ -0,0 +1,90 @@
+// watch_tests.rs — Live Tailing Tests
+//
+// Tests cover:
+// - Incremental line delivery and partial-line buffering
+// - Truncation, replacement and late creation of the tailed file
+// - INV-T1 tracking across rotation
+
+use pmatrix_encoder::watch::*;
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+fn temp_path(name: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("pmatrix-watch-{}-{}", std::process::id(), name));
+    let _ = fs::remove_file(&path);
+    path
+}
+
+fn append(path: &PathBuf, text: &str) {
+    let mut f = OpenOptions::new().create(true).append(true).open(path).unwrap();
+    f.write_all(text.as_bytes()).unwrap();
+}
+
+fn line(s: &str) -> TailEvent {
+    TailEvent::Line(s.to_string())
+}
+
+// ============================================================
+// TailReader
+// ============================================================
+
+#[test]
+fn test_tail_incremental_and_partial_lines() {
+    let path = temp_path("incremental");
+    append(&path, "a\nb");
+    let mut tail = TailReader::new(&path);
+    assert_eq!(tail.poll().unwrap(), vec![line("a")]);
+    append(&path, "c\r\n\nd\n");
+    assert_eq!(tail.poll().unwrap(), vec![line("bc"), line("d")]);
+    assert!(tail.poll().unwrap().is_empty());
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_tail_truncation_restarts() {
+    let path = temp_path("truncate");
+    append(&path, "first\nsecond\n");
+    let mut tail = TailReader::new(&path);
+    assert_eq!(tail.poll().unwrap().len(), 2);
+    fs::write(&path, "new\n").unwrap();
+    assert_eq!(tail.poll().unwrap(), vec![TailEvent::Rotated, line("new")]);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_tail_waits_for_missing_file() {
+    let path = temp_path("missing");
+    let mut tail = TailReader::new(&path);
+    assert_eq!(tail.poll().unwrap(), vec![TailEvent::Waiting]);
+    assert!(tail.poll().unwrap().is_empty());
+    append(&path, "late\n");
+    assert_eq!(tail.poll().unwrap(), vec![line("late")]);
+
+    fs::remove_file(&path).unwrap();
+    assert_eq!(tail.poll().unwrap(), vec![TailEvent::Waiting]);
+    append(&path, "again\n");
+    assert_eq!(tail.poll().unwrap(), vec![TailEvent::Rotated, line("again")]);
+    fs::remove_file(&path).unwrap();
+}
+
+// ============================================================
+// T1Tracker
+// ============================================================
+
+#[test]
+fn test_t1_tracker_violation_and_reset() {
+    let mut t1 = T1Tracker::default();
+    assert_eq!(t1.observe(1000), T1Outcome::Ok);
+    assert_eq!(t1.observe(1000), T1Outcome::Ok);
+    assert_eq!(t1.observe(999), T1Outcome::Violation { previous: 1000 });
+    assert_eq!(t1.observe(1001), T1Outcome::Ok);
+
+    // Across rotation: continuing timestamps stay checked, restarts reset.
+    t1.rotated();
+    assert_eq!(t1.observe(1002), T1Outcome::Ok);
+    t1.rotated();
+    assert_eq!(t1.observe(10), T1Outcome::Reset { previous: 1002 });
+    assert_eq!(t1.observe(9), T1Outcome::Violation { previous: 10 });
+}